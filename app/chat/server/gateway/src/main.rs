@@ -1,19 +1,28 @@
 use bodhi_config::prelude::*;
 
-const PROFILE: &str = "dev";
-
 bodhi_config::service_config!();
 
 fn main() -> Result<()> {
-  let store: ServiceConfigStore = load_service_config_store(PROFILE)?;
+  let store: ServiceConfigStore = load_selected_service_config_store()?;
   let snapshot = store.snapshot();
-  bootstrap_gateway(snapshot.version(), snapshot.infra(), snapshot.service());
+  bootstrap_gateway(
+    snapshot.version(),
+    snapshot.content_hash(),
+    snapshot.infra(),
+    snapshot.service(),
+  );
 
   Ok(())
 }
 
-fn bootstrap_gateway(version: u64, infra: &InfraConfig, service: &ServiceConfig) {
+fn bootstrap_gateway(
+  version: u64,
+  content_hash: ContentHash,
+  infra: &InfraConfig,
+  service: &ServiceConfig,
+) {
   println!("config_version = {}", version);
+  println!("config_hash = {}", content_hash.short());
   init_gateway_infra(infra);
   init_gateway_server(infra, service);
 }
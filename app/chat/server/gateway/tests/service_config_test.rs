@@ -0,0 +1,62 @@
+#[allow(dead_code)]
+mod legacy {
+  bodhi_config::service_config!("gateway");
+
+  pub(crate) fn check() {
+    assert_eq!(SERVICE_NAME, "gateway");
+
+    let config = load_service_config("dev").unwrap();
+    let infra = load_infra_config("dev").unwrap();
+    let service = load_service_layer_config("dev").unwrap();
+    let store = load_service_config_store("dev").unwrap();
+    let snapshot = store.snapshot();
+
+    assert_eq!(config.service.name, infra.service.name);
+    assert_eq!(snapshot.infra().service.name, infra.service.name);
+    assert_eq!(
+      snapshot.service().server.http_port,
+      service.server.http_port
+    );
+    assert_eq!(store.profile(), "dev");
+  }
+}
+
+#[allow(dead_code)]
+mod args {
+  bodhi_config::service_config!(
+    service = "gateway",
+    config_dir = "config",
+    profile_env = "BODHI_GATEWAY_TEST_PROFILE",
+    default_profile = "dev"
+  );
+
+  pub(crate) fn check() {
+    assert_eq!(service_profile(), "dev");
+
+    let selected = load_selected_service_config_store().unwrap();
+    let explicit = service_config_store_builder("dev")
+      .unwrap()
+      .build()
+      .unwrap();
+
+    assert_eq!(selected.profile(), "dev");
+    assert_eq!(
+      selected.snapshot().content_hash(),
+      explicit.snapshot().content_hash()
+    );
+    assert_eq!(
+      load_selected_infra_config().unwrap().service.name,
+      load_infra_config("dev").unwrap().service.name
+    );
+  }
+}
+
+#[test]
+fn service_config_should_keep_profile_taking_loaders() {
+  legacy::check();
+}
+
+#[test]
+fn service_config_should_select_profile_for_selected_loaders() {
+  args::check();
+}
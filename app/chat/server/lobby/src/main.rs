@@ -1,19 +1,44 @@
 use bodhi_config::prelude::*;
 
-const PROFILE: &str = "dev";
-
-bodhi_config::service_config!();
+bodhi_config::service_config!(
+  service = "lobby",
+  profile_env = "BODHI_PROFILE",
+  default_profile = "dev"
+);
 
 fn main() -> Result<()> {
-  let store: ServiceConfigStore = load_service_config_store(PROFILE)?;
+  let store = selected_service_config_store_builder()?
+    .validator("matchmaking.max_rooms", validate_matchmaking)
+    .build()?;
   let snapshot = store.snapshot();
-  bootstrap_lobby(snapshot.version(), snapshot.infra(), snapshot.service());
+  bootstrap_lobby(
+    snapshot.version(),
+    snapshot.content_hash(),
+    snapshot.infra(),
+    snapshot.service(),
+  );
+
+  Ok(())
+}
+
+fn validate_matchmaking(snapshot: &ConfigSnapshot<InfraConfig, ServiceConfig>) -> Result<()> {
+  if snapshot.service().matchmaking.max_rooms == 0 {
+    return Err(
+      Error::new(CONFIGERR_VALIDATIONFAILED).wrap_context("matchmaking.max_rooms must be positive"),
+    );
+  }
 
   Ok(())
 }
 
-fn bootstrap_lobby(version: u64, infra: &InfraConfig, service: &ServiceConfig) {
+fn bootstrap_lobby(
+  version: u64,
+  content_hash: ContentHash,
+  infra: &InfraConfig,
+  service: &ServiceConfig,
+) {
   println!("config_version = {}", version);
+  println!("config_hash = {}", content_hash.short());
   init_lobby_infra(infra);
   init_matchmaking(infra, service);
 }
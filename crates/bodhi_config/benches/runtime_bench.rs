@@ -0,0 +1,151 @@
+use std::fs;
+use std::hint::black_box;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+
+use bodhi_config::prelude::*;
+use criterion::{Criterion, criterion_group, criterion_main};
+use serde::Deserialize;
+use tempfile::tempdir;
+
+#[derive(Debug, Deserialize)]
+struct InfraConfig {
+  log: LogConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct LogConfig {
+  level: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServiceConfig {
+  server: ServerConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServerConfig {
+  http_port: u16,
+}
+
+type Store = ConfigStore<InfraConfig, ServiceConfig>;
+type Snapshot = ConfigSnapshot<InfraConfig, ServiceConfig>;
+
+/// 旧版存储设计：每次读取都获取读锁并克隆 `Arc`
+struct RwLockStore {
+  state: RwLock<Arc<Snapshot>>,
+}
+
+impl RwLockStore {
+  fn snapshot(&self) -> Arc<Snapshot> {
+    Arc::clone(&self.state.read().unwrap_or_else(|err| err.into_inner()))
+  }
+}
+
+fn snapshot_read(c: &mut Criterion) {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  write_bench_config(&config_dir);
+
+  let store = Arc::new(Store::load_from(&config_dir, "dev", "gateway").expect("load config store"));
+  let rwlock_store = Arc::new(RwLockStore {
+    state: RwLock::new(store.snapshot()),
+  });
+
+  let mut group = c.benchmark_group("snapshot_read");
+  group.bench_function("rwlock_arc_clone", |b| {
+    b.iter(|| black_box(rwlock_store.snapshot().service().server.http_port))
+  });
+  group.bench_function("arc_swap_load_full", |b| {
+    b.iter(|| black_box(store.snapshot().service().server.http_port))
+  });
+  group.bench_function("arc_swap_guard", |b| {
+    b.iter(|| black_box(store.snapshot_guard().service().server.http_port))
+  });
+  group.bench_function("cached_handle", |b| {
+    let mut handle = store.handle();
+    b.iter(|| black_box(handle.get().service().server.http_port))
+  });
+  group.finish();
+
+  let mut group = c.benchmark_group("snapshot_read_contended");
+  group.bench_function("rwlock_arc_clone", |b| {
+    let background = Arc::clone(&rwlock_store);
+    with_background_readers(
+      move || black_box(background.snapshot().infra().log.level.len()),
+      || b.iter(|| black_box(rwlock_store.snapshot().service().server.http_port)),
+    )
+  });
+  group.bench_function("arc_swap_load_full", |b| {
+    let background = Arc::clone(&store);
+    with_background_readers(
+      move || black_box(background.snapshot().infra().log.level.len()),
+      || b.iter(|| black_box(store.snapshot().service().server.http_port)),
+    )
+  });
+  group.bench_function("arc_swap_guard", |b| {
+    let background = Arc::clone(&store);
+    with_background_readers(
+      move || black_box(background.snapshot_guard().infra().log.level.len()),
+      || b.iter(|| black_box(store.snapshot_guard().service().server.http_port)),
+    )
+  });
+  group.bench_function("cached_handle", |b| {
+    let background = Arc::clone(&store);
+    let mut handle = store.handle();
+    with_background_readers(
+      move || black_box(background.snapshot().infra().log.level.len()),
+      || b.iter(|| black_box(handle.get().service().server.http_port)),
+    )
+  });
+  group.finish();
+}
+
+fn with_background_readers<R, F>(read: R, measure: F)
+where
+  R: Fn() -> usize + Clone + Send + 'static,
+  F: FnOnce(),
+{
+  let stop = Arc::new(AtomicBool::new(false));
+  let readers: Vec<_> = (0..3)
+    .map(|_| {
+      let stop = Arc::clone(&stop);
+      let read = read.clone();
+      thread::spawn(move || {
+        while !stop.load(Ordering::Relaxed) {
+          read();
+        }
+      })
+    })
+    .collect();
+
+  measure();
+
+  stop.store(true, Ordering::Relaxed);
+  for reader in readers {
+    reader.join().expect("background reader should finish");
+  }
+}
+
+fn write_bench_config(config_dir: &Path) {
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    "[server]\nhttp_port = 8080\n",
+  )
+  .expect("write gateway template");
+  fs::write(config_dir.join("profile/dev.toml"), "").expect("write dev profile");
+}
+
+criterion_group!(benches, snapshot_read);
+criterion_main!(benches);
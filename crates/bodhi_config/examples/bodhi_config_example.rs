@@ -7,7 +7,7 @@ use generated_gateway_config::Config;
 
 fn main() -> Result<()> {
   let engine = ConfigEngine::new("config")?;
-  let rust_output_path = engine.default_rust_output_path("dev", "gateway");
+  let rust_output_path = engine.default_rust_output_path("dev", "gateway")?;
   engine.generate_rust_types("dev", "gateway", &rust_output_path)?;
 
   let config = engine.resolve("dev", "gateway")?;
@@ -2,8 +2,9 @@ use std::fmt::Write as _;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use bodhi_config::codegen::write_rust_types;
+use bodhi_config::codegen::{rust_output_file_name, write_rust_types};
 use bodhi_config::prelude::*;
+use bodhi_config::resolve::service_target;
 use clap::{Parser, Subcommand, ValueEnum};
 use serde::Serialize;
 
@@ -13,6 +14,15 @@ use serde::Serialize;
 struct Cli {
   #[arg(long, default_value = "config")]
   config_dir: PathBuf,
+  /// 从指定格式的预生成产物读取配置，而非模板和 profile
+  #[arg(long, global = true)]
+  products: Option<String>,
+  /// 临时覆盖单个值，形如 `services.gateway.server.http_port=9999`，可重复
+  #[arg(long = "set", global = true)]
+  sets: Vec<String>,
+  /// 临时覆盖文件，结构与 profile 相同，可重复，先于 `--set` 合并
+  #[arg(long = "overlay", global = true)]
+  overlays: Vec<PathBuf>,
   #[command(subcommand)]
   command: Command,
 }
@@ -36,30 +46,70 @@ enum Command {
   },
   /// 生成配置产物
   Gen {
+    /// profile，可用逗号叠加多个，例如 `dev,stanley`，从左到右合并
     #[arg(long)]
     profile: String,
     #[arg(long)]
     service: Option<String>,
+    /// 只生成指定服务实例的产物
+    #[arg(long, requires = "service")]
+    instance: Option<String>,
+    /// profile 以外的维度取值，形如 `region=eu`，可重复
+    #[arg(long = "dim")]
+    dims: Vec<String>,
     #[arg(long = "format")]
     formats: Vec<String>,
   },
+  /// 解析 profile 启用的全部服务及实例并执行配置校验
+  Check {
+    /// profile，可用逗号叠加多个，例如 `dev,stanley`，从左到右合并
+    #[arg(long)]
+    profile: String,
+    /// profile 以外的维度取值，形如 `region=eu`，可重复
+    #[arg(long = "dim")]
+    dims: Vec<String>,
+  },
   /// 展示最终合并后的配置
   Show {
+    /// profile，可用逗号叠加多个，例如 `dev,stanley`，从左到右合并
     #[arg(long)]
     profile: String,
     #[arg(long)]
     service: String,
+    /// 展示 profile 中声明的服务实例
+    #[arg(long)]
+    instance: Option<String>,
+    /// profile 以外的维度取值，形如 `region=eu`，可重复
+    #[arg(long = "dim")]
+    dims: Vec<String>,
     #[arg(long, default_value = "toml")]
     format: String,
   },
+  /// 展示最终配置中每个叶子值的来源层
+  Explain {
+    /// profile，可用逗号叠加多个，例如 `dev,stanley`，从左到右合并
+    #[arg(long)]
+    profile: String,
+    #[arg(long)]
+    service: String,
+    #[arg(long)]
+    instance: Option<String>,
+    /// profile 以外的维度取值，形如 `region=eu`，可重复
+    #[arg(long = "dim")]
+    dims: Vec<String>,
+  },
   /// 生成 Rust 配置结构定义文件
   GenRust {
+    /// profile，可用逗号叠加多个，例如 `dev,stanley`，从左到右合并
     #[arg(long)]
     profile: String,
     #[arg(long, conflicts_with = "service_prefix")]
     service: Option<String>,
     #[arg(long, conflicts_with = "service")]
     service_prefix: Option<String>,
+    /// 按 profile 中声明的服务实例生成
+    #[arg(long, requires = "service")]
+    instance: Option<String>,
     #[arg(long)]
     output: Option<PathBuf>,
     #[arg(long)]
@@ -70,6 +120,26 @@ enum Command {
     report_output: Option<PathBuf>,
     #[arg(long, default_value = "Config")]
     root_struct: String,
+    /// 同时生成以 profile 解析结果为字面量的 `CONFIG` 静态实例
+    #[arg(long)]
+    with_values: bool,
+    /// 同时按约束文件为各结构生成 `validate()` 方法
+    #[arg(long)]
+    with_validate: bool,
+    /// 基础模板结构所在的模块路径，例如 `crate::base_server`，继承的配置段复用其中的结构
+    #[arg(long, conflicts_with = "with_values")]
+    base_types: Option<String>,
+  },
+  /// 生成基础模板的 Rust 配置结构定义文件
+  GenBaseRust {
+    /// `template/base/` 下的基础模板名
+    #[arg(long)]
+    base: String,
+    #[arg(long)]
+    output: PathBuf,
+    /// 同时按约束文件为各结构生成 `validate()` 方法
+    #[arg(long)]
+    with_validate: bool,
   },
 }
 
@@ -96,7 +166,13 @@ struct GenRustRuleReport {
 
 fn main() -> Result<()> {
   let cli = Cli::parse();
-  let engine = ConfigEngine::new(&cli.config_dir)?;
+  let mut engine = ConfigEngine::new(&cli.config_dir)?;
+  if let Some(format) = cli.products.as_deref() {
+    engine = engine.with_products(format.parse()?);
+  }
+  if !cli.sets.is_empty() || !cli.overlays.is_empty() {
+    engine = engine.with_overrides(parse_overrides(&cli.sets, &cli.overlays)?);
+  }
 
   match cli.command {
     Command::List => {
@@ -106,9 +182,33 @@ fn main() -> Result<()> {
       }
 
       println!("services:");
-      for service in engine.services()? {
+      let services = engine.services()?;
+      for service in &services {
         println!("  {service}");
       }
+
+      println!("dimensions:");
+      for dimension in engine.dimensions()? {
+        let values = engine.dimension_values(&dimension.name)?;
+        println!("  {} [{}]", dimension.name, values.join(", "));
+      }
+
+      println!("enabled services:");
+      for profile in engine.profiles()? {
+        println!(
+          "  {profile} [{}]",
+          engine.enabled_services(&profile)?.join(", ")
+        );
+      }
+
+      println!("instances:");
+      for profile in engine.profiles()? {
+        for service in &engine.enabled_services(&profile)? {
+          for instance in engine.instances(&profile, service)? {
+            println!("  {profile} {}", service_target(service, &instance));
+          }
+        }
+      }
     }
     Command::GenProject {
       rust_output,
@@ -133,6 +233,7 @@ fn main() -> Result<()> {
         let options = RustCodegenOptions {
           root_struct_name: root_struct,
           type_overrides: type_overrides.clone(),
+          ..RustCodegenOptions::default()
         };
         let mut generated = Vec::new();
 
@@ -170,6 +271,8 @@ fn main() -> Result<()> {
     Command::Gen {
       profile,
       service,
+      instance,
+      dims,
       formats,
     } => {
       let formats = if formats.is_empty() {
@@ -182,34 +285,61 @@ fn main() -> Result<()> {
         parsed
       };
 
+      let dimensions = parse_dimensions(&profile, &dims)?;
       if let Some(service) = service {
-        engine.generate_service(&profile, &service, &formats)?;
+        let target = target_name(&service, instance.as_deref());
+        engine.generate_service_with(&dimensions, &target, &formats)?;
       } else {
-        engine.generate(&profile, &formats)?;
+        engine.generate_with(&dimensions, &formats)?;
       }
     }
+    Command::Check { profile, dims } => {
+      let dimensions = parse_dimensions(&profile, &dims)?;
+      engine.check_rules_with(&dimensions)?;
+      println!("config check passed for {}", dimensions.label());
+    }
     Command::Show {
       profile,
       service,
+      instance,
+      dims,
       format,
     } => {
       let format: OutputFormat = format.parse()?;
-      let resolved = engine.resolve(&profile, &service)?;
+      let dimensions = parse_dimensions(&profile, &dims)?;
+      let target = target_name(&service, instance.as_deref());
+      let resolved = engine.resolve_with(&dimensions, &target)?;
       let content = resolved.to_format(format)?;
       print!("{content}");
       if !content.ends_with('\n') {
         println!();
       }
     }
+    Command::Explain {
+      profile,
+      service,
+      instance,
+      dims,
+    } => {
+      let dimensions = parse_dimensions(&profile, &dims)?;
+      let target = target_name(&service, instance.as_deref());
+      for (path, layer) in engine.provenance(&dimensions, &target)?.merged() {
+        println!("{path} <- {layer}");
+      }
+    }
     Command::GenRust {
       profile,
       service,
       service_prefix,
+      instance,
       output,
       type_rules,
       report_format,
       report_output,
       root_struct,
+      with_values,
+      with_validate,
+      base_types,
     } => {
       let type_overrides = if let Some(type_rules) = type_rules.as_ref() {
         TypeOverrideRules::from_file(type_rules)?
@@ -220,22 +350,28 @@ fn main() -> Result<()> {
       let options = RustCodegenOptions {
         root_struct_name: root_struct,
         type_overrides: type_overrides.clone(),
+        with_values,
+        with_validate,
+        base_types,
+        ..RustCodegenOptions::default()
       };
       let show_rule_report = type_rules.is_some();
       let mut generated = Vec::new();
 
       if let Some(service) = service {
-        let output = output.unwrap_or_else(|| engine.default_rust_output_path(&profile, &service));
+        let service = target_name(&service, instance.as_deref());
+        let output =
+          output.map_or_else(|| engine.default_rust_output_path(&profile, &service), Ok)?;
         generated.push(generate_service_report(
           &engine, &profile, &service, output, &options,
         )?);
       } else if let Some(service_prefix) = service_prefix {
-        let output_dir = output.unwrap_or_else(|| engine.default_rust_output_dir(&profile));
+        let output_dir = output.map_or_else(|| engine.default_rust_output_dir(&profile), Ok)?;
         ensure_batch_output_dir(&output_dir)?;
 
         let services = engine.services_with_prefix(&service_prefix)?;
         for service in services {
-          let output_path = output_dir.join(format!("{}_config.rs", service));
+          let output_path = output_dir.join(rust_output_file_name(&service));
           generated.push(generate_service_report(
             &engine,
             &profile,
@@ -245,11 +381,11 @@ fn main() -> Result<()> {
           )?);
         }
       } else {
-        let output_dir = output.unwrap_or_else(|| engine.default_rust_output_dir(&profile));
+        let output_dir = output.map_or_else(|| engine.default_rust_output_dir(&profile), Ok)?;
         ensure_batch_output_dir(&output_dir)?;
 
         for service in engine.services()? {
-          let output_path = output_dir.join(format!("{}_config.rs", service));
+          let output_path = output_dir.join(rust_output_file_name(&service));
           generated.push(generate_service_report(
             &engine,
             &profile,
@@ -278,11 +414,59 @@ fn main() -> Result<()> {
         }
       }
     }
+    Command::GenBaseRust {
+      base,
+      output,
+      with_validate,
+    } => {
+      let options = RustCodegenOptions {
+        with_validate,
+        ..RustCodegenOptions::default()
+      };
+      let content = engine.render_base_rust_types_with(&base, &options)?;
+      write_rust_types(&output, &content)?;
+      println!("generated {}", output.display());
+    }
   }
 
   Ok(())
 }
 
+fn parse_dimensions(profile: &str, dims: &[String]) -> Result<Dimensions> {
+  let mut selector = profile.to_string();
+  for dim in dims {
+    // 不带 `=` 的部分会被当作叠加的 profile，这里要求显式写出维度名
+    if !dim.contains('=') {
+      return Err(
+        Error::new(CONFIGERR_INVALIDSTRUCTURE)
+          .wrap_context("dimension must be written as name=value")
+          .wrap_context_with(|| format!("dim={dim}")),
+      );
+    }
+    selector.push(',');
+    selector.push_str(dim);
+  }
+  selector.parse()
+}
+
+fn parse_overrides(sets: &[String], overlays: &[PathBuf]) -> Result<Overrides> {
+  let mut overrides = Overrides::new();
+  for overlay in overlays {
+    overrides.merge_file(overlay)?;
+  }
+  for set in sets {
+    overrides.set_assignment(set)?;
+  }
+  Ok(overrides)
+}
+
+fn target_name(service: &str, instance: Option<&str>) -> String {
+  match instance {
+    Some(instance) => service_target(service, instance),
+    None => service.to_string(),
+  }
+}
+
 fn ensure_batch_output_dir(output_dir: &Path) -> Result<()> {
   if output_dir.extension().is_some() {
     return Err(
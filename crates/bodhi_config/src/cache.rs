@@ -0,0 +1,132 @@
+//! 最近一次有效配置的持久化缓存模块
+
+use std::fs;
+use std::path::Path;
+
+use bodhi_error::prelude::*;
+use serde::{Deserialize, Serialize};
+use toml::Value;
+
+use crate::engine::ResolvedLayers;
+use crate::errcode::configerr::*;
+use crate::hash::ContentHash;
+
+/// 缓存文件格式版本，格式不兼容时递增
+pub const CACHE_FORMAT_VERSION: u32 = 1;
+
+/// 缓存文件元数据
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CacheMeta {
+  pub format_version: u32,
+  /// 维度标签，例如 `prod+stanley,region=eu`
+  pub label: String,
+  pub service: String,
+  pub content_hash: String,
+}
+
+#[derive(Deserialize, Serialize)]
+struct CacheFile {
+  meta: CacheMeta,
+  infra: Value,
+  service: Value,
+}
+
+/// 将分层配置写入缓存文件
+///
+/// 先写临时文件再重命名，避免进程中断时留下半截缓存。
+pub fn write_cache(path: &Path, label: &str, service: &str, layers: &ResolvedLayers) -> Result<()> {
+  let cache = CacheFile {
+    meta: CacheMeta {
+      format_version: CACHE_FORMAT_VERSION,
+      label: label.to_string(),
+      service: service.to_string(),
+      content_hash: layers.content_hash().to_hex(),
+    },
+    infra: layers.infra().clone(),
+    service: layers.service().clone(),
+  };
+
+  let content = toml::to_string_pretty(&cache)
+    .map_err(Error::from_std)
+    .wrap_context("serialize config cache failed")
+    .wrap_context_with(|| format!("path={}", path.display()))?;
+
+  if let Some(parent) = path.parent()
+    && !parent.as_os_str().is_empty()
+  {
+    fs::create_dir_all(parent)
+      .map_err(Error::from_std)
+      .wrap_context("create config cache directory failed")
+      .wrap_context_with(|| format!("dir={}", parent.display()))?;
+  }
+
+  let temp_path = path.with_extension("tmp");
+  fs::write(&temp_path, content)
+    .map_err(Error::from_std)
+    .wrap_context("write config cache failed")
+    .wrap_context_with(|| format!("path={}", temp_path.display()))?;
+  fs::rename(&temp_path, path)
+    .map_err(Error::from_std)
+    .wrap_context("replace config cache failed")
+    .wrap_context_with(|| format!("path={}", path.display()))
+}
+
+/// 读取并校验缓存文件
+///
+/// 格式版本、维度标签、service 或内容哈希任一不匹配时拒绝使用缓存。
+pub fn read_cache(path: &Path, label: &str, service: &str) -> Result<ResolvedLayers> {
+  let content = fs::read_to_string(path)
+    .map_err(Error::from_std)
+    .wrap_context("read config cache failed")
+    .wrap_context_with(|| format!("path={}", path.display()))?;
+
+  let cache: CacheFile = toml::from_str(&content)
+    .map_err(|err| Error::new(CONFIGERR_CACHECORRUPTED).wrap_context(err.to_string()))
+    .wrap_context("parse config cache failed")
+    .wrap_context_with(|| format!("path={}", path.display()))?;
+
+  let meta = &cache.meta;
+  if meta.format_version != CACHE_FORMAT_VERSION {
+    return Err(cache_corrupted(
+      path,
+      format!(
+        "format_version={} expected={CACHE_FORMAT_VERSION}",
+        meta.format_version
+      ),
+    ));
+  }
+
+  if meta.label != label || meta.service != service {
+    return Err(cache_corrupted(
+      path,
+      format!(
+        "cached label={} service={} requested label={label} service={service}",
+        meta.label, meta.service
+      ),
+    ));
+  }
+
+  let expected: ContentHash = meta
+    .content_hash
+    .parse()
+    .map_err(|_| cache_corrupted(path, format!("content_hash={}", meta.content_hash)))?;
+  let layers = ResolvedLayers::new(cache.infra, cache.service)?;
+  if layers.content_hash() != expected {
+    return Err(cache_corrupted(
+      path,
+      format!(
+        "content_hash={} actual={}",
+        meta.content_hash,
+        layers.content_hash()
+      ),
+    ));
+  }
+
+  Ok(layers)
+}
+
+fn cache_corrupted(path: &Path, detail: String) -> Error {
+  Error::new(CONFIGERR_CACHECORRUPTED)
+    .wrap_context("config cache integrity check failed")
+    .wrap_context_with(|| format!("path={} {detail}", path.display()))
+}
@@ -9,11 +9,17 @@ use serde::{Deserialize, Serialize};
 use syn::Type;
 use toml::Value;
 
+use crate::constraint::{Constraint, ConstraintSet, Constraints, ValueFormat};
 use crate::errcode::configerr::*;
+use crate::merge::{OPTIONAL_KEY, UNSET_KEY, is_marker_key};
+use crate::validate::{
+  FieldMarker, MAP_KEY, is_field_marker, is_optional_field, schema_child, value_kind,
+};
 
 const MERGED_MODULE_NAME: &str = "merged";
 const INFRA_MODULE_NAME: &str = "infra";
 const SERVICE_MODULE_NAME: &str = "service";
+const VALUE_STATIC_NAME: &str = "CONFIG";
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub struct RustCodegenResult {
@@ -68,6 +74,19 @@ struct ResolvedTypeOverride {
 pub struct RustCodegenOptions {
   pub root_struct_name: String,
   pub type_overrides: TypeOverrideRules,
+  /// 同时生成以解析结果为字面量的 `CONFIG` 静态实例
+  pub with_values: bool,
+  /// 同时按约束文件为各结构生成 `validate()` 方法
+  pub with_validate: bool,
+  /// 生成 `validate()` 使用的约束，由引擎按服务加载
+  pub constraints: Constraints,
+  /// 基础模板结构所在的 Rust 模块路径，例如 `crate::base_server`
+  ///
+  /// 设置后，service 原样继承自基础模板的配置段直接引用该模块中由
+  /// `render_base_rust_types` 生成的结构，不再各自生成一份。
+  pub base_types: Option<String>,
+  /// 复用的配置段路径及其结构类型，由引擎按 `extends` 链计算
+  pub shared_types: BTreeMap<String, String>,
 }
 
 impl Default for RustCodegenOptions {
@@ -75,6 +94,11 @@ impl Default for RustCodegenOptions {
     Self {
       root_struct_name: String::from("Config"),
       type_overrides: TypeOverrideRules::default(),
+      with_values: false,
+      with_validate: false,
+      constraints: Constraints::default(),
+      base_types: None,
+      shared_types: BTreeMap::new(),
     }
   }
 }
@@ -228,11 +252,30 @@ pub fn render_rust_types_report(
   let root_struct_name = sanitize_type_name(&options.root_struct_name);
   let mut generator = Generator {
     type_overrides: options.type_overrides.clone(),
+    shared_types: options.shared_types.clone(),
     ..Default::default()
   };
   generator.used_struct_names.insert(root_struct_name.clone());
   generator.visit_table(root_struct_name.clone(), &[], root_table)?;
-  let content = generator.render(&root_struct_name);
+  let mut content = generator.render(&root_struct_name);
+  if options.with_validate {
+    content.push('\n');
+    content.push_str(&render_validate_impls(
+      &generator.definitions,
+      &options.constraints.merged(),
+      &options.shared_types,
+      "",
+    )?);
+  }
+  if options.with_values {
+    content.push('\n');
+    content.push_str(&render_value_static(
+      &root_struct_name,
+      &generator.definitions,
+      root_table,
+      "",
+    )?);
+  }
   let matched_rules = generator.matched_rules;
   let unused_rules = options.type_overrides.find_unused_rules(&matched_rules);
 
@@ -253,7 +296,40 @@ pub fn render_layered_rust_types_report(
   let infra_module = generate_module(infra, options, "Config")?;
   let service_module = generate_module(service, options, "Config")?;
 
-  let content = render_layered_modules(&merged_module, &infra_module, &service_module);
+  let values = if options.with_values || options.with_validate {
+    let tail = |module: &GeneratedModule, value: &Value, constraints: &ConstraintSet| {
+      let mut tail = String::new();
+      if options.with_validate {
+        tail.push_str(&render_validate_impls(
+          &module.definitions,
+          constraints,
+          &options.shared_types,
+          "  ",
+        )?);
+      }
+      if options.with_values {
+        if !tail.is_empty() {
+          tail.push('\n');
+        }
+        tail.push_str(&render_module_values(module, value)?);
+      }
+      Ok::<_, Error>(tail)
+    };
+    Some(LayeredValues {
+      merged: tail(&merged_module, merged, &options.constraints.merged())?,
+      infra: tail(&infra_module, infra, &options.constraints.infra)?,
+      service: tail(&service_module, service, &options.constraints.service)?,
+      with_static: options.with_values,
+    })
+  } else {
+    None
+  };
+  let content = render_layered_modules(
+    &merged_module,
+    &infra_module,
+    &service_module,
+    values.as_ref(),
+  );
   let matched_rules = unique_hits(
     merged_module
       .matched_rules
@@ -270,6 +346,21 @@ pub fn render_layered_rust_types_report(
   })
 }
 
+/// 解析目标对应的默认 Rust 结构文件名，实例分隔符等非标识符字符替换为 `_`
+pub fn rust_output_file_name(target: &str) -> String {
+  let stem: String = target
+    .chars()
+    .map(|ch| {
+      if ch.is_ascii_alphanumeric() || ch == '_' {
+        ch
+      } else {
+        '_'
+      }
+    })
+    .collect();
+  format!("{stem}_config.rs")
+}
+
 pub fn write_rust_types(output_path: &Path, content: &str) -> Result<()> {
   if let Some(parent) = output_path.parent() {
     fs::create_dir_all(parent)
@@ -284,11 +375,108 @@ pub fn write_rust_types(output_path: &Path, content: &str) -> Result<()> {
     .wrap_context_with(|| format!("path={}", output_path.display()))
 }
 
+/// 计算 service 中与基础模板结构一致的配置段及其在 `base_types` 模块中的结构
+///
+/// `base` 为合并后的基础模板，`service` 为合并了基础模板的 service 模板；service 只改默认值的
+/// 配置段复用基础模板的结构，增改了字段的不复用。结构名与 `render_rust_types` 为基础模板生成的一致。
+pub fn inherited_section_types(
+  base: &Value,
+  service: &Value,
+  options: &RustCodegenOptions,
+) -> Result<BTreeMap<String, String>> {
+  let Some(base_types) = options.base_types.as_deref() else {
+    return Ok(BTreeMap::new());
+  };
+  let shape_options = RustCodegenOptions {
+    type_overrides: options.type_overrides.clone(),
+    ..RustCodegenOptions::default()
+  };
+  let root_name = &shape_options.root_struct_name;
+  let base_module = generate_module(base, &shape_options, root_name)?;
+  let service_module = generate_module(service, &shape_options, root_name)?;
+  let base_definitions = definitions_by_name(&base_module);
+  let service_definitions = definitions_by_name(&service_module);
+  let (Some(base_root), Some(service_root)) = (
+    base_definitions.get(base_module.root_struct_name.as_str()),
+    service_definitions.get(service_module.root_struct_name.as_str()),
+  ) else {
+    return Ok(BTreeMap::new());
+  };
+
+  let mut shared_types = BTreeMap::new();
+  for field in &base_root.fields {
+    if !base_definitions.contains_key(field.ty.as_str()) {
+      continue;
+    }
+    let Some(service_field) = service_root
+      .fields
+      .iter()
+      .find(|service_field| field_key(service_field) == field_key(field))
+    else {
+      continue;
+    };
+    if type_shape(&base_definitions, &field.ty)
+      == type_shape(&service_definitions, &service_field.ty)
+    {
+      shared_types.insert(
+        field_key(field).to_string(),
+        format!("{base_types}::{}", field.ty),
+      );
+    }
+  }
+  Ok(shared_types)
+}
+
+fn definitions_by_name(module: &GeneratedModule) -> BTreeMap<&str, &StructDefinition> {
+  module
+    .definitions
+    .iter()
+    .map(|definition| (definition.name.as_str(), definition))
+    .collect()
+}
+
+/// 类型的形状，其中的结构名展开为字段，用于比较两次生成的结构是否一致
+fn type_shape(definitions: &BTreeMap<&str, &StructDefinition>, ty: &str) -> String {
+  let mut shape = String::new();
+  let mut ident = String::new();
+  let flush = |ident: &mut String, shape: &mut String| {
+    match definitions.get(ident.as_str()) {
+      Some(definition) => {
+        let fields: Vec<_> = definition
+          .fields
+          .iter()
+          .map(|field| {
+            format!(
+              "{}:{}",
+              field_key(field),
+              type_shape(definitions, &field.ty)
+            )
+          })
+          .collect();
+        shape.push_str(&format!("{{{}}}", fields.join(",")));
+      }
+      None => shape.push_str(ident),
+    }
+    ident.clear();
+  };
+  for ch in ty.chars() {
+    if ch.is_alphanumeric() || ch == '_' {
+      ident.push(ch);
+    } else {
+      flush(&mut ident, &mut shape);
+      shape.push(ch);
+    }
+  }
+  flush(&mut ident, &mut shape);
+  shape
+}
+
 #[derive(Debug, Default)]
 struct Generator {
   definitions: Vec<StructDefinition>,
   matched_rules: Vec<TypeOverrideHit>,
   type_overrides: TypeOverrideRules,
+  shared_types: BTreeMap<String, String>,
   used_struct_names: BTreeSet<String>,
 }
 
@@ -300,7 +488,11 @@ impl Generator {
     table: &toml::map::Map<String, Value>,
   ) -> Result<()> {
     let mut fields = Vec::new();
-    let mut keys: Vec<_> = table.keys().cloned().collect();
+    let mut keys: Vec<_> = table
+      .keys()
+      .filter(|key| !is_marker_key(key))
+      .cloned()
+      .collect();
     keys.sort();
 
     for key in keys {
@@ -310,6 +502,8 @@ impl Generator {
       let field_path = join_segments(path, &key);
 
       let field_type = match overridden_type {
+        // 继承自基础模板的配置段引用基础模板的结构
+        _ if self.shared_types.contains_key(&field_path) => self.shared_types[&field_path].clone(),
         Some(override_hit) => {
           self.matched_rules.push(TypeOverrideHit {
             field_path,
@@ -320,6 +514,13 @@ impl Generator {
           override_hit.rust_type
         }
         None => match value {
+          // 没有默认值的字段按标记声明的类型生成
+          _ if is_field_marker(value) => FieldMarker::of(value)
+            .map(|marker| marker.rust_type().to_string())
+            .unwrap_or_else(|| scalar_type(&key, value)),
+          Value::Table(child_table) if child_table.contains_key(MAP_KEY) => {
+            self.map_type(path, &key, &child_table[MAP_KEY])?
+          }
           Value::Table(child_table) => {
             let child_struct_name = self.allocate_struct_name(path, &key, "Config");
             let mut child_path = path.to_vec();
@@ -332,6 +533,13 @@ impl Generator {
         },
       };
 
+      // 模板声明为可选的字段可能被覆盖层删除
+      let field_type = if is_optional_field(table, &key) {
+        format!("Option<{field_type}>")
+      } else {
+        field_type
+      };
+
       fields.push(FieldDefinition {
         name: field_name,
         rename,
@@ -341,12 +549,30 @@ impl Generator {
 
     self.definitions.push(StructDefinition {
       name: struct_name,
+      path: path.to_vec(),
       fields,
     });
 
     Ok(())
   }
 
+  /// 映射表生成 `BTreeMap<String, T>`，`T` 由 `"*"` 声明的值结构推断
+  fn map_type(&mut self, path: &[String], key: &str, value_schema: &Value) -> Result<String> {
+    let item_type = match value_schema {
+      Value::Table(child_table) => {
+        let child_struct_name = self.allocate_struct_name(path, key, "Item");
+        let mut child_path = path.to_vec();
+        child_path.push(key.to_string());
+        child_path.push(MAP_KEY.to_string());
+        self.visit_table(child_struct_name.clone(), &child_path, child_table)?;
+        child_struct_name
+      }
+      Value::Array(items) => self.array_type(path, key, items)?,
+      _ => scalar_type(key, value_schema),
+    };
+    Ok(format!("std::collections::BTreeMap<String, {item_type}>"))
+  }
+
   fn array_type(&mut self, path: &[String], key: &str, items: &[Value]) -> Result<String> {
     if items.is_empty() {
       return Ok(String::from("Vec<bodhi_config::toml::Value>"));
@@ -439,6 +665,8 @@ struct GeneratedModule {
 #[derive(Clone, Debug)]
 struct StructDefinition {
   name: String,
+  /// 结构在模板中的路径，映射表条目以 `*` 表示
+  path: Vec<String>,
   fields: Vec<FieldDefinition>,
 }
 
@@ -462,6 +690,7 @@ fn generate_module(
   let root_struct_name = sanitize_type_name(root_struct_name);
   let mut generator = Generator {
     type_overrides: options.type_overrides.clone(),
+    shared_types: options.shared_types.clone(),
     ..Default::default()
   };
   generator.used_struct_names.insert(root_struct_name.clone());
@@ -474,27 +703,658 @@ fn generate_module(
   })
 }
 
+/// 各模块结构定义之后追加的代码，包括 `validate()` 方法和 `CONFIG` 静态实例
+#[derive(Debug)]
+struct LayeredValues {
+  merged: String,
+  infra: String,
+  service: String,
+  /// 是否包含 `CONFIG` 静态实例
+  with_static: bool,
+}
+
 fn render_layered_modules(
   merged: &GeneratedModule,
   infra: &GeneratedModule,
   service: &GeneratedModule,
+  values: Option<&LayeredValues>,
 ) -> String {
   let mut output = String::from("use serde::Deserialize;\n\n");
-  output.push_str(&render_module(MERGED_MODULE_NAME, merged));
+  output.push_str(&render_module(
+    MERGED_MODULE_NAME,
+    merged,
+    values.map(|values| values.merged.as_str()),
+  ));
   output.push('\n');
   output.push('\n');
-  output.push_str(&render_module(INFRA_MODULE_NAME, infra));
+  output.push_str(&render_module(
+    INFRA_MODULE_NAME,
+    infra,
+    values.map(|values| values.infra.as_str()),
+  ));
   output.push('\n');
   output.push('\n');
-  output.push_str(&render_module(SERVICE_MODULE_NAME, service));
+  output.push_str(&render_module(
+    SERVICE_MODULE_NAME,
+    service,
+    values.map(|values| values.service.as_str()),
+  ));
   output.push('\n');
   output.push('\n');
   output.push_str("pub use merged::Config;\n");
+  if values.is_some_and(|values| values.with_static) {
+    output.push_str(&format!("pub use merged::{VALUE_STATIC_NAME};\n"));
+  }
 
   output
 }
 
-fn render_module(module_name: &str, module: &GeneratedModule) -> String {
+fn render_module_values(module: &GeneratedModule, value: &Value) -> Result<String> {
+  let table = value
+    .as_table()
+    .ok_or_else(|| Error::new(CONFIGERR_INVALIDSTRUCTURE))
+    .wrap_context("resolved config root must be a table")?;
+  render_value_static(&module.root_struct_name, &module.definitions, table, "  ")
+}
+
+/// 按约束为各结构渲染 `validate()` 方法，违反项文本与解析时的约束检查一致
+fn render_validate_impls(
+  definitions: &[StructDefinition],
+  constraints: &ConstraintSet,
+  shared_types: &BTreeMap<String, String>,
+  indent: &str,
+) -> Result<String> {
+  let struct_names: BTreeSet<_> = definitions
+    .iter()
+    .map(|definition| definition.name.as_str())
+    .chain(shared_types.values().map(String::as_str))
+    .collect();
+  let mut definitions: Vec<_> = definitions.iter().collect();
+  definitions.sort_by(|left, right| left.name.cmp(&right.name));
+
+  let mut output = String::new();
+  for (index, definition) in definitions.iter().enumerate() {
+    if index > 0 {
+      output.push('\n');
+    }
+    let body = format!("{indent}    ");
+    output.push_str(&format!("{indent}impl {} {{\n", definition.name));
+    output.push_str(&format!(
+      "{indent}  /// 校验约束文件声明的约束，返回全部违反项\n\
+       {indent}  pub fn validate(&self) -> Vec<String> {{\n\
+       {body}let mut violations = Vec::new();\n\
+       {body}self.validate_at(\"\", &mut violations);\n\
+       {body}violations\n\
+       {indent}  }}\n\n"
+    ));
+    output.push_str(&format!(
+      "{indent}  /// 以 `path` 为前缀校验约束，违反项追加到 `violations`\n\
+       {indent}  #[allow(unused_variables)]\n\
+       {indent}  pub fn validate_at(&self, path: &str, violations: &mut Vec<String>) {{\n\
+       {body}let prefix = if path.is_empty() {{ String::new() }} else {{ format!(\"{{path}}.\") }};\n"
+    ));
+
+    for field in &definition.fields {
+      let key = field_key(field);
+      let mut field_path = definition.path.clone();
+      field_path.push(key.to_string());
+      let field_path = field_path.join(".");
+      let (ty, optional) = match field
+        .ty
+        .strip_prefix("Option<")
+        .and_then(|inner| inner.strip_suffix('>'))
+      {
+        Some(inner) => (inner, true),
+        None => (field.ty.as_str(), false),
+      };
+      let access = format!("self.{}", field.name);
+      let label = escape_format(key);
+
+      let mut checks = String::new();
+      if let Some(constraint) = constraints.get(&field_path) {
+        checks.push_str(&constraint_checks(
+          constraint,
+          &field_path,
+          ty,
+          &label,
+          &format!("{body}  "),
+        )?);
+      }
+      if struct_names.contains(ty) {
+        checks.push_str(&format!(
+          "{body}  value.validate_at(&format!(\"{{prefix}}{label}\"), violations);\n"
+        ));
+      }
+      if let Some(item_ty) = map_item_type(ty) {
+        let mut item_checks = String::new();
+        if let Some(constraint) = constraints.get(&format!("{field_path}.{MAP_KEY}")) {
+          item_checks.push_str(&constraint_checks(
+            constraint,
+            &format!("{field_path}.{MAP_KEY}"),
+            item_ty,
+            &format!("{label}.{{key}}"),
+            &format!("{body}    "),
+          )?);
+        }
+        if struct_names.contains(item_ty) {
+          item_checks.push_str(&format!(
+            "{body}    value.validate_at(&format!(\"{{prefix}}{label}.{{key}}\"), violations);\n"
+          ));
+        }
+        if !item_checks.is_empty() {
+          checks.push_str(&format!(
+            "{body}  for (key, value) in value {{\n{item_checks}{body}  }}\n"
+          ));
+        }
+      }
+      if checks.is_empty() {
+        continue;
+      }
+
+      if optional {
+        output.push_str(&format!("{body}if let Some(value) = &{access} {{\n"));
+      } else {
+        output.push_str(&format!("{body}{{\n{body}  let value = &{access};\n"));
+      }
+      output.push_str(&checks);
+      output.push_str(&format!("{body}}}\n"));
+    }
+    output.push_str(&format!("{indent}  }}\n{indent}}}\n"));
+  }
+  Ok(output)
+}
+
+/// 渲染单个值的约束检查，`value` 为对该值的引用
+///
+/// 类型覆盖可能把字段改为约束无法作用的类型，此时返回错误而不是漏掉检查。
+fn constraint_checks(
+  constraint: &Constraint,
+  path: &str,
+  ty: &str,
+  label: &str,
+  indent: &str,
+) -> Result<String> {
+  let mut checks = Vec::new();
+  let mut applied = Vec::new();
+  let category = type_category(ty);
+  let mut push =
+    |rule: &'static str, prelude: Option<String>, condition: String, message: String| {
+      applied.push(rule);
+      checks.push((prelude, condition, message));
+    };
+
+  if category == TypeCategory::Number {
+    if let Some(min) = constraint.min {
+      push(
+        "min",
+        None,
+        format!("(*value as f64) < {min:?}"),
+        format!("must be >= {min}"),
+      );
+    }
+    if let Some(max) = constraint.max {
+      push(
+        "max",
+        None,
+        format!("(*value as f64) > {max:?}"),
+        format!("must be <= {max}"),
+      );
+    }
+  }
+
+  let length = match category {
+    TypeCategory::Text => Some("value.chars().count()"),
+    TypeCategory::Collection => Some("value.len()"),
+    _ => None,
+  };
+  if let Some(length) = length {
+    if let Some(min) = constraint.min_length {
+      push(
+        "min_length",
+        None,
+        format!("{length} < {min}"),
+        format!("length must be >= {min}"),
+      );
+    }
+    if let Some(max) = constraint.max_length {
+      push(
+        "max_length",
+        None,
+        format!("{length} > {max}"),
+        format!("length must be <= {max}"),
+      );
+    }
+    if constraint.non_empty {
+      push(
+        "non_empty",
+        None,
+        String::from("value.is_empty()"),
+        String::from("must not be empty"),
+      );
+    }
+  }
+
+  if category == TypeCategory::Text {
+    if let Some(pattern) = &constraint.pattern {
+      // 每个正则只编译一次，加载约束时已校验过正则有效
+      let regex = format!(
+        "{indent}  static PATTERN: std::sync::LazyLock<bodhi_config::regex::Regex> =\n\
+         {indent}    std::sync::LazyLock::new(|| {{\n\
+         {indent}      bodhi_config::regex::Regex::new({:?}).expect(\"constraint pattern is valid\")\n\
+         {indent}    }});\n",
+        pattern.as_str()
+      );
+      push(
+        "pattern",
+        Some(regex),
+        String::from("!PATTERN.is_match(value)"),
+        format!("must match pattern `{}`", pattern.as_str()),
+      );
+    }
+    if let Some(format) = constraint.format {
+      let variant = match format {
+        ValueFormat::SocketAddr => "SocketAddr",
+        ValueFormat::Url => "Url",
+      };
+      push(
+        "format",
+        None,
+        format!("!bodhi_config::constraint::ValueFormat::{variant}.matches(value)"),
+        format.describe().to_string(),
+      );
+    }
+  }
+
+  if !constraint.one_of.is_empty() {
+    let items = match category {
+      TypeCategory::Text => constraint
+        .one_of
+        .iter()
+        .map(|item| item.as_str().map(|text| format!("{text:?}")))
+        .collect::<Option<Vec<_>>>()
+        .map(|items| (items, "value.as_str()")),
+      TypeCategory::Number => constraint
+        .one_of
+        .iter()
+        .map(|item| match item {
+          Value::Integer(number) => Some(format!("{:?}", *number as f64)),
+          Value::Float(number) => Some(format!("{number:?}")),
+          _ => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|items| (items, "(*value as f64)")),
+      TypeCategory::Boolean => constraint
+        .one_of
+        .iter()
+        .map(|item| item.as_bool().map(|flag| flag.to_string()))
+        .collect::<Option<Vec<_>>>()
+        .map(|items| (items, "*value")),
+      _ => None,
+    };
+    if let Some((items, expr)) = items {
+      push(
+        "one_of",
+        None,
+        format!("![{}].contains(&{expr})", items.join(", ")),
+        format!("must be one of {}", constraint.one_of_list()),
+      );
+    }
+  }
+
+  if let Some(rule) = constraint
+    .rules()
+    .into_iter()
+    .find(|rule| !applied.contains(rule))
+  {
+    return Err(
+      Error::new(CONFIGERR_CODEGENFAILED)
+        .wrap_context("constraint cannot be applied to generated field type")
+        .wrap_context_with(|| format!("path={path} type={ty} rule={rule}")),
+    );
+  }
+
+  Ok(
+    checks
+      .into_iter()
+      .map(|(prelude, condition, message)| {
+        let check = |indent: &str| {
+          format!(
+            "{indent}if {condition} {{\n\
+           {indent}  violations.push(format!(\"{{prefix}}{label}: {{}}\", {message:?}));\n\
+           {indent}}}\n"
+          )
+        };
+        match prelude {
+          // 带静态项的检查放进独立的块，避免同一作用域内重名
+          Some(prelude) => format!(
+            "{indent}{{\n{prelude}{}{indent}}}\n",
+            check(&format!("{indent}  "))
+          ),
+          None => check(indent),
+        }
+      })
+      .collect(),
+  )
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum TypeCategory {
+  Number,
+  Text,
+  Boolean,
+  Collection,
+  Other,
+}
+
+fn type_category(ty: &str) -> TypeCategory {
+  let name = ty
+    .split('<')
+    .next()
+    .unwrap_or(ty)
+    .rsplit("::")
+    .next()
+    .unwrap_or(ty)
+    .trim();
+  match name {
+    "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+    | "usize" | "f32" | "f64" => TypeCategory::Number,
+    "String" => TypeCategory::Text,
+    "bool" => TypeCategory::Boolean,
+    "Vec" | "VecDeque" | "HashSet" | "BTreeSet" | "IndexSet" | "HashMap" | "BTreeMap"
+    | "IndexMap" => TypeCategory::Collection,
+    _ => TypeCategory::Other,
+  }
+}
+
+/// 生成的映射表类型的值类型，例如 `std::collections::BTreeMap<String, RoutesItem>` 中的 `RoutesItem`
+fn map_item_type(ty: &str) -> Option<&str> {
+  ty.strip_prefix("std::collections::BTreeMap<String, ")
+    .and_then(|inner| inner.strip_suffix('>'))
+}
+
+fn field_key(field: &FieldDefinition) -> &str {
+  let key = field.rename.as_deref().unwrap_or(&field.name);
+  key.strip_prefix("r#").unwrap_or(key)
+}
+
+fn escape_format(text: &str) -> String {
+  text
+    .replace('{', "{{")
+    .replace('}', "}}")
+    .replace('"', "\\\"")
+}
+
+/// 渲染 `CONFIG` 静态实例，字符串等堆分配字段无法在常量上下文构造，因此使用 `LazyLock`
+fn render_value_static(
+  root_struct_name: &str,
+  definitions: &[StructDefinition],
+  table: &toml::map::Map<String, Value>,
+  indent: &str,
+) -> Result<String> {
+  let renderer = ValueRenderer {
+    definitions: definitions
+      .iter()
+      .map(|definition| (definition.name.as_str(), definition))
+      .collect(),
+  };
+  let literal = renderer.struct_literal(root_struct_name, &[], table, &format!("{indent}  "))?;
+
+  Ok(format!(
+    "{indent}pub static {VALUE_STATIC_NAME}: std::sync::LazyLock<{root_struct_name}> =\n\
+     {indent}  std::sync::LazyLock::new(|| {literal});\n"
+  ))
+}
+
+/// 按生成的结构定义把配置值渲染为 Rust 字面量表达式
+struct ValueRenderer<'a> {
+  definitions: BTreeMap<&'a str, &'a StructDefinition>,
+}
+
+impl ValueRenderer<'_> {
+  fn struct_literal(
+    &self,
+    struct_name: &str,
+    path: &[String],
+    table: &toml::map::Map<String, Value>,
+    indent: &str,
+  ) -> Result<String> {
+    let definition = self.definitions.get(struct_name).ok_or_else(|| {
+      Error::new(CONFIGERR_CODEGENFAILED)
+        .wrap_context("struct definition not found for value literal")
+        .wrap_context_with(|| format!("struct={struct_name}"))
+    })?;
+
+    let field_indent = format!("{indent}  ");
+    let mut output = format!("{struct_name} {{\n");
+    for field in &definition.fields {
+      let key = field.rename.as_deref().unwrap_or(&field.name);
+      let key = key.strip_prefix("r#").unwrap_or(key);
+      // 被删除的可选字段在注解后仍保留模板值用于推断类型，字面量为 `None`
+      let value = table.get(key).filter(|_| !is_unset_field(table, key));
+      let Some(value) = value else {
+        if field.ty.starts_with("Option<") {
+          output.push_str(&format!("{field_indent}{}: None,\n", field.name));
+          continue;
+        }
+        return Err(
+          Error::new(CONFIGERR_CODEGENFAILED)
+            .wrap_context("value missing for generated field")
+            .wrap_context_with(|| format!("path={}", join_path(path, key))),
+        );
+      };
+
+      let mut child_path = path.to_vec();
+      child_path.push(key.to_string());
+      let ty = syn::parse_str::<Type>(&field.ty)
+        .map_err(|err| Error::new(CONFIGERR_CODEGENFAILED).wrap_context(err.to_string()))
+        .wrap_context("parse generated field type failed")
+        .wrap_context_with(|| format!("path={} type={}", child_path.join("."), field.ty))?;
+      let literal = self.value_literal(&ty, &child_path, value, &field_indent)?;
+      output.push_str(&format!("{field_indent}{}: {literal},\n", field.name));
+    }
+    output.push_str(&format!("{indent}}}"));
+
+    Ok(output)
+  }
+
+  fn value_literal(
+    &self,
+    ty: &Type,
+    path: &[String],
+    value: &Value,
+    indent: &str,
+  ) -> Result<String> {
+    let unsupported = |reason: &str| {
+      Error::new(CONFIGERR_CODEGENFAILED)
+        .wrap_context(format!("cannot render value literal: {reason}"))
+        .wrap_context_with(|| format!("path={}", path.join(".")))
+    };
+
+    let type_path = match ty {
+      Type::Path(type_path) if type_path.qself.is_none() => type_path,
+      Type::Reference(reference) => match (&*reference.elem, value) {
+        (Type::Path(elem), Value::String(text)) if elem.path.is_ident("str") => {
+          return Ok(format!("{text:?}"));
+        }
+        _ => return Err(unsupported("only &str references are supported")),
+      },
+      Type::Paren(paren) => return self.value_literal(&paren.elem, path, value, indent),
+      Type::Group(group) => return self.value_literal(&group.elem, path, value, indent),
+      _ => return Err(unsupported("unsupported type form")),
+    };
+    let Some(segment) = type_path.path.segments.last() else {
+      return Err(unsupported("empty type path"));
+    };
+    let name = segment.ident.to_string();
+    let args: Vec<&Type> = match &segment.arguments {
+      syn::PathArguments::AngleBracketed(arguments) => arguments
+        .args
+        .iter()
+        .filter_map(|arg| match arg {
+          syn::GenericArgument::Type(ty) => Some(ty),
+          _ => None,
+        })
+        .collect(),
+      _ => Vec::new(),
+    };
+    // 去掉泛型参数后的类型路径，用于调用 `new`/`from` 等关联函数
+    let constructor = type_path
+      .path
+      .segments
+      .iter()
+      .map(|segment| segment.ident.to_string())
+      .collect::<Vec<_>>()
+      .join("::");
+    let constructor = if type_path.path.leading_colon.is_some() {
+      format!("::{constructor}")
+    } else {
+      constructor
+    };
+
+    match (name.as_str(), args.as_slice(), value) {
+      (_, [], Value::Table(table)) if self.definitions.contains_key(name.as_str()) => {
+        self.struct_literal(&name, path, table, indent)
+      }
+      ("Option", [inner], _) => Ok(format!(
+        "Some({})",
+        self.value_literal(inner, path, value, indent)?
+      )),
+      ("Box" | "Arc" | "Rc", [inner], _) => Ok(format!(
+        "{constructor}::new({})",
+        self.value_literal(inner, path, value, indent)?
+      )),
+      ("Vec", [inner], Value::Array(items)) => Ok(format!(
+        "vec![{}]",
+        self.array_items(inner, path, items, indent)?
+      )),
+      ("VecDeque" | "HashSet" | "BTreeSet" | "IndexSet", [inner], Value::Array(items)) => {
+        Ok(format!(
+          "{constructor}::from([{}])",
+          self.array_items(inner, path, items, indent)?
+        ))
+      }
+      ("HashMap" | "BTreeMap" | "IndexMap", [_, inner], Value::Table(table)) => {
+        let mut entries = Vec::with_capacity(table.len());
+        // 注解后的映射表保留 `"*"` 值结构，不属于条目
+        for (key, item) in table
+          .iter()
+          .filter(|(key, _)| *key != MAP_KEY && !is_marker_key(key))
+        {
+          let mut child_path = path.to_vec();
+          child_path.push(key.clone());
+          let item = self.value_literal(inner, &child_path, item, indent)?;
+          entries.push(format!("(String::from({key:?}), {item})"));
+        }
+        Ok(format!("{constructor}::from([{}])", entries.join(", ")))
+      }
+      ("String", [], Value::String(text)) => Ok(format!("String::from({text:?})")),
+      ("PathBuf", [], Value::String(text)) => Ok(format!("{constructor}::from({text:?})")),
+      ("char", [], Value::String(text)) if text.chars().count() == 1 => {
+        Ok(format!("{:?}", text.chars().next().expect("single char")))
+      }
+      ("bool", [], Value::Boolean(flag)) => Ok(flag.to_string()),
+      ("f32" | "f64", [], Value::Float(number)) => Ok(float_literal(&name, *number)),
+      ("f32" | "f64", [], Value::Integer(number)) => Ok(format!("{number}.0")),
+      (
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+        | "usize",
+        [],
+        Value::Integer(number),
+      ) => Ok(format!("{number}{name}")),
+      ("Datetime", [], Value::Datetime(datetime)) => Ok(format!(
+        "{:?}.parse::<{constructor}>().expect(\"valid datetime literal\")",
+        datetime.to_string()
+      )),
+      _ => Err(unsupported(&format!(
+        "value kind {} does not fit the field type",
+        value_kind(value)
+      ))),
+    }
+  }
+
+  fn array_items(
+    &self,
+    item_type: &Type,
+    path: &[String],
+    items: &[Value],
+    indent: &str,
+  ) -> Result<String> {
+    let mut literals = Vec::with_capacity(items.len());
+    for (index, item) in items.iter().enumerate() {
+      let mut child_path = path.to_vec();
+      child_path.push(index.to_string());
+      literals.push(self.value_literal(item_type, &child_path, item, indent)?);
+    }
+    Ok(literals.join(", "))
+  }
+}
+
+fn is_unset_field(table: &toml::map::Map<String, Value>, key: &str) -> bool {
+  table
+    .get(UNSET_KEY)
+    .and_then(Value::as_array)
+    .is_some_and(|keys| keys.iter().any(|unset| unset.as_str() == Some(key)))
+}
+
+/// 按模板为解析结果补充可选字段标记
+///
+/// 被覆盖层删除或缺省的可选字段补回模板值或字段标记用于推断类型，并记入 `__unset`，
+/// 渲染字面量时为 `None`。
+pub(crate) fn annotate_optional_fields(resolved: &Value, schema: &Value) -> Value {
+  let (Value::Table(resolved_table), Value::Table(schema_table)) = (resolved, schema) else {
+    return resolved.clone();
+  };
+
+  let mut annotated = toml::map::Map::new();
+  // 映射表保留 `"*"` 值结构用于推断条目类型
+  if let Some(value_schema) = schema_table.get(MAP_KEY) {
+    annotated.insert(MAP_KEY.to_string(), value_schema.clone());
+  }
+  for (key, value) in resolved_table {
+    let value = match schema_child(schema_table, key) {
+      Some(schema_value) => annotate_optional_fields(value, schema_value),
+      None => value.clone(),
+    };
+    annotated.insert(key.clone(), value);
+  }
+
+  let optional: Vec<_> = schema_table
+    .keys()
+    .filter(|key| !is_marker_key(key) && is_optional_field(schema_table, key))
+    .collect();
+  if optional.is_empty() {
+    return Value::Table(annotated);
+  }
+  let mut unset = Vec::new();
+  for key in &optional {
+    if !annotated.contains_key(*key) {
+      annotated.insert((*key).clone(), schema_table[*key].clone());
+      unset.push(Value::String((*key).clone()));
+    }
+  }
+  annotated.insert(
+    OPTIONAL_KEY.to_string(),
+    Value::Array(optional.into_iter().cloned().map(Value::String).collect()),
+  );
+  if !unset.is_empty() {
+    annotated.insert(UNSET_KEY.to_string(), Value::Array(unset));
+  }
+  Value::Table(annotated)
+}
+
+fn float_literal(ty: &str, number: f64) -> String {
+  if number.is_nan() {
+    format!("{ty}::NAN")
+  } else if number == f64::INFINITY {
+    format!("{ty}::INFINITY")
+  } else if number == f64::NEG_INFINITY {
+    format!("{ty}::NEG_INFINITY")
+  } else {
+    format!("{number:?}{ty}")
+  }
+}
+
+fn render_module(module_name: &str, module: &GeneratedModule, values: Option<&str>) -> String {
   let mut output = format!("pub mod {module_name} {{\n  use super::*;\n\n");
   let mut definitions = module.definitions.clone();
   definitions.sort_by(|left, right| {
@@ -523,6 +1383,11 @@ fn render_module(module_name: &str, module: &GeneratedModule) -> String {
     output.push_str("  }\n");
   }
 
+  if let Some(values) = values {
+    output.push('\n');
+    output.push_str(values);
+  }
+
   output.push('}');
   output
 }
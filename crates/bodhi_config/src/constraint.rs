@@ -0,0 +1,398 @@
+//! 值约束模块
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::SocketAddr;
+
+use bodhi_error::prelude::*;
+use regex::Regex;
+use toml::Value;
+
+use crate::errcode::configerr::*;
+use crate::validate::MAP_KEY;
+
+/// 模板旁约束文件的名称后缀，例如 `template/service/gateway.schema.toml`
+pub const SCHEMA_SIDECAR_SUFFIX: &str = ".schema";
+
+/// 约束表中允许的键
+pub const CONSTRAINT_KEYS: [&str; 8] = [
+  "min",
+  "max",
+  "min_length",
+  "max_length",
+  "non_empty",
+  "pattern",
+  "one_of",
+  "format",
+];
+
+/// 判断模板名是否为约束文件
+pub fn is_schema_sidecar(name: &str) -> bool {
+  name.ends_with(SCHEMA_SIDECAR_SUFFIX)
+}
+
+/// 字符串格式约束
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ValueFormat {
+  /// `IP:port` 或 `host:port`
+  SocketAddr,
+  /// 带 scheme 的 URL，例如 `https://example.com`
+  Url,
+}
+
+impl ValueFormat {
+  pub fn parse(value: &str) -> Option<Self> {
+    match value {
+      "socket_addr" => Some(Self::SocketAddr),
+      "url" => Some(Self::Url),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Self::SocketAddr => "socket_addr",
+      Self::Url => "url",
+    }
+  }
+
+  /// 判断字符串是否符合格式
+  pub fn matches(self, text: &str) -> bool {
+    match self {
+      Self::SocketAddr => is_socket_addr(text),
+      Self::Url => is_url(text),
+    }
+  }
+
+  /// 违反格式时的说明
+  pub fn describe(self) -> &'static str {
+    match self {
+      Self::SocketAddr => "must be a socket address",
+      Self::Url => "must be a URL",
+    }
+  }
+}
+
+/// 单个路径上的值约束
+#[derive(Clone, Debug, Default)]
+pub struct Constraint {
+  pub min: Option<f64>,
+  pub max: Option<f64>,
+  pub min_length: Option<usize>,
+  pub max_length: Option<usize>,
+  pub non_empty: bool,
+  pub pattern: Option<Regex>,
+  pub one_of: Vec<Value>,
+  pub format: Option<ValueFormat>,
+}
+
+impl Constraint {
+  /// 从约束表读取约束，`path` 用于错误上下文
+  pub fn from_table(table: &toml::map::Map<String, Value>, path: &str) -> Result<Self> {
+    let invalid = |key: &str, reason: &str| {
+      Error::new(CONFIGERR_INVALIDSTRUCTURE)
+        .wrap_context(reason.to_string())
+        .wrap_context_with(|| format!("path={path} key={key}"))
+    };
+    let number = |key: &str| match table.get(key) {
+      None => Ok(None),
+      Some(Value::Integer(number)) => Ok(Some(*number as f64)),
+      Some(Value::Float(number)) => Ok(Some(*number)),
+      Some(_) => Err(invalid(key, "constraint bound must be a number")),
+    };
+    let length = |key: &str| match table.get(key) {
+      None => Ok(None),
+      Some(Value::Integer(number)) if *number >= 0 => Ok(Some(*number as usize)),
+      Some(_) => Err(invalid(
+        key,
+        "length constraint must be a non-negative integer",
+      )),
+    };
+
+    let mut constraint = Self {
+      min: number("min")?,
+      max: number("max")?,
+      min_length: length("min_length")?,
+      max_length: length("max_length")?,
+      ..Self::default()
+    };
+    for (key, value) in table {
+      match (key.as_str(), value) {
+        ("min" | "max" | "min_length" | "max_length", _) => {}
+        ("non_empty", Value::Boolean(flag)) => constraint.non_empty = *flag,
+        ("pattern", Value::String(pattern)) => {
+          let regex = Regex::new(pattern).map_err(|err| {
+            invalid(key, "constraint pattern is not a valid regex").wrap_context(err.to_string())
+          })?;
+          constraint.pattern = Some(regex);
+        }
+        ("one_of", Value::Array(items)) if !items.is_empty() => {
+          constraint.one_of = items.clone();
+        }
+        ("format", Value::String(format)) => {
+          constraint.format = Some(
+            ValueFormat::parse(format)
+              .ok_or_else(|| invalid(key, "constraint format must be socket_addr or url"))?,
+          );
+        }
+        _ if CONSTRAINT_KEYS.contains(&key.as_str()) => {
+          return Err(invalid(key, "constraint value has invalid type"));
+        }
+        _ => return Err(invalid(key, "unknown constraint")),
+      }
+    }
+    Ok(constraint)
+  }
+
+  /// 已声明的约束规则名，顺序与 [`CONSTRAINT_KEYS`] 一致
+  pub fn rules(&self) -> Vec<&'static str> {
+    let declared = [
+      self.min.is_some(),
+      self.max.is_some(),
+      self.min_length.is_some(),
+      self.max_length.is_some(),
+      self.non_empty,
+      self.pattern.is_some(),
+      !self.one_of.is_empty(),
+      self.format.is_some(),
+    ];
+    CONSTRAINT_KEYS
+      .into_iter()
+      .zip(declared)
+      .filter_map(|(rule, declared)| declared.then_some(rule))
+      .collect()
+  }
+
+  /// 按约束检查一个值，违反项追加到 `violations`
+  pub fn check(&self, path: &str, value: &Value, violations: &mut Vec<Violation>) {
+    let mut violate = |rule: &'static str, message: String| {
+      violations.push(Violation {
+        path: path.to_string(),
+        rule,
+        message,
+      });
+    };
+
+    let number = match value {
+      Value::Integer(number) => Some(*number as f64),
+      Value::Float(number) => Some(*number),
+      _ => None,
+    };
+    if let Some(number) = number {
+      if let Some(min) = self.min.filter(|min| number < *min) {
+        violate("min", format!("must be >= {min}"));
+      }
+      if let Some(max) = self.max.filter(|max| number > *max) {
+        violate("max", format!("must be <= {max}"));
+      }
+    }
+
+    let length = match value {
+      Value::String(text) => Some(text.chars().count()),
+      Value::Array(items) => Some(items.len()),
+      Value::Table(table) => Some(table.len()),
+      _ => None,
+    };
+    if let Some(length) = length {
+      if let Some(min) = self.min_length.filter(|min| length < *min) {
+        violate("min_length", format!("length must be >= {min}"));
+      }
+      if let Some(max) = self.max_length.filter(|max| length > *max) {
+        violate("max_length", format!("length must be <= {max}"));
+      }
+      if self.non_empty && length == 0 {
+        violate("non_empty", String::from("must not be empty"));
+      }
+    }
+
+    if let Value::String(text) = value {
+      if let Some(pattern) = self.pattern.as_ref().filter(|regex| !regex.is_match(text)) {
+        violate(
+          "pattern",
+          format!("must match pattern `{}`", pattern.as_str()),
+        );
+      }
+      if let Some(format) = self.format.filter(|format| !format.matches(text)) {
+        violate("format", format.describe().to_string());
+      }
+    }
+
+    if !self.one_of.is_empty() && !self.one_of.contains(value) {
+      violate("one_of", format!("must be one of {}", self.one_of_list()));
+    }
+  }
+
+  /// `one_of` 的展示文本，例如 `["DEBUG", "INFO"]`
+  pub fn one_of_list(&self) -> String {
+    let items: Vec<_> = self.one_of.iter().map(Value::to_string).collect();
+    format!("[{}]", items.join(", "))
+  }
+}
+
+/// 一项约束违反
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Violation {
+  pub path: String,
+  pub rule: &'static str,
+  pub message: String,
+}
+
+impl fmt::Display for Violation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}: {}", self.path, self.message)
+  }
+}
+
+/// 一个配置根下按路径声明的约束，路径以点分隔，`*` 匹配映射表的任意键
+#[derive(Clone, Debug, Default)]
+pub struct ConstraintSet {
+  rules: BTreeMap<String, Constraint>,
+}
+
+impl ConstraintSet {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn insert(&mut self, path: impl Into<String>, constraint: Constraint) {
+    self.rules.insert(path.into(), constraint);
+  }
+
+  pub fn get(&self, path: &str) -> Option<&Constraint> {
+    self.rules.get(path)
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (&str, &Constraint)> {
+    self
+      .rules
+      .iter()
+      .map(|(path, constraint)| (path.as_str(), constraint))
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.rules.is_empty()
+  }
+
+  /// 合并另一组约束，同一路径以后者为准
+  pub fn extend(&mut self, other: &ConstraintSet) {
+    self.rules.extend(
+      other
+        .rules
+        .iter()
+        .map(|(path, constraint)| (path.clone(), constraint.clone())),
+    );
+  }
+
+  /// 检查配置值，返回全部违反项，路径加上 `prefix`；不存在的可选值不检查
+  pub fn check(&self, value: &Value, prefix: &str) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for (path, constraint) in &self.rules {
+      let mut matches = Vec::new();
+      collect_matches(
+        value,
+        path.split('.').collect::<Vec<_>>().as_slice(),
+        prefix,
+        &mut matches,
+      );
+      for (concrete_path, matched) in matches {
+        constraint.check(&concrete_path, matched, &mut violations);
+      }
+    }
+    violations
+  }
+}
+
+fn collect_matches<'a>(
+  value: &'a Value,
+  segments: &[&str],
+  path: &str,
+  matches: &mut Vec<(String, &'a Value)>,
+) {
+  let Some((segment, rest)) = segments.split_first() else {
+    matches.push((path.to_string(), value));
+    return;
+  };
+  let Some(table) = value.as_table() else {
+    return;
+  };
+  let join = |key: &str| {
+    if path.is_empty() {
+      key.to_string()
+    } else {
+      format!("{path}.{key}")
+    }
+  };
+
+  if *segment == MAP_KEY {
+    for (key, child) in table {
+      collect_matches(child, rest, &join(key), matches);
+    }
+  } else if let Some(child) = table.get(*segment) {
+    collect_matches(child, rest, &join(segment), matches);
+  }
+}
+
+/// 一个服务解析时适用的约束
+#[derive(Clone, Debug, Default)]
+pub struct Constraints {
+  /// 作用于 infra 配置
+  pub infra: ConstraintSet,
+  /// 作用于服务配置
+  pub service: ConstraintSet,
+}
+
+impl Constraints {
+  /// 合并配置适用的约束，infra 和服务配置位于同一根下
+  pub fn merged(&self) -> ConstraintSet {
+    let mut merged = self.infra.clone();
+    merged.extend(&self.service);
+    merged
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.infra.is_empty() && self.service.is_empty()
+  }
+}
+
+/// 全部约束文件中的约束
+#[derive(Clone, Debug, Default)]
+pub struct ConstraintSchema {
+  pub infra: ConstraintSet,
+  pub services: BTreeMap<String, ConstraintSet>,
+}
+
+impl ConstraintSchema {
+  /// 获取指定服务适用的约束
+  pub fn service(&self, service: &str) -> Constraints {
+    Constraints {
+      infra: self.infra.clone(),
+      service: self.services.get(service).cloned().unwrap_or_default(),
+    }
+  }
+}
+
+fn is_socket_addr(text: &str) -> bool {
+  if text.parse::<SocketAddr>().is_ok() {
+    return true;
+  }
+  text.rsplit_once(':').is_some_and(|(host, port)| {
+    !host.is_empty()
+      && !host.contains(':')
+      && !host.chars().any(char::is_whitespace)
+      && port.parse::<u16>().is_ok()
+  })
+}
+
+fn is_url(text: &str) -> bool {
+  let Some((scheme, rest)) = text.split_once("://") else {
+    return false;
+  };
+  let mut scheme_chars = scheme.chars();
+  scheme_chars
+    .next()
+    .is_some_and(|ch| ch.is_ascii_alphabetic())
+    && scheme_chars.all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '+' | '-' | '.'))
+    && !rest.is_empty()
+    && !rest.starts_with('/')
+    && !text.chars().any(char::is_whitespace)
+}
@@ -0,0 +1,321 @@
+//! 解析维度模块
+//!
+//! `dimensions.toml` 按顺序声明 profile 之外的维度，例如 region、datacenter，
+//! 每个维度的取值对应其覆盖目录下的同名文件，结构与 profile 相同。
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+
+use bodhi_error::prelude::*;
+use serde::Deserialize;
+use toml::Value;
+
+use crate::errcode::configerr::*;
+use crate::source::{ConfigSource, PRODUCT_DIR, PROFILE_DIR, list_toml_stems, read_toml};
+
+/// 维度声明文件
+pub const DIMENSIONS_FILE: &str = "dimensions.toml";
+/// profile 维度名，始终是第一个维度
+pub const PROFILE_DIMENSION: &str = "profile";
+/// 叠加 profile 的规范分隔符，例如 `dev+stanley`，输入时也接受逗号
+pub const PROFILE_STACK_SEPARATOR: char = '+';
+
+/// 拆分叠加的 profile，按从左到右的合并顺序返回
+pub fn split_profile_stack(profile: &str) -> Vec<&str> {
+  profile
+    .split([PROFILE_STACK_SEPARATOR, ','])
+    .map(str::trim)
+    .filter(|profile| !profile.is_empty())
+    .collect()
+}
+
+/// 规范化叠加的 profile，例如 `dev, stanley` 规范化为 `dev+stanley`
+pub fn normalize_profile_stack(profile: &str) -> String {
+  split_profile_stack(profile).join(&PROFILE_STACK_SEPARATOR.to_string())
+}
+
+/// 校验单个 profile 名，叠加分隔符 `+` 和 `,` 不能出现在名字中
+pub fn validate_profile_name(profile: &str) -> Result<()> {
+  if profile.trim().is_empty() || profile.contains([PROFILE_STACK_SEPARATOR, ',']) {
+    return Err(
+      Error::new(CONFIGERR_INVALIDSTRUCTURE)
+        .wrap_context("profile name must be non-empty and must not contain `+` or `,`")
+        .wrap_context_with(|| format!("profile={profile}")),
+    );
+  }
+  Ok(())
+}
+
+const RESERVED_NAMES: &[&str] = &[
+  PROFILE_DIMENSION,
+  "infra",
+  "services",
+  "instances",
+  "template",
+  "product",
+];
+
+/// 维度声明
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DimensionSpec {
+  /// 维度名
+  pub name: String,
+  /// 覆盖目录，相对配置根目录，缺省与维度名相同
+  #[serde(default)]
+  pub dir: Option<String>,
+}
+
+impl DimensionSpec {
+  /// 获取覆盖目录
+  pub fn dir(&self) -> &str {
+    self.dir.as_deref().unwrap_or(&self.name)
+  }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DimensionsFile {
+  #[serde(default)]
+  dimension: Vec<DimensionSpec>,
+}
+
+/// 读取维度声明，文件不存在时只有 profile 维度
+pub fn load_dimension_specs(source: &dyn ConfigSource) -> Result<Vec<DimensionSpec>> {
+  let Some(value) = read_toml(source, DIMENSIONS_FILE)? else {
+    return Ok(Vec::new());
+  };
+
+  let file: DimensionsFile = value
+    .try_into()
+    .map_err(|err: toml::de::Error| {
+      Error::new(CONFIGERR_INVALIDSTRUCTURE).wrap_context(err.to_string())
+    })
+    .wrap_context("parse dimension declarations failed")
+    .wrap_context_with(|| format!("path={DIMENSIONS_FILE}"))?;
+
+  let mut names = BTreeSet::new();
+  for spec in &file.dimension {
+    let valid_name = !spec.name.is_empty()
+      && spec
+        .name
+        .chars()
+        .all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-');
+    let reserved_dir = [PROFILE_DIR, PRODUCT_DIR, "template"].contains(&spec.dir());
+    if !valid_name || RESERVED_NAMES.contains(&spec.name.as_str()) || reserved_dir {
+      return Err(
+        Error::new(CONFIGERR_INVALIDSTRUCTURE)
+          .wrap_context("dimension name or dir is invalid or reserved")
+          .wrap_context_with(|| {
+            format!(
+              "path={DIMENSIONS_FILE} dimension={} dir={}",
+              spec.name,
+              spec.dir()
+            )
+          }),
+      );
+    }
+    if !names.insert(spec.name.as_str()) {
+      return Err(
+        Error::new(CONFIGERR_INVALIDSTRUCTURE)
+          .wrap_context("dimension declared more than once")
+          .wrap_context_with(|| format!("path={DIMENSIONS_FILE} dimension={}", spec.name)),
+      );
+    }
+  }
+
+  Ok(file.dimension)
+}
+
+/// 列出维度的全部取值，覆盖目录不存在时为空
+pub fn dimension_values(source: &dyn ConfigSource, spec: &DimensionSpec) -> Result<Vec<String>> {
+  if source.list(spec.dir())?.is_none() {
+    return Ok(Vec::new());
+  }
+  list_toml_stems(source, spec.dir(), CONFIGERR_DIMENSIONNOTFOUND)
+}
+
+/// 读取维度取值对应的覆盖文件
+pub fn load_dimension_overlay(
+  source: &dyn ConfigSource,
+  spec: &DimensionSpec,
+  value: &str,
+) -> Result<Value> {
+  let path = format!("{}/{value}.toml", spec.dir());
+  read_toml(source, &path)?
+    .ok_or_else(|| Error::new(CONFIGERR_DIMENSIONNOTFOUND))
+    .wrap_context_with(|| {
+      format!(
+        "dimension={} value={value} source={} path={path} not found",
+        spec.name,
+        source.describe()
+      )
+    })
+}
+
+/// 解析时选定的各维度取值
+///
+/// 字符串形式为 `profile=prod,region=eu`，只有 profile 时也可简写为 `prod`；
+/// 叠加的 profile 写作 `profile=dev+stanley`，或在开头直接列出 `dev,stanley,region=eu`。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Dimensions {
+  profile: String,
+  values: BTreeMap<String, String>,
+}
+
+impl Dimensions {
+  /// 只选定 profile，可以是 `dev,stanley` 形式的叠加 profile
+  pub fn new(profile: &str) -> Self {
+    Self {
+      profile: normalize_profile_stack(profile),
+      values: BTreeMap::new(),
+    }
+  }
+
+  /// 按合并顺序选定叠加的 profile，每项须是单个 profile 名
+  pub fn with_profiles(profiles: &[&str]) -> Result<Self> {
+    if profiles.is_empty() {
+      return Err(
+        Error::new(CONFIGERR_INVALIDSTRUCTURE).wrap_context("at least one profile is required"),
+      );
+    }
+    for profile in profiles {
+      validate_profile_name(profile)?;
+    }
+    Ok(Self::new(
+      &profiles.join(&PROFILE_STACK_SEPARATOR.to_string()),
+    ))
+  }
+
+  /// 追加一个维度取值，`profile` 会替换当前 profile
+  pub fn with(mut self, name: &str, value: &str) -> Self {
+    self.set(name, value);
+    self
+  }
+
+  /// 设置一个维度取值，`profile` 会替换当前 profile
+  pub fn set(&mut self, name: &str, value: &str) {
+    if name == PROFILE_DIMENSION {
+      self.profile = normalize_profile_stack(value);
+    } else {
+      self.values.insert(name.to_string(), value.to_string());
+    }
+  }
+
+  /// 获取规范化的 profile，叠加时形如 `dev+stanley`
+  pub fn profile(&self) -> &str {
+    &self.profile
+  }
+
+  /// 按合并顺序获取叠加的各个 profile
+  pub fn profiles(&self) -> Vec<&str> {
+    split_profile_stack(&self.profile)
+  }
+
+  /// 获取维度取值
+  pub fn get(&self, name: &str) -> Option<&str> {
+    if name == PROFILE_DIMENSION {
+      return Some(&self.profile);
+    }
+    self.values.get(name).map(String::as_str)
+  }
+
+  /// 遍历 profile 以外的维度取值
+  pub fn extra(&self) -> impl Iterator<Item = (&str, &str)> {
+    self
+      .values
+      .iter()
+      .map(|(name, value)| (name.as_str(), value.as_str()))
+  }
+
+  /// 产物目录和缓存使用的标签，只有 profile 时即为 profile 名
+  pub fn label(&self) -> String {
+    let mut label = self.profile.clone();
+    for (name, value) in &self.values {
+      label.push_str(&format!(",{name}={value}"));
+    }
+    label
+  }
+
+  /// 校验所选维度均已声明，返回按声明顺序排列的取值
+  pub(crate) fn ordered<'a>(
+    &'a self,
+    specs: &'a [DimensionSpec],
+  ) -> Result<Vec<(&'a DimensionSpec, &'a str)>> {
+    for name in self.values.keys() {
+      if !specs.iter().any(|spec| &spec.name == name) {
+        return Err(
+          Error::new(CONFIGERR_DIMENSIONNOTFOUND)
+            .wrap_context("dimension not declared")
+            .wrap_context_with(|| format!("dimension={name} path={DIMENSIONS_FILE}")),
+        );
+      }
+    }
+
+    Ok(
+      specs
+        .iter()
+        .filter_map(|spec| Some((spec, self.values.get(&spec.name)?.as_str())))
+        .collect(),
+    )
+  }
+}
+
+impl From<&str> for Dimensions {
+  fn from(profile: &str) -> Self {
+    Self::new(profile)
+  }
+}
+
+impl fmt::Display for Dimensions {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.label())
+  }
+}
+
+impl FromStr for Dimensions {
+  type Err = Error;
+
+  fn from_str(selector: &str) -> Result<Self> {
+    let invalid = || {
+      Error::new(CONFIGERR_INVALIDSTRUCTURE)
+        .wrap_context("dimension selector must be name=value pairs including profile")
+        .wrap_context_with(|| format!("selector={selector}"))
+    };
+
+    let mut profiles = Vec::new();
+    let mut dimensions = Self::new("");
+    for part in selector.split(',').map(str::trim) {
+      let Some((name, value)) = part.split_once('=') else {
+        // 开头不带维度名的部分依次叠加为 profile
+        if part.is_empty() || !dimensions.values.is_empty() {
+          return Err(invalid());
+        }
+        profiles.push(part);
+        continue;
+      };
+
+      let (name, value) = (name.trim(), value.trim());
+      let duplicated = if name == PROFILE_DIMENSION {
+        !profiles.is_empty()
+      } else {
+        dimensions.values.contains_key(name)
+      };
+      if name.is_empty() || value.is_empty() || duplicated {
+        return Err(invalid());
+      }
+      if name == PROFILE_DIMENSION {
+        profiles.push(value);
+      } else {
+        dimensions.set(name, value);
+      }
+    }
+
+    if profiles.is_empty() {
+      return Err(invalid());
+    }
+    dimensions.profile = normalize_profile_stack(&profiles.join(","));
+    Ok(dimensions)
+  }
+}
@@ -1,25 +1,51 @@
 //! 配置引擎模块
 
+use std::collections::BTreeMap;
 use std::env;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use bodhi_error::prelude::*;
 use serde::de::DeserializeOwned;
 use toml::Value;
 
 use crate::codegen::{
-  RustCodegenOptions, RustCodegenResult, render_layered_rust_types,
-  render_layered_rust_types_report, render_rust_types, render_rust_types_report, write_rust_types,
+  RustCodegenOptions, RustCodegenResult, annotate_optional_fields, inherited_section_types,
+  render_layered_rust_types, render_layered_rust_types_report, render_rust_types,
+  render_rust_types_report, rust_output_file_name, write_rust_types,
+};
+use crate::constraint::Constraints;
+use crate::dimension::{
+  DimensionSpec, Dimensions, dimension_values, load_dimension_specs, normalize_profile_stack,
 };
 use crate::errcode::configerr::*;
-use crate::loader::{discover_profiles, discover_services, ensure_config_dir, find_config_dir};
-use crate::merge::deep_merge;
-use crate::output::{OutputFormat, serialize_value, write_product};
+use crate::hash::ContentHash;
+use crate::loader::{
+  discover_product_instances, discover_product_profile_services, discover_product_profiles,
+  discover_product_services, discover_profiles, discover_services, ensure_config_dir,
+  find_config_dir, load_base_constraints, load_base_template, load_constraints,
+  load_profile_locals, load_template_chain,
+};
+use crate::merge::{deep_merge, merge_all};
+use crate::output::{OutputFormat, read_product, serialize_value, write_product};
+use crate::overrides::Overrides;
+use crate::resolve::{
+  Provenance, profile_instances, profile_services, resolve_layers_overridden, resolve_provenance,
+  service_target, split_service_target,
+};
+use crate::rule::{ConfigRule, RuleRegistry};
+use crate::source::{
+  ConfigSource, EmbeddedSource, FsSource, LayeredSource, PROFILE_DIR, SearchSource,
+};
+use crate::validate::{service_schema, validate_template_markers};
 
 /// 配置引擎
 #[derive(Debug)]
 pub struct ConfigEngine {
-  config_dir: PathBuf,
+  source: Arc<dyn ConfigSource>,
+  product_format: Option<OutputFormat>,
+  overrides: Overrides,
+  rules: RuleRegistry,
 }
 
 impl ConfigEngine {
@@ -27,7 +53,43 @@ impl ConfigEngine {
   pub fn new(config_dir: impl AsRef<Path>) -> Result<Self> {
     let config_dir = config_dir.as_ref().to_path_buf();
     ensure_config_dir(&config_dir)?;
-    Ok(Self { config_dir })
+    Ok(Self::from_source(FsSource::new(config_dir)))
+  }
+
+  /// 从任意配置来源创建配置引擎
+  pub fn from_source(source: impl ConfigSource + 'static) -> Self {
+    Self::from_shared_source(Arc::new(source))
+  }
+
+  /// 使用编译期嵌入的配置创建配置引擎
+  ///
+  /// 能从当前工作目录向上找到 `overlay_dir` 时，其中的同名文件优先于嵌入的文件。
+  pub fn embedded(embedded: EmbeddedSource, overlay_dir: impl AsRef<Path>) -> Self {
+    let overlay_dir = env::current_dir()
+      .ok()
+      .and_then(|current_dir| find_config_dir(&current_dir, overlay_dir.as_ref()).ok());
+
+    let mut source = LayeredSource::new();
+    if let Some(overlay_dir) = overlay_dir {
+      source = source.with_layer(FsSource::new(overlay_dir));
+    }
+    Self::from_source(source.with_layer(embedded))
+  }
+
+  /// 从共享的配置来源创建配置引擎
+  pub fn from_shared_source(source: Arc<dyn ConfigSource>) -> Self {
+    Self {
+      source,
+      product_format: None,
+      overrides: Overrides::new(),
+      rules: RuleRegistry::new(),
+    }
+  }
+
+  /// 创建延迟查找配置目录的配置引擎，每次解析时从当前工作目录向上重新查找，直到找到为止
+  pub(crate) fn deferred(config_dir: &Path) -> Self {
+    let current_dir = env::current_dir().unwrap_or_default();
+    Self::from_source(SearchSource::new(current_dir, config_dir))
   }
 
   /// 从当前工作目录向上查找配置目录并创建配置引擎
@@ -42,49 +104,238 @@ impl ConfigEngine {
   /// 从指定起始目录向上查找配置目录并创建配置引擎
   pub fn find_from(start_dir: impl AsRef<Path>, config_dir: impl AsRef<Path>) -> Result<Self> {
     let config_dir = find_config_dir(start_dir.as_ref(), config_dir.as_ref())?;
-    Ok(Self { config_dir })
+    Ok(Self::from_source(FsSource::new(config_dir)))
+  }
+
+  /// 切换为产物模式，从 `product/<profile>/<format>/` 下的预生成产物读取配置
+  ///
+  /// 产物模式不再读取模板和 profile，也就无法解析 service 配置结构。
+  pub fn with_products(mut self, format: OutputFormat) -> Self {
+    self.product_format = Some(format);
+    self
+  }
+
+  /// 获取产物模式使用的产物格式，模板模式返回 `None`
+  pub fn product_format(&self) -> Option<OutputFormat> {
+    self.product_format
+  }
+
+  /// 附加临时覆盖，作为优先级最高的覆盖层参与每次解析
+  ///
+  /// 临时覆盖按模板校验，产物模式下无法使用。
+  pub fn with_overrides(mut self, overrides: Overrides) -> Self {
+    self.overrides = overrides;
+    self
+  }
+
+  /// 获取附加的临时覆盖
+  pub fn overrides(&self) -> &Overrides {
+    &self.overrides
   }
 
-  /// 获取配置根目录
-  pub fn config_dir(&self) -> &Path {
-    &self.config_dir
+  /// 注册一条配置规则，每次解析后执行
+  pub fn with_rule(mut self, rule: impl ConfigRule + 'static) -> Self {
+    self.rules.register(rule);
+    self
+  }
+
+  /// 替换全部配置规则
+  pub fn with_rules(mut self, rules: RuleRegistry) -> Self {
+    self.rules = rules;
+    self
+  }
+
+  /// 获取已注册的配置规则
+  pub fn rules(&self) -> &RuleRegistry {
+    &self.rules
+  }
+
+  /// 获取配置来源
+  pub fn source(&self) -> &dyn ConfigSource {
+    self.source.as_ref()
+  }
+
+  /// 获取本地配置根目录，非文件系统来源返回 `None`
+  pub fn config_dir(&self) -> Option<&Path> {
+    self.source.local_dir()
   }
 
   /// 列出所有服务
   pub fn services(&self) -> Result<Vec<String>> {
-    discover_services(&self.config_dir)
+    match self.product_format {
+      None => discover_services(self.source()),
+      Some(format) => discover_product_services(self.source(), format),
+    }
+  }
+
+  /// 列出 profile 启用的服务，产物模式下为该 profile 已生成产物的服务
+  pub fn enabled_services(&self, profile: &str) -> Result<Vec<String>> {
+    match self.product_format {
+      None => profile_services(self.source(), profile),
+      Some(format) => {
+        let profile = normalize_profile_stack(profile);
+        discover_product_profile_services(self.source(), &profile, format)
+      }
+    }
   }
 
   /// 列出所有 profile
   pub fn profiles(&self) -> Result<Vec<String>> {
-    discover_profiles(&self.config_dir)
+    match self.product_format {
+      None => discover_profiles(self.source()),
+      Some(format) => discover_product_profiles(self.source(), format),
+    }
+  }
+
+  /// 列出按顺序声明的 profile 以外的解析维度
+  pub fn dimensions(&self) -> Result<Vec<DimensionSpec>> {
+    load_dimension_specs(self.source())
+  }
+
+  /// 列出指定维度的全部取值
+  pub fn dimension_values(&self, dimension: &str) -> Result<Vec<String>> {
+    let specs = self.dimensions()?;
+    let spec = specs
+      .iter()
+      .find(|spec| spec.name == dimension)
+      .ok_or_else(|| Error::new(CONFIGERR_DIMENSIONNOTFOUND))
+      .wrap_context("dimension not declared")
+      .wrap_context_with(|| format!("dimension={dimension}"))?;
+    dimension_values(self.source(), spec)
+  }
+
+  /// 列出 profile 为指定服务声明的实例
+  pub fn instances(&self, profile: &str, service: &str) -> Result<Vec<String>> {
+    match self.product_format {
+      None => profile_instances(self.source(), profile, service),
+      Some(format) => {
+        let profile = normalize_profile_stack(profile);
+        discover_product_instances(self.source(), &profile, service, format)
+      }
+    }
   }
 
   /// 解析指定 profile 和 service 的最终配置
+  ///
+  /// `profile` 可写作 `dev,stanley` 以从左到右叠加多个 profile，
+  /// `service` 可写作 `gateway@gw-2` 以选择 profile 中声明的实例。
   pub fn resolve(&self, profile: &str, service: &str) -> Result<ResolvedConfig> {
-    crate::resolve::resolve(&self.config_dir, profile, service)
+    Ok(
+      self
+        .resolve_layers(profile, service)?
+        .into_resolved_config(),
+    )
   }
 
   /// 解析指定 profile 和 service 的分层配置
   pub fn resolve_layers(&self, profile: &str, service: &str) -> Result<ResolvedLayers> {
-    crate::resolve::resolve_layers(&self.config_dir, profile, service)
+    self.resolve_layers_with(&Dimensions::new(profile), service)
+  }
+
+  /// 按维度取值解析最终配置，例如 `profile=prod,region=eu`
+  pub fn resolve_with(&self, dimensions: &Dimensions, service: &str) -> Result<ResolvedConfig> {
+    Ok(
+      self
+        .resolve_layers_with(dimensions, service)?
+        .into_resolved_config(),
+    )
+  }
+
+  /// 按维度取值解析分层配置
+  ///
+  /// 产物模式下读取 [`crate::output::product_dir`] 对应目录下的产物。解析结果需通过单服务规则。
+  pub fn resolve_layers_with(
+    &self,
+    dimensions: &Dimensions,
+    service: &str,
+  ) -> Result<ResolvedLayers> {
+    let resolved = self.resolve_layers_unchecked(dimensions, service)?;
+    self.rules.check_service(service, &resolved)?;
+    Ok(resolved)
+  }
+
+  fn resolve_layers_unchecked(
+    &self,
+    dimensions: &Dimensions,
+    service: &str,
+  ) -> Result<ResolvedLayers> {
+    match self.product_format {
+      None => resolve_layers_overridden(self.source(), dimensions, &self.overrides, service),
+      Some(format) if self.overrides.is_empty() => {
+        Ok(read_product(self.source(), &dimensions.label(), service, format)?.1)
+      }
+      Some(format) => Err(
+        Error::new(CONFIGERR_UNSUPPORTEDOPERATION)
+          .wrap_context("overrides require templates, unavailable in product mode")
+          .wrap_context_with(|| format!("service={service} product_format={}", format.as_str())),
+      ),
+    }
+  }
+
+  /// 解析分层配置并返回每个叶子值的来源层
+  pub fn provenance(&self, dimensions: &Dimensions, service: &str) -> Result<Provenance> {
+    if let Some(format) = self.product_format {
+      return Err(
+        Error::new(CONFIGERR_UNSUPPORTEDOPERATION)
+          .wrap_context("provenance requires templates, unavailable in product mode")
+          .wrap_context_with(|| format!("service={service} product_format={}", format.as_str())),
+      );
+    }
+
+    Ok(resolve_provenance(self.source(), dimensions, &self.overrides, service)?.1)
   }
 
   /// 解析指定 service 的配置结构
   pub fn resolve_service_schema(&self, service: &str) -> Result<ResolvedConfig> {
-    crate::resolve::resolve_service_schema(&self.config_dir, service)
+    Ok(
+      self
+        .resolve_service_schema_layers(service)?
+        .into_resolved_config(),
+    )
   }
 
   /// 解析指定 service 的分层配置结构
   pub fn resolve_service_schema_layers(&self, service: &str) -> Result<ResolvedLayers> {
-    crate::resolve::resolve_service_schema_layers(&self.config_dir, service)
+    if let Some(format) = self.product_format {
+      return Err(
+        Error::new(CONFIGERR_UNSUPPORTEDOPERATION)
+          .wrap_context("service schema requires templates, unavailable in product mode")
+          .wrap_context_with(|| format!("service={service} product_format={}", format.as_str())),
+      );
+    }
+
+    crate::resolve::resolve_service_schema_layers(self.source(), service)
   }
 
-  /// 生成指定 profile 下全部服务的产物
+  /// 生成指定 profile 下全部服务及其实例的产物
   pub fn generate(&self, profile: &str, formats: &[OutputFormat]) -> Result<()> {
-    let services = self.services()?;
-    for service in services {
-      self.generate_service(profile, &service, formats)?;
+    self.generate_with(&Dimensions::new(profile), formats)
+  }
+
+  /// 解析指定 profile 下全部服务及其实例并执行全部配置规则
+  pub fn check_rules(&self, profile: &str) -> Result<()> {
+    self.check_rules_with(&Dimensions::new(profile))
+  }
+
+  /// 按维度取值解析 profile 启用的全部服务及其实例并执行全部配置规则
+  pub fn check_rules_with(&self, dimensions: &Dimensions) -> Result<()> {
+    let resolved = self.resolve_enabled_targets(dimensions)?;
+    self.rules.check_profile(&dimensions.label(), &resolved)
+  }
+
+  /// 按维度取值生成 profile 启用的全部服务及其实例的产物，生成前执行全部配置规则
+  ///
+  /// 每个目标只解析一次，规则检查和写入产物使用同一份解析结果。
+  pub fn generate_with(&self, dimensions: &Dimensions, formats: &[OutputFormat]) -> Result<()> {
+    self.local_dir("generate products")?;
+    let resolved = self.resolve_enabled_targets(dimensions)?;
+    if !self.rules.is_empty() {
+      self.rules.check_profile(&dimensions.label(), &resolved)?;
+    }
+
+    let local_overrides = self.local_overrides(dimensions)?;
+    for (target, layers) in &resolved {
+      self.write_products(dimensions, target, layers, &local_overrides, formats)?;
     }
     Ok(())
   }
@@ -96,18 +347,80 @@ impl ConfigEngine {
     service: &str,
     formats: &[OutputFormat],
   ) -> Result<()> {
-    let resolved = self.resolve(profile, service)?;
+    self.generate_service_with(&Dimensions::new(profile), service, formats)
+  }
+
+  /// 按维度取值生成指定服务的产物
+  ///
+  /// 产物目录见 [`crate::output::product_dir`]，例如 `prod,region=eu` 写入
+  /// `product/prod/dimensions/region/eu/<format>/`。
+  pub fn generate_service_with(
+    &self,
+    dimensions: &Dimensions,
+    service: &str,
+    formats: &[OutputFormat],
+  ) -> Result<()> {
+    self.local_dir("generate products")?;
+    let resolved = self.resolve_layers_with(dimensions, service)?;
+    let local_overrides = self.local_overrides(dimensions)?;
+    self.write_products(dimensions, service, &resolved, &local_overrides, formats)
+  }
+
+  /// 解析 profile 启用的全部服务及其实例，键为服务目标，例如 `gateway@gw-2`
+  fn resolve_enabled_targets(
+    &self,
+    dimensions: &Dimensions,
+  ) -> Result<BTreeMap<String, ResolvedLayers>> {
+    let mut resolved = BTreeMap::new();
+    for service in self.enabled_services(dimensions.profile())? {
+      resolved.insert(
+        service.clone(),
+        self.resolve_layers_with(dimensions, &service)?,
+      );
+      for instance in self.instances(dimensions.profile(), &service)? {
+        let target = service_target(&service, &instance);
+        let layers = self.resolve_layers_with(dimensions, &target)?;
+        resolved.insert(target, layers);
+      }
+    }
+    Ok(resolved)
+  }
+
+  /// 按格式写入单个目标的产物，`formats` 为空时写入全部格式
+  fn write_products(
+    &self,
+    dimensions: &Dimensions,
+    target: &str,
+    layers: &ResolvedLayers,
+    local_overrides: &[String],
+    formats: &[OutputFormat],
+  ) -> Result<()> {
+    let config_dir = self.local_dir("generate products")?;
     let formats = if formats.is_empty() {
-      OutputFormat::all().to_vec()
+      OutputFormat::all()
     } else {
-      formats.to_vec()
+      formats
     };
+    let label = dimensions.label();
+    for &format in formats {
+      write_product(config_dir, &label, target, layers, local_overrides, format)?;
+    }
+    Ok(())
+  }
 
-    for format in formats {
-      write_product(&self.config_dir, profile, service, resolved.value(), format)?;
+  /// 列出参与解析的本地 profile 覆盖文件路径
+  fn local_overrides(&self, dimensions: &Dimensions) -> Result<Vec<String>> {
+    if self.product_format.is_some() {
+      return Ok(Vec::new());
     }
 
-    Ok(())
+    let mut paths = Vec::new();
+    for profile in dimensions.profiles() {
+      for (name, _) in load_profile_locals(self.source(), profile)? {
+        paths.push(format!("{PROFILE_DIR}/{name}.toml"));
+      }
+    }
+    Ok(paths)
   }
 
   /// 渲染指定服务的 Rust 配置结构定义
@@ -115,6 +428,15 @@ impl ConfigEngine {
     self.render_rust_types_with(profile, service, &RustCodegenOptions::default())
   }
 
+  /// 渲染指定服务的 Rust 配置结构定义及以解析结果为字面量的 `CONFIG` 静态实例
+  pub fn render_rust_values(&self, profile: &str, service: &str) -> Result<String> {
+    let options = RustCodegenOptions {
+      with_values: true,
+      ..RustCodegenOptions::default()
+    };
+    self.render_rust_types_with(profile, service, &options)
+  }
+
   /// 按 service 配置结构渲染 Rust 配置结构定义
   pub fn render_service_rust_types(&self, service: &str) -> Result<String> {
     self.render_service_rust_types_with(service, &RustCodegenOptions::default())
@@ -127,13 +449,112 @@ impl ConfigEngine {
     service: &str,
     options: &RustCodegenOptions,
   ) -> Result<String> {
+    let options = self.codegen_options(service, options)?;
+    let [infra, service, merged] = self.codegen_layers(profile, service)?;
+    render_layered_rust_types(&infra, &service, &merged, &options)
+  }
+
+  /// 需要生成 `validate()` 时按服务加载约束，需要复用基础模板结构时计算继承的配置段
+  fn codegen_options(
+    &self,
+    service: &str,
+    options: &RustCodegenOptions,
+  ) -> Result<RustCodegenOptions> {
+    let mut options = options.clone();
+    if !options.with_validate && options.base_types.is_none() {
+      return Ok(options);
+    }
+    if let Some(format) = self.product_format {
+      return Err(
+        Error::new(CONFIGERR_UNSUPPORTEDOPERATION)
+          .wrap_context("validate generation and base type reuse require templates, unavailable in product mode")
+          .wrap_context_with(|| format!("service={service} product_format={}", format.as_str())),
+      );
+    }
+
+    let (service, _) = split_service_target(service);
+    if options.with_validate {
+      options.constraints = load_constraints(self.source())?.service(service);
+    }
+    if options.base_types.is_some() {
+      if options.with_values {
+        return Err(
+          Error::new(CONFIGERR_UNSUPPORTEDOPERATION)
+            .wrap_context("base type reuse cannot render value literals")
+            .wrap_context_with(|| format!("service={service}")),
+        );
+      }
+      let chain = load_template_chain(self.source(), service)?;
+      if chain.len() > 1 {
+        let merge = |chain: &[(String, Value)]| {
+          merge_all(chain.iter().rev().map(|(_, template)| template.clone()))
+        };
+        options.shared_types = inherited_section_types(
+          &service_schema(&merge(&chain[1..])),
+          &service_schema(&merge(&chain)),
+          &options,
+        )?;
+      }
+    }
+    Ok(options)
+  }
+
+  /// 渲染基础模板的 Rust 配置结构定义，继承它的服务可通过 `RustCodegenOptions::base_types` 复用
+  pub fn render_base_rust_types(&self, base: &str) -> Result<String> {
+    self.render_base_rust_types_with(base, &RustCodegenOptions::default())
+  }
+
+  /// 按指定选项渲染基础模板的 Rust 配置结构定义，基础模板没有解析值，不支持 `with_values`
+  pub fn render_base_rust_types_with(
+    &self,
+    base: &str,
+    options: &RustCodegenOptions,
+  ) -> Result<String> {
+    if let Some(format) = self.product_format {
+      return Err(
+        Error::new(CONFIGERR_UNSUPPORTEDOPERATION)
+          .wrap_context("base template requires templates, unavailable in product mode")
+          .wrap_context_with(|| format!("base={base} product_format={}", format.as_str())),
+      );
+    }
+    if options.with_values {
+      return Err(
+        Error::new(CONFIGERR_UNSUPPORTEDOPERATION)
+          .wrap_context("base template has no resolved values to render")
+          .wrap_context_with(|| format!("base={base}")),
+      );
+    }
+
+    let template = load_base_template(self.source(), base)?;
+    validate_template_markers(&template, &format!("template.base.{base}"))?;
+    let mut options = options.clone();
+    if options.with_validate {
+      options.constraints = Constraints {
+        service: load_base_constraints(self.source(), base)?,
+        ..Constraints::default()
+      };
+    }
+    render_rust_types(&service_schema(&template), &options)
+  }
+
+  /// 获取用于生成 Rust 结构的 infra、service、合并配置，按模板补充可选字段标记
+  fn codegen_layers(&self, profile: &str, service: &str) -> Result<[Value; 3]> {
     let resolved = self.resolve_layers(profile, service)?;
-    render_layered_rust_types(
-      resolved.infra(),
-      resolved.service(),
-      resolved.merged(),
-      options,
-    )
+    if self.product_format.is_some() {
+      return Ok([
+        resolved.infra().clone(),
+        resolved.service().clone(),
+        resolved.merged().clone(),
+      ]);
+    }
+
+    let (service, _) = split_service_target(service);
+    let schema = self.resolve_service_schema_layers(service)?;
+    Ok([
+      annotate_optional_fields(resolved.infra(), schema.infra()),
+      annotate_optional_fields(resolved.service(), schema.service()),
+      annotate_optional_fields(resolved.merged(), schema.merged()),
+    ])
   }
 
   /// 按 service 配置结构和指定选项渲染 Rust 配置结构定义
@@ -142,12 +563,13 @@ impl ConfigEngine {
     service: &str,
     options: &RustCodegenOptions,
   ) -> Result<String> {
+    let options = self.codegen_options(service, options)?;
     let resolved = self.resolve_service_schema_layers(service)?;
     render_layered_rust_types(
       resolved.infra(),
       resolved.service(),
       resolved.merged(),
-      options,
+      &options,
     )
   }
 
@@ -172,13 +594,9 @@ impl ConfigEngine {
     service: &str,
     options: &RustCodegenOptions,
   ) -> Result<RustCodegenResult> {
-    let resolved = self.resolve_layers(profile, service)?;
-    render_layered_rust_types_report(
-      resolved.infra(),
-      resolved.service(),
-      resolved.merged(),
-      options,
-    )
+    let options = self.codegen_options(service, options)?;
+    let [infra, service, merged] = self.codegen_layers(profile, service)?;
+    render_layered_rust_types_report(&infra, &service, &merged, &options)
   }
 
   /// 按 service 配置结构和指定选项渲染 Rust 配置结构定义和规则命中报告
@@ -187,12 +605,13 @@ impl ConfigEngine {
     service: &str,
     options: &RustCodegenOptions,
   ) -> Result<RustCodegenResult> {
+    let options = self.codegen_options(service, options)?;
     let resolved = self.resolve_service_schema_layers(service)?;
     render_layered_rust_types_report(
       resolved.infra(),
       resolved.service(),
       resolved.merged(),
-      options,
+      &options,
     )
   }
 
@@ -211,6 +630,17 @@ impl ConfigEngine {
     )
   }
 
+  /// 生成指定服务的 Rust 配置结构及 `CONFIG` 静态实例文件
+  pub fn generate_rust_values(
+    &self,
+    profile: &str,
+    service: &str,
+    output_path: impl AsRef<Path>,
+  ) -> Result<()> {
+    let content = self.render_rust_values(profile, service)?;
+    write_rust_types(output_path.as_ref(), &content)
+  }
+
   /// 按 service 配置结构生成 Rust 配置结构文件
   pub fn generate_service_rust_types(
     &self,
@@ -342,52 +772,73 @@ impl ConfigEngine {
     options: &RustCodegenOptions,
   ) -> Result<()> {
     for service in services {
-      let output_path = output_dir.join(format!("{}_config.rs", service));
+      let output_path = output_dir.join(rust_output_file_name(service));
       self.generate_rust_types_with(profile, service, output_path, options)?;
     }
 
     Ok(())
   }
 
-  /// 获取默认 Rust 结构输出目录
-  pub fn default_rust_output_dir(&self, profile: &str) -> PathBuf {
-    self.config_dir.join("product").join(profile).join("rust")
+  /// 获取默认 Rust 结构输出目录，配置来源没有本地目录时返回错误
+  pub fn default_rust_output_dir(&self, profile: &str) -> Result<PathBuf> {
+    let config_dir = self.local_dir("resolve default rust output dir")?;
+    Ok(rust_output_dir(config_dir, profile))
   }
 
-  /// 获取 workspace 级 Rust 结构输出目录
-  pub fn default_target_rust_output_dir(&self) -> PathBuf {
-    self.workspace_root().join("target").join("bodhi_config")
+  /// 获取 workspace 级 Rust 结构输出目录，配置来源没有本地目录时返回错误
+  pub fn default_target_rust_output_dir(&self) -> Result<PathBuf> {
+    let config_dir = self.local_dir("resolve workspace rust output dir")?;
+    Ok(target_rust_output_dir(config_dir))
   }
 
-  /// 获取默认 Rust 结构输出路径
-  pub fn default_rust_output_path(&self, profile: &str, service: &str) -> PathBuf {
-    self
-      .default_rust_output_dir(profile)
-      .join(format!("{}_config.rs", service))
+  /// 获取默认 Rust 结构输出路径，配置来源没有本地目录时返回错误
+  pub fn default_rust_output_path(&self, profile: &str, service: &str) -> Result<PathBuf> {
+    Ok(
+      self
+        .default_rust_output_dir(profile)?
+        .join(rust_output_file_name(service)),
+    )
   }
 
-  /// 获取 workspace 级服务 Rust 结构输出路径
-  pub fn default_target_rust_output_path(&self, service: &str) -> PathBuf {
-    self
-      .default_target_rust_output_dir()
-      .join(service)
-      .join("config.rs")
+  /// 获取 workspace 级服务 Rust 结构输出路径，配置来源没有本地目录时返回错误
+  pub fn default_target_rust_output_path(&self, service: &str) -> Result<PathBuf> {
+    Ok(
+      self
+        .default_target_rust_output_dir()?
+        .join(service)
+        .join("config.rs"),
+    )
   }
 
-  fn workspace_root(&self) -> &Path {
+  fn local_dir(&self, operation: &str) -> Result<&Path> {
     self
-      .config_dir
-      .parent()
-      .unwrap_or(self.config_dir.as_path())
+      .source
+      .local_dir()
+      .ok_or_else(|| Error::new(CONFIGERR_UNSUPPORTEDOPERATION))
+      .wrap_context("operation requires a local config directory")
+      .wrap_context_with(|| format!("operation={operation} source={}", self.source.describe()))
   }
 }
 
+fn rust_output_dir(config_dir: &Path, profile: &str) -> PathBuf {
+  config_dir
+    .join("product")
+    .join(normalize_profile_stack(profile))
+    .join("rust")
+}
+
+fn target_rust_output_dir(config_dir: &Path) -> PathBuf {
+  let workspace_root = config_dir.parent().unwrap_or(config_dir);
+  workspace_root.join("target").join("bodhi_config")
+}
+
 /// 分层解析后的配置
 #[derive(Clone, Debug)]
 pub struct ResolvedLayers {
   infra: Value,
   service: Value,
   merged: Value,
+  content_hash: ContentHash,
 }
 
 impl ResolvedLayers {
@@ -398,11 +849,13 @@ impl ResolvedLayers {
     let mut merged = infra.clone();
     deep_merge(&mut merged, &service);
     ensure_table(&merged, "merged config must be a table")?;
+    let content_hash = ContentHash::of_layers(&infra, &service);
 
     Ok(Self {
       infra,
       service,
       merged,
+      content_hash,
     })
   }
 
@@ -421,6 +874,11 @@ impl ResolvedLayers {
     &self.merged
   }
 
+  /// 获取分层配置的内容哈希
+  pub fn content_hash(&self) -> ContentHash {
+    self.content_hash
+  }
+
   /// 提取指定路径的 infra 层类型化配置
   pub fn extract_infra<T>(&self, path: &str) -> Result<T>
   where
@@ -452,7 +910,7 @@ impl ResolvedLayers {
 
   /// 转换为现有的最终配置对象
   pub fn into_resolved_config(self) -> ResolvedConfig {
-    ResolvedConfig::new(self.merged)
+    ResolvedConfig::new(self.merged, self.content_hash)
   }
 }
 
@@ -460,11 +918,20 @@ impl ResolvedLayers {
 #[derive(Debug)]
 pub struct ResolvedConfig {
   value: Value,
+  content_hash: ContentHash,
 }
 
 impl ResolvedConfig {
-  pub(crate) fn new(value: Value) -> Self {
-    Self { value }
+  pub(crate) fn new(value: Value, content_hash: ContentHash) -> Self {
+    Self {
+      value,
+      content_hash,
+    }
+  }
+
+  /// 获取来源分层配置的内容哈希
+  pub fn content_hash(&self) -> ContentHash {
+    self.content_hash
   }
 
   /// 获取配置原始值
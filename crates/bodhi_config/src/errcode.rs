@@ -34,5 +34,29 @@ bodhi_error::define_error_codes! {
     TypeMismatch = -115,
     /// Rust 代码生成失败
     CodegenFailed = -116,
+    /// 配置校验钩子未通过
+    ValidationFailed = -117,
+    /// 配置快照版本不存在
+    VersionNotFound = -118,
+    /// 配置缓存校验失败
+    CacheCorrupted = -119,
+    /// 当前配置来源不支持该操作
+    UnsupportedOperation = -120,
+    /// 产物文件不合法
+    ProductInvalid = -121,
+    /// 配置来源不可用
+    SourceUnavailable = -122,
+    /// Service 实例不存在
+    InstanceNotFound = -123,
+    /// 解析维度未声明或取值不存在
+    DimensionNotFound = -124,
+    /// profile 缺少模板声明的必填字段
+    RequiredFieldMissing = -125,
+    /// 配置规则未通过
+    RuleViolated = -126,
+    /// service 模板声明的 infra 模块不存在
+    InfraModuleNotFound = -127,
+    /// Service 在 profile 中未启用
+    ServiceDisabled = -128,
   }
 }
@@ -0,0 +1,126 @@
+//! 配置内容哈希模块
+
+use std::fmt;
+use std::str::FromStr;
+
+use bodhi_error::prelude::*;
+use sha2::{Digest, Sha256};
+use toml::Value;
+
+use crate::errcode::configerr::*;
+
+/// 配置内容哈希
+///
+/// 基于分层配置的规范化编码计算 SHA-256，与进程、平台和表键顺序无关，可用于跨进程比较配置。
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ContentHash([u8; 32]);
+
+impl ContentHash {
+  /// 计算 infra 层和 service 层配置的内容哈希
+  pub fn of_layers(infra: &Value, service: &Value) -> Self {
+    let mut hasher = Sha256::new();
+    hasher.update(b"infra");
+    hash_value(&mut hasher, infra);
+    hasher.update(b"service");
+    hash_value(&mut hasher, service);
+    Self(hasher.finalize().into())
+  }
+
+  /// 获取原始哈希字节
+  pub fn as_bytes(&self) -> &[u8; 32] {
+    &self.0
+  }
+
+  /// 获取完整的十六进制表示
+  pub fn to_hex(&self) -> String {
+    self.0.iter().map(|byte| format!("{byte:02x}")).collect()
+  }
+
+  /// 获取用于日志展示的短哈希
+  pub fn short(&self) -> String {
+    self.to_hex()[..12].to_string()
+  }
+}
+
+impl fmt::Display for ContentHash {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.to_hex())
+  }
+}
+
+impl fmt::Debug for ContentHash {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "ContentHash({})", self.to_hex())
+  }
+}
+
+impl FromStr for ContentHash {
+  type Err = Error;
+
+  fn from_str(value: &str) -> Result<Self> {
+    let invalid = || {
+      Error::new(CONFIGERR_PARSEFAILED)
+        .wrap_context("invalid content hash")
+        .wrap_context_with(|| format!("hash={value}"))
+    };
+
+    if value.len() != 64 || !value.is_ascii() {
+      return Err(invalid());
+    }
+
+    let mut bytes = [0u8; 32];
+    for (index, byte) in bytes.iter_mut().enumerate() {
+      *byte = u8::from_str_radix(&value[index * 2..index * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(Self(bytes))
+  }
+}
+
+fn hash_value(hasher: &mut Sha256, value: &Value) {
+  match value {
+    Value::String(text) => {
+      hasher.update(b"s");
+      hash_bytes(hasher, text.as_bytes());
+    }
+    Value::Integer(number) => {
+      hasher.update(b"i");
+      hasher.update(number.to_le_bytes());
+    }
+    Value::Float(number) => {
+      hasher.update(b"f");
+      hasher.update(number.to_bits().to_le_bytes());
+    }
+    Value::Boolean(flag) => {
+      hasher.update(b"b");
+      hasher.update([u8::from(*flag)]);
+    }
+    Value::Datetime(datetime) => {
+      hasher.update(b"d");
+      hash_bytes(hasher, datetime.to_string().as_bytes());
+    }
+    Value::Array(items) => {
+      hasher.update(b"a");
+      hasher.update((items.len() as u64).to_le_bytes());
+      for item in items {
+        hash_value(hasher, item);
+      }
+    }
+    Value::Table(table) => {
+      // 显式排序，避免依赖 toml 表的内部键顺序
+      let mut keys: Vec<_> = table.keys().collect();
+      keys.sort();
+
+      hasher.update(b"t");
+      hasher.update((keys.len() as u64).to_le_bytes());
+      for key in keys {
+        hash_bytes(hasher, key.as_bytes());
+        hash_value(hasher, &table[key.as_str()]);
+      }
+    }
+  }
+}
+
+fn hash_bytes(hasher: &mut Sha256, bytes: &[u8]) {
+  hasher.update((bytes.len() as u64).to_le_bytes());
+  hasher.update(bytes);
+}
@@ -1,27 +1,50 @@
 //! # Bodhi 配置模块
 
+pub mod cache;
 pub mod codegen;
+pub mod constraint;
+pub mod dimension;
 pub mod engine;
 pub mod errcode;
+pub mod hash;
 pub mod loader;
 pub mod merge;
 pub mod output;
+pub mod overrides;
+pub mod profile;
 pub mod resolve;
+pub mod rule;
 pub mod runtime;
+pub mod source;
+pub mod typed;
 pub mod validate;
 
+#[doc(hidden)]
+pub use regex;
 #[doc(hidden)]
 pub use toml;
 
-pub use bodhi_config_macros::service_config;
+pub use bodhi_config_macros::{embed_config, service_config};
 
 pub use crate::codegen::{
   RustCodegenOptions, RustCodegenResult, TypeOverrideHit, TypeOverrideRule, TypeOverrideRules,
   TypeOverrideSource,
 };
+pub use crate::dimension::{DimensionSpec, Dimensions};
 pub use crate::engine::{ConfigEngine, ResolvedConfig, ResolvedLayers};
+pub use crate::hash::ContentHash;
 pub use crate::output::OutputFormat;
-pub use crate::runtime::{ConfigSnapshot, ConfigStore};
+pub use crate::overrides::Overrides;
+pub use crate::profile::{DEFAULT_PROFILE_ENV, select_profile};
+pub use crate::resolve::Provenance;
+pub use crate::rule::{ConfigRule, RuleRegistry, RuleViolation};
+pub use crate::runtime::{
+  ConfigSnapshot, ConfigStore, ConfigStoreBuilder, ConfigStoreStats, SnapshotHandle,
+};
+pub use crate::source::{
+  ConfigSource, EmbeddedSource, FsSource, HttpSource, LayeredSource, MemorySource, SearchSource,
+};
+pub use crate::typed::BodhiConfig;
 
 use std::path::Path;
 
@@ -59,19 +82,57 @@ pub fn load_layered_config_from(
   engine.resolve_layers(profile, service)
 }
 
+pub fn load_product_config<T>(
+  profile: &str,
+  service: &str,
+  format: OutputFormat,
+) -> prelude::Result<T>
+where
+  T: DeserializeOwned,
+{
+  load_product_config_from("config", profile, service, format)
+}
+
+pub fn load_product_config_from<T>(
+  config_dir: impl AsRef<Path>,
+  profile: &str,
+  service: &str,
+  format: OutputFormat,
+) -> prelude::Result<T>
+where
+  T: DeserializeOwned,
+{
+  let engine = ConfigEngine::find(config_dir)?.with_products(format);
+  engine.resolve(profile, service)?.extract(".")
+}
+
 /// 预导入模块
 pub mod prelude {
   pub use crate::codegen::{
     RustCodegenOptions, RustCodegenResult, TypeOverrideHit, TypeOverrideRule, TypeOverrideRules,
     TypeOverrideSource,
   };
+  pub use crate::dimension::{DimensionSpec, Dimensions};
   pub use crate::engine::{ConfigEngine, ResolvedConfig, ResolvedLayers};
   pub use crate::errcode::configerr::*;
+  pub use crate::hash::ContentHash;
   pub use crate::load_config;
   pub use crate::load_config_from;
   pub use crate::load_layered_config;
   pub use crate::load_layered_config_from;
+  pub use crate::load_product_config;
+  pub use crate::load_product_config_from;
   pub use crate::output::OutputFormat;
-  pub use crate::runtime::{ConfigSnapshot, ConfigStore};
+  pub use crate::overrides::Overrides;
+  pub use crate::profile::{DEFAULT_PROFILE_ENV, select_profile};
+  pub use crate::resolve::Provenance;
+  pub use crate::rule::{ConfigRule, RuleRegistry, RuleViolation};
+  pub use crate::runtime::{
+    ConfigSnapshot, ConfigStore, ConfigStoreBuilder, ConfigStoreStats, SnapshotHandle,
+  };
+  pub use crate::source::{
+    ConfigSource, EmbeddedSource, FsSource, HttpSource, LayeredSource, MemorySource, SearchSource,
+  };
+  pub use crate::typed::BodhiConfig;
   pub use bodhi_error::prelude::{Error, OptionExt, Result, ResultExt};
 }
@@ -1,14 +1,35 @@
 //! 配置加载模块
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use bodhi_error::prelude::*;
 use toml::Value;
 
+use crate::constraint::{
+  CONSTRAINT_KEYS, Constraint, ConstraintSchema, ConstraintSet, SCHEMA_SIDECAR_SUFFIX,
+};
+use crate::dimension::validate_profile_name;
 use crate::errcode::configerr::*;
-use crate::merge::merge_all;
+use crate::merge::{
+  MERGE_RULES_FILE, MergeRuleSet, MergeRules, MergeStrategy, deep_merge, merge_all,
+};
+use crate::output::OutputFormat;
+use crate::resolve::INSTANCE_SEPARATOR;
+use crate::source::{
+  BASE_TEMPLATE_DIR, ConfigSource, INFRA_TEMPLATE_DIR, PRODUCT_DIR, PROFILE_DIR,
+  SERVICE_TEMPLATE_DIR, read_toml,
+};
+use crate::validate::service_infra;
+
+/// profile 本地覆盖文件名中的标记，例如 `profile/dev.local.toml`
+pub const LOCAL_PROFILE_MARKER: &str = "local";
+/// 指定本地覆盖文件用户名的环境变量，例如 `profile/dev.local.stanley.toml`
+pub const LOCAL_PROFILE_USER_ENV: &str = "BODHI_CONFIG_USER";
+/// 模板中声明继承基础模板的键，例如 `extends = "base_server"`
+pub const EXTENDS_KEY: &str = "extends";
 
 pub fn ensure_config_dir(config_dir: &Path) -> Result<()> {
   if config_dir.is_dir() {
@@ -47,117 +68,434 @@ pub fn find_config_dir(start_dir: &Path, config_dir: &Path) -> Result<PathBuf> {
   )
 }
 
-pub fn discover_services(config_dir: &Path) -> Result<Vec<String>> {
-  let service_dir = config_dir.join("template").join("service");
-  list_toml_stems(&service_dir, CONFIGERR_TEMPLATEDIRNOTFOUND)
+pub fn discover_services(source: &dyn ConfigSource) -> Result<Vec<String>> {
+  source.service_names()
 }
 
-pub fn discover_profiles(config_dir: &Path) -> Result<Vec<String>> {
-  let profile_dir = config_dir.join("profile");
-  list_toml_stems(&profile_dir, CONFIGERR_PROFILEDIRNOTFOUND)
+/// 列出 profile，本地覆盖文件 `<name>.local[.<user>].toml` 不计入
+pub fn discover_profiles(source: &dyn ConfigSource) -> Result<Vec<String>> {
+  let mut profiles = source.profile_names()?;
+  profiles.retain(|profile| !is_local_profile(profile));
+  for profile in &profiles {
+    validate_profile_name(profile)
+      .wrap_context_with(|| format!("source={} dir={PROFILE_DIR}", source.describe()))?;
+  }
+  Ok(profiles)
 }
 
-pub fn load_infra_configs(config_dir: &Path) -> Result<Value> {
-  let infra_dir = config_dir.join("template").join("infra");
-  let paths = list_toml_files(&infra_dir, CONFIGERR_TEMPLATEDIRNOTFOUND)?;
-  let mut values = Vec::with_capacity(paths.len());
-  for path in paths {
-    values.push(load_toml_file(&path)?);
+pub fn load_infra_configs(source: &dyn ConfigSource) -> Result<Value> {
+  let names = source.infra_template_names()?;
+  let mut values = Vec::with_capacity(names.len());
+  for name in names {
+    values.push(source.infra_template(&name)?);
   }
   Ok(merge_all(values))
 }
 
-pub fn load_service_template(config_dir: &Path, service: &str) -> Result<Value> {
-  let path = config_dir
-    .join("template")
-    .join("service")
-    .join(format!("{service}.toml"));
+/// 读取 service 使用的 infra 模板，按 service 模板声明的 `infra_modules` 筛选
+pub fn load_service_infra_configs(source: &dyn ConfigSource, service: &str) -> Result<Value> {
+  let service_cfg = load_service_template(source, service)?;
+  service_infra(service, &load_infra_configs(source)?, &service_cfg)
+}
+
+/// 读取 service 模板，按 `extends` 链合并基础模板，越近的模板优先
+pub fn load_service_template(source: &dyn ConfigSource, service: &str) -> Result<Value> {
+  Ok(merge_chain(load_template_chain(source, service)?))
+}
+
+/// 读取基础模板，按 `extends` 链合并更上层的基础模板
+pub fn load_base_template(source: &dyn ConfigSource, base: &str) -> Result<Value> {
+  let chain = resolve_template_chain(
+    source,
+    base_template_path(base),
+    source.base_template(base)?,
+  )?;
+  Ok(merge_chain(chain))
+}
+
+/// 读取 service 模板的 `extends` 链，从 service 自身到最远的基础模板，各模板已去掉 `extends`
+///
+/// 链中每项为模板文件路径和内容，例如 `template/base/base_server.toml`。
+pub fn load_template_chain(
+  source: &dyn ConfigSource,
+  service: &str,
+) -> Result<Vec<(String, Value)>> {
+  resolve_template_chain(
+    source,
+    format!("{SERVICE_TEMPLATE_DIR}/{service}.toml"),
+    source.service_template(service)?,
+  )
+}
+
+fn base_template_path(base: &str) -> String {
+  format!("{BASE_TEMPLATE_DIR}/{base}.toml")
+}
+
+fn resolve_template_chain(
+  source: &dyn ConfigSource,
+  label: String,
+  template: Value,
+) -> Result<Vec<(String, Value)>> {
+  let mut chain = vec![(label, template)];
+  loop {
+    let (label, template) = chain
+      .last_mut()
+      .expect("template chain should not be empty");
+    let Some(base) = take_extends(template, label)? else {
+      return Ok(chain);
+    };
+    let label = label.clone();
 
-  if !path.is_file() {
-    return Err(
-      Error::new(CONFIGERR_SERVICENOTFOUND)
-        .wrap_context_with(|| format!("service={service} path={} not found", path.display())),
-    );
+    let base_label = base_template_path(&base);
+    if chain.iter().any(|(label, _)| *label == base_label) {
+      let labels: Vec<_> = chain.iter().map(|(label, _)| label.as_str()).collect();
+      return Err(
+        Error::new(CONFIGERR_INVALIDSTRUCTURE)
+          .wrap_context("template extends chain forms a cycle")
+          .wrap_context_with(|| format!("chain={} -> {base_label}", labels.join(" -> "))),
+      );
+    }
+    let base_template = source
+      .base_template(&base)
+      .wrap_context_with(|| format!("template={label} extends={base}"))?;
+    chain.push((base_label, base_template));
   }
+}
 
-  load_toml_file(&path)
+fn take_extends(template: &mut Value, label: &str) -> Result<Option<String>> {
+  let Some(table) = template.as_table_mut() else {
+    return Ok(None);
+  };
+  match table.remove(EXTENDS_KEY) {
+    None => Ok(None),
+    Some(Value::String(base)) if !base.is_empty() => Ok(Some(base)),
+    Some(_) => Err(
+      Error::new(CONFIGERR_INVALIDSTRUCTURE)
+        .wrap_context("template extends must name a base template")
+        .wrap_context_with(|| format!("template={label}")),
+    ),
+  }
 }
 
-pub fn load_service_templates(config_dir: &Path) -> Result<BTreeMap<String, Value>> {
-  let services = discover_services(config_dir)?;
+/// 从最远的基础模板开始合并模板链
+fn merge_chain(chain: Vec<(String, Value)>) -> Value {
+  merge_all(chain.into_iter().rev().map(|(_, template)| template))
+}
+
+pub fn load_service_templates(source: &dyn ConfigSource) -> Result<BTreeMap<String, Value>> {
+  let services = discover_services(source)?;
   let mut templates = BTreeMap::new();
 
   for service in services {
-    let template = load_service_template(config_dir, &service)?;
+    let template = load_service_template(source, &service)?;
     templates.insert(service, template);
   }
 
   Ok(templates)
 }
 
-pub fn load_profile(config_dir: &Path, profile: &str) -> Result<Value> {
-  let path = config_dir.join("profile").join(format!("{profile}.toml"));
+/// 读取 profile，并合并存在的本地覆盖文件
+pub fn load_profile(source: &dyn ConfigSource, profile: &str) -> Result<Value> {
+  let mut profile_cfg = source.profile(profile)?;
+  for (_, local_cfg) in load_profile_locals(source, profile)? {
+    deep_merge(&mut profile_cfg, &local_cfg);
+  }
+  Ok(profile_cfg)
+}
 
-  if !path.is_file() {
-    return Err(
-      Error::new(CONFIGERR_PROFILENOTFOUND)
-        .wrap_context_with(|| format!("profile={profile} path={} not found", path.display())),
-    );
+/// 读取 profile 的本地覆盖文件，按 `<name>.local`、`<name>.local.<user>` 的顺序返回存在的文件
+///
+/// 本地覆盖文件不纳入版本管理，用户名取自 [`LOCAL_PROFILE_USER_ENV`]，未设置时取 `USER`/`USERNAME`。
+pub fn load_profile_locals(
+  source: &dyn ConfigSource,
+  profile: &str,
+) -> Result<Vec<(String, Value)>> {
+  let mut locals = Vec::new();
+  for name in local_profile_names(profile) {
+    let path = format!("{PROFILE_DIR}/{name}.toml");
+    if let Some(local_cfg) = read_toml(source, &path)? {
+      locals.push((name, local_cfg));
+    }
   }
+  Ok(locals)
+}
 
-  load_toml_file(&path)
+/// 获取 profile 本地覆盖文件名（不含扩展名），例如 `dev.local` 和 `dev.local.stanley`
+pub fn local_profile_names(profile: &str) -> Vec<String> {
+  let mut names = vec![format!("{profile}.{LOCAL_PROFILE_MARKER}")];
+  if let Some(user) = local_profile_user() {
+    names.push(format!("{profile}.{LOCAL_PROFILE_MARKER}.{user}"));
+  }
+  names
 }
 
-pub fn load_toml_file(path: &Path) -> Result<Value> {
-  let content = fs::read_to_string(path)
-    .map_err(Error::from_std)
-    .wrap_context("read config file failed")
-    .wrap_context_with(|| format!("path={}", path.display()))?;
+/// 判断 profile 目录下的文件名是否为本地覆盖文件
+pub fn is_local_profile(name: &str) -> bool {
+  name.split('.').nth(1) == Some(LOCAL_PROFILE_MARKER)
+}
 
-  toml::from_str::<Value>(&content)
-    .map_err(Error::from_std)
-    .wrap_context("parse toml file failed")
-    .wrap_context_with(|| format!("path={}", path.display()))
+fn local_profile_user() -> Option<String> {
+  [LOCAL_PROFILE_USER_ENV, "USER", "USERNAME"]
+    .into_iter()
+    .filter_map(|key| env::var(key).ok())
+    .map(|user| user.trim().to_string())
+    .find(|user| {
+      !user.is_empty()
+        && !user
+          .chars()
+          .any(|ch| matches!(ch, '.' | '/' | '\\') || ch.is_whitespace())
+    })
 }
 
-fn list_toml_files(dir: &Path, missing_code: i32) -> Result<Vec<PathBuf>> {
-  if !dir.is_dir() {
-    return Err(
-      Error::new(missing_code).wrap_context_with(|| format!("dir={} not found", dir.display())),
-    );
+/// 读取合并规则文件，文件不存在时返回空规则
+pub fn load_merge_rules(source: &dyn ConfigSource) -> Result<MergeRuleSet> {
+  let mut rule_set = MergeRuleSet::default();
+  let Some(rules_cfg) = read_toml(source, MERGE_RULES_FILE)? else {
+    return Ok(rule_set);
+  };
+
+  let rules_table = expect_rules_table(&rules_cfg, "", "merge rules root must be a table")?;
+  for (key, value) in rules_table {
+    match key.as_str() {
+      "infra" => collect_merge_rules(value, "infra", "", &mut rule_set.infra)?,
+      "services" => {
+        let services = expect_rules_table(value, key, "merge rules services must be a table")?;
+        for (service, service_rules) in services {
+          let rules = rule_set.services.entry(service.clone()).or_default();
+          collect_merge_rules(service_rules, &format!("services.{service}"), "", rules)?;
+        }
+      }
+      _ => {
+        return Err(invalid_merge_rules(
+          key,
+          "merge rules root only allows infra and services",
+        ));
+      }
+    }
   }
+  Ok(rule_set)
+}
 
-  let mut paths = Vec::new();
-  for entry in fs::read_dir(dir)
-    .map_err(Error::from_std)
-    .wrap_context("read config directory failed")
-    .wrap_context_with(|| format!("dir={}", dir.display()))?
-  {
-    let entry = entry
-      .map_err(Error::from_std)
-      .wrap_context("read directory entry failed")
-      .wrap_context_with(|| format!("dir={}", dir.display()))?;
-    let path = entry.path();
-    if path.extension().and_then(|ext| ext.to_str()) == Some("toml") {
-      paths.push(path);
+/// 收集规则表中的策略，嵌套表按点分隔路径展开
+fn collect_merge_rules(
+  value: &Value,
+  section: &str,
+  path: &str,
+  rules: &mut MergeRules,
+) -> Result<()> {
+  if let Some(strategy) = MergeStrategy::from_value(value) {
+    rules.insert(path, strategy);
+    return Ok(());
+  }
+
+  match value {
+    Value::Table(table) if !table.contains_key("strategy") => {
+      for (key, child) in table {
+        let child_path = if path.is_empty() {
+          key.clone()
+        } else {
+          format!("{path}.{key}")
+        };
+        collect_merge_rules(child, section, &child_path, rules)?;
+      }
+      Ok(())
+    }
+    _ => Err(invalid_merge_rules(
+      &format!("{section}.{path}"),
+      "invalid merge strategy",
+    )),
+  }
+}
+
+fn expect_rules_table<'a>(
+  value: &'a Value,
+  path: &str,
+  reason: &str,
+) -> Result<&'a toml::map::Map<String, Value>> {
+  value
+    .as_table()
+    .ok_or_else(|| invalid_merge_rules(path, reason))
+}
+
+fn invalid_merge_rules(path: &str, reason: &str) -> Error {
+  Error::new(CONFIGERR_INVALIDSTRUCTURE)
+    .wrap_context(reason.to_string())
+    .wrap_context_with(|| format!("file={MERGE_RULES_FILE} path={path}"))
+}
+
+/// 读取模板旁的约束文件，例如 `template/service/gateway.schema.toml`
+pub fn load_constraints(source: &dyn ConfigSource) -> Result<ConstraintSchema> {
+  let mut schema = ConstraintSchema::default();
+  for name in source.infra_template_names()? {
+    let file = format!("{INFRA_TEMPLATE_DIR}/{name}{SCHEMA_SIDECAR_SUFFIX}.toml");
+    if let Some(constraints_cfg) = read_toml(source, &file)? {
+      collect_constraints(&constraints_cfg, &file, "", &mut schema.infra)?;
+    }
+  }
+  for service in discover_services(source)? {
+    let constraints = chain_constraints(source, &load_template_chain(source, &service)?)?;
+    if !constraints.is_empty() {
+      schema.services.insert(service, constraints);
+    }
+  }
+  Ok(schema)
+}
+
+/// 读取基础模板及其 `extends` 链上的约束文件
+pub fn load_base_constraints(source: &dyn ConfigSource, base: &str) -> Result<ConstraintSet> {
+  let chain = resolve_template_chain(
+    source,
+    base_template_path(base),
+    source.base_template(base)?,
+  )?;
+  chain_constraints(source, &chain)
+}
+
+/// 基础模板的约束文件随 `extends` 链继承，越近的模板优先
+fn chain_constraints(
+  source: &dyn ConfigSource,
+  chain: &[(String, Value)],
+) -> Result<ConstraintSet> {
+  let mut constraints = ConstraintSet::new();
+  for (template, _) in chain.iter().rev() {
+    let stem = template.strip_suffix(".toml").unwrap_or(template);
+    let file = format!("{stem}{SCHEMA_SIDECAR_SUFFIX}.toml");
+    if let Some(constraints_cfg) = read_toml(source, &file)? {
+      let mut layer = ConstraintSet::new();
+      collect_constraints(&constraints_cfg, &file, "", &mut layer)?;
+      constraints.extend(&layer);
+    }
+  }
+  Ok(constraints)
+}
+
+/// 收集约束，表中的约束键构成该路径的约束，其余键按点分隔路径展开
+fn collect_constraints(
+  value: &Value,
+  file: &str,
+  path: &str,
+  constraints: &mut ConstraintSet,
+) -> Result<()> {
+  let table = value
+    .as_table()
+    .ok_or_else(|| Error::new(CONFIGERR_INVALIDSTRUCTURE))
+    .wrap_context("constraint entry must be a table")
+    .wrap_context_with(|| format!("file={file} path={path}"))?;
+
+  let (rules, children): (toml::Table, toml::Table) = table
+    .clone()
+    .into_iter()
+    .partition(|(key, value)| CONSTRAINT_KEYS.contains(&key.as_str()) && !value.is_table());
+  if !rules.is_empty() {
+    if path.is_empty() {
+      return Err(
+        Error::new(CONFIGERR_INVALIDSTRUCTURE)
+          .wrap_context("constraint must be declared under a field path")
+          .wrap_context_with(|| format!("file={file}")),
+      );
     }
+    let constraint =
+      Constraint::from_table(&rules, path).wrap_context_with(|| format!("file={file}"))?;
+    constraints.insert(path, constraint);
   }
 
-  paths.sort();
-  Ok(paths)
+  for (key, child) in &children {
+    let child_path = if path.is_empty() {
+      key.clone()
+    } else {
+      format!("{path}.{key}")
+    };
+    collect_constraints(child, file, &child_path, constraints)?;
+  }
+  Ok(())
 }
 
-fn list_toml_stems(dir: &Path, missing_code: i32) -> Result<Vec<String>> {
-  let mut names = Vec::new();
-  for path in list_toml_files(dir, missing_code)? {
-    let stem = path
-      .file_stem()
-      .and_then(|value| value.to_str())
-      .ok_or_else(|| Error::new(CONFIGERR_FILELOADFAILED))
-      .wrap_context("get file stem failed")
-      .wrap_context_with(|| format!("path={}", path.display()))?;
-    names.push(stem.to_string());
+/// 列出存在指定格式产物的 profile
+pub fn discover_product_profiles(
+  source: &dyn ConfigSource,
+  format: OutputFormat,
+) -> Result<Vec<String>> {
+  let entries = source
+    .list(PRODUCT_DIR)?
+    .ok_or_else(|| Error::new(CONFIGERR_PROFILEDIRNOTFOUND))
+    .wrap_context_with(|| format!("source={} dir={PRODUCT_DIR} not found", source.describe()))?;
+
+  let mut profiles = Vec::new();
+  // 带维度的产物嵌套在 profile 目录下，早期布局的 `prod,region=eu` 目录不计入 profile
+  for profile in entries.into_iter().filter(|entry| !entry.contains(',')) {
+    let format_dir = format!("{PRODUCT_DIR}/{profile}/{}", format.as_str());
+    if source.list(&format_dir)?.is_some() {
+      profiles.push(profile);
+    }
   }
 
-  Ok(names)
+  profiles.sort();
+  Ok(profiles)
+}
+
+/// 列出任一 profile 下存在指定格式产物的 service
+pub fn discover_product_services(
+  source: &dyn ConfigSource,
+  format: OutputFormat,
+) -> Result<Vec<String>> {
+  let mut services = BTreeSet::new();
+  for profile in discover_product_profiles(source, format)? {
+    services.extend(discover_product_profile_services(source, &profile, format)?);
+  }
+
+  Ok(services.into_iter().collect())
+}
+
+/// 列出指定 profile 下存在指定格式产物的服务
+pub fn discover_product_profile_services(
+  source: &dyn ConfigSource,
+  profile: &str,
+  format: OutputFormat,
+) -> Result<Vec<String>> {
+  let suffix = format!(".{}", format.extension());
+  let format_dir = format!("{PRODUCT_DIR}/{profile}/{}", format.as_str());
+  let mut services = Vec::new();
+  for entry in source.list(&format_dir)?.unwrap_or_default() {
+    if let Some(service) = entry.strip_suffix(&suffix)
+      && !service.contains(INSTANCE_SEPARATOR)
+    {
+      services.push(service.to_string());
+    }
+  }
+  services.sort();
+
+  Ok(services)
+}
+
+/// 列出指定 profile 下某服务已生成产物的实例
+pub fn discover_product_instances(
+  source: &dyn ConfigSource,
+  profile: &str,
+  service: &str,
+  format: OutputFormat,
+) -> Result<Vec<String>> {
+  let format_dir = format!("{PRODUCT_DIR}/{profile}/{}", format.as_str());
+  let suffix = format!(".{}", format.extension());
+  let prefix = format!("{service}{INSTANCE_SEPARATOR}");
+  let mut instances: Vec<_> = source
+    .list(&format_dir)?
+    .unwrap_or_default()
+    .iter()
+    .filter_map(|entry| entry.strip_suffix(&suffix)?.strip_prefix(&prefix))
+    .map(str::to_string)
+    .collect();
+  instances.sort();
+  Ok(instances)
+}
+
+pub fn load_toml_file(path: &Path) -> Result<Value> {
+  let content = fs::read_to_string(path)
+    .map_err(Error::from_std)
+    .wrap_context("read config file failed")
+    .wrap_context_with(|| format!("path={}", path.display()))?;
+
+  toml::from_str::<Value>(&content)
+    .map_err(Error::from_std)
+    .wrap_context("parse toml file failed")
+    .wrap_context_with(|| format!("path={}", path.display()))
 }
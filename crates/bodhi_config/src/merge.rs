@@ -1,38 +1,253 @@
 //! 配置合并模块
 
+use std::collections::BTreeMap;
+
 use toml::Value;
 
+/// 覆盖层中列出待删除键的保留键，例如 `__unset = ["tls"]` 删除同一表下继承的 `tls`
+pub const UNSET_KEY: &str = "__unset";
+/// 模板中列出可选字段的保留键，例如 `__optional = ["tls"]`，合并时取并集
+///
+/// 没有默认值的可选字段写作带类型的条目 `{ name = "idle_timeout", type = "integer" }`。
+pub const OPTIONAL_KEY: &str = "__optional";
+
 /// 深度合并配置值
 ///
 /// 合并策略：
-/// - Table: 递归合并
-/// - Array: 整体替换
+/// - Table: 递归合并，先删除 [`UNSET_KEY`] 列出的键
+/// - Array: 整体替换，需要其他策略时使用 [`deep_merge_with`]
 /// - Scalar: 后值覆盖前值
 pub fn deep_merge(base: &mut Value, overlay: &Value) {
+  deep_merge_with(base, overlay, &MergeRules::new());
+}
+
+/// 按顺序合并多个配置值
+pub fn merge_all<I>(values: I) -> Value
+where
+  I: IntoIterator<Item = Value>,
+{
+  let mut merged = Value::Table(Default::default());
+  for value in values {
+    deep_merge(&mut merged, &value);
+  }
+  merged
+}
+
+/// 按路径声明合并策略的规则文件，位于配置根目录
+pub const MERGE_RULES_FILE: &str = "merge_rules.toml";
+
+/// 数组合并策略
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum MergeStrategy {
+  /// 后值整体替换前值
+  #[default]
+  Replace,
+  /// 追加到前值末尾
+  Append,
+  /// 插入到前值开头
+  Prepend,
+  /// 追加前值中不存在的元素
+  Union,
+  /// 按表元素的指定键合并，键相同的元素递归合并，其余追加
+  MergeByKey(String),
+}
+
+impl MergeStrategy {
+  /// 从规则文件中的值解析合并策略
+  ///
+  /// 取值为 `"replace"`、`"append"`、`"prepend"`、`"union"`，
+  /// 或 `{ strategy = "merge_by_key", key = "name" }`。
+  pub fn from_value(value: &Value) -> Option<Self> {
+    match value {
+      Value::String(strategy) => match strategy.as_str() {
+        "replace" => Some(Self::Replace),
+        "append" => Some(Self::Append),
+        "prepend" => Some(Self::Prepend),
+        "union" => Some(Self::Union),
+        _ => None,
+      },
+      Value::Table(table) => match (table.get("strategy"), table.get("key"), table.len()) {
+        (Some(Value::String(strategy)), Some(Value::String(key)), 2)
+          if strategy == "merge_by_key" && !key.is_empty() =>
+        {
+          Some(Self::MergeByKey(key.clone()))
+        }
+        _ => None,
+      },
+      _ => None,
+    }
+  }
+
+  fn merge_array(&self, base: &mut Vec<Value>, overlay: &[Value], path: &str, rules: &MergeRules) {
+    match self {
+      Self::Replace => *base = overlay.iter().map(strip_unset).collect(),
+      Self::Append => base.extend(overlay.iter().map(strip_unset)),
+      Self::Prepend => {
+        base.splice(0..0, overlay.iter().map(strip_unset));
+      }
+      Self::Union => {
+        for item in overlay {
+          if !base.contains(item) {
+            base.push(strip_unset(item));
+          }
+        }
+      }
+      Self::MergeByKey(key) => {
+        for item in overlay {
+          let item_key = item.get(key);
+          let matched = base
+            .iter_mut()
+            .find(|base_item| item_key.is_some() && base_item.get(key) == item_key);
+          match matched {
+            Some(base_item) => merge_at(base_item, item, path, rules),
+            None => base.push(strip_unset(item)),
+          }
+        }
+      }
+    }
+  }
+}
+
+/// 按点分隔路径声明的合并策略，路径相对 infra 层或 service 层根
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MergeRules {
+  strategies: BTreeMap<String, MergeStrategy>,
+}
+
+impl MergeRules {
+  /// 创建空规则，所有数组整体替换
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// 声明路径的合并策略
+  pub fn with(mut self, path: &str, strategy: MergeStrategy) -> Self {
+    self.insert(path, strategy);
+    self
+  }
+
+  /// 声明路径的合并策略
+  pub fn insert(&mut self, path: &str, strategy: MergeStrategy) {
+    self.strategies.insert(path.to_string(), strategy);
+  }
+
+  /// 获取路径的合并策略，未声明时为整体替换
+  pub fn strategy(&self, path: &str) -> &MergeStrategy {
+    static REPLACE: MergeStrategy = MergeStrategy::Replace;
+    self.strategies.get(path).unwrap_or(&REPLACE)
+  }
+
+  /// 遍历已声明的路径和合并策略
+  pub fn iter(&self) -> impl Iterator<Item = (&str, &MergeStrategy)> {
+    self
+      .strategies
+      .iter()
+      .map(|(path, strategy)| (path.as_str(), strategy))
+  }
+
+  /// 是否没有声明任何策略
+  pub fn is_empty(&self) -> bool {
+    self.strategies.is_empty()
+  }
+}
+
+/// 规则文件声明的全部合并规则
+///
+/// ```toml
+/// [infra]
+/// "net.allowed_origins" = "union"
+///
+/// [services.gateway]
+/// routes = { strategy = "merge_by_key", key = "name" }
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MergeRuleSet {
+  /// infra 层规则
+  pub infra: MergeRules,
+  /// 各服务 service 层规则
+  pub services: BTreeMap<String, MergeRules>,
+}
+
+impl MergeRuleSet {
+  /// 获取指定服务的 service 层规则
+  pub fn service(&self, service: &str) -> MergeRules {
+    self.services.get(service).cloned().unwrap_or_default()
+  }
+}
+
+/// 按合并规则深度合并配置值，未声明策略的数组整体替换
+pub fn deep_merge_with(base: &mut Value, overlay: &Value, rules: &MergeRules) {
+  merge_at(base, overlay, "", rules);
+}
+
+fn merge_at(base: &mut Value, overlay: &Value, path: &str, rules: &MergeRules) {
   match (base, overlay) {
     (Value::Table(base_table), Value::Table(overlay_table)) => {
+      for key in unset_keys(overlay) {
+        base_table.remove(key);
+      }
       for (key, overlay_value) in overlay_table {
+        if key == UNSET_KEY {
+          continue;
+        }
         if let Some(base_value) = base_table.get_mut(key) {
-          deep_merge(base_value, overlay_value);
+          if let (OPTIONAL_KEY, Value::Array(base_items), Value::Array(overlay_items)) =
+            (key.as_str(), &mut *base_value, overlay_value)
+          {
+            MergeStrategy::Union.merge_array(base_items, overlay_items, path, rules);
+            continue;
+          }
+          merge_at(base_value, overlay_value, &join_path(path, key), rules);
         } else {
-          base_table.insert(key.clone(), overlay_value.clone());
+          base_table.insert(key.clone(), strip_unset(overlay_value));
         }
       }
     }
+    (Value::Array(base_items), Value::Array(overlay_items)) => {
+      rules
+        .strategy(path)
+        .merge_array(base_items, overlay_items, path, rules);
+    }
     (base_value, overlay_value) => {
-      *base_value = overlay_value.clone();
+      *base_value = strip_unset(overlay_value);
     }
   }
 }
 
-/// 按顺序合并多个配置值
-pub fn merge_all<I>(values: I) -> Value
-where
-  I: IntoIterator<Item = Value>,
-{
-  let mut merged = Value::Table(Default::default());
-  for value in values {
-    deep_merge(&mut merged, &value);
+/// 判断键是否为 [`UNSET_KEY`] 或 [`OPTIONAL_KEY`] 标记
+pub fn is_marker_key(key: &str) -> bool {
+  key == UNSET_KEY || key == OPTIONAL_KEY
+}
+
+/// 获取表中 [`UNSET_KEY`] 列出的待删除键
+pub fn unset_keys(value: &Value) -> impl Iterator<Item = &str> {
+  value
+    .get(UNSET_KEY)
+    .and_then(Value::as_array)
+    .into_iter()
+    .flatten()
+    .filter_map(Value::as_str)
+}
+
+/// 去掉值中所有的 [`UNSET_KEY`] 标记
+pub fn strip_unset(value: &Value) -> Value {
+  match value {
+    Value::Table(table) => Value::Table(
+      table
+        .iter()
+        .filter(|(key, _)| *key != UNSET_KEY)
+        .map(|(key, child)| (key.clone(), strip_unset(child)))
+        .collect(),
+    ),
+    Value::Array(items) => Value::Array(items.iter().map(strip_unset).collect()),
+    _ => value.clone(),
+  }
+}
+
+fn join_path(path: &str, key: &str) -> String {
+  if path.is_empty() {
+    key.to_string()
+  } else {
+    format!("{path}.{key}")
   }
-  merged
 }
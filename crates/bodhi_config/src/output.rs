@@ -1,15 +1,54 @@
 //! 配置输出模块
 
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use bodhi_error::prelude::*;
+use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use toml::Value;
 
+use crate::engine::ResolvedLayers;
 use crate::errcode::configerr::*;
+use crate::source::{ConfigSource, PRODUCT_DIR};
+
+/// 产物元数据旁路文件的附加后缀，例如 `gateway.json.meta`，内容固定为 TOML
+pub const PRODUCT_META_SUFFIX: &str = "meta";
+
+/// 产物元数据
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ProductMeta {
+  pub profile: String,
+  pub service: String,
+  pub content_hash: String,
+  /// 分层边界，用于从产物重建 infra 层和 service 层
+  pub layers: ProductLayers,
+  /// 参与生成的本地 profile 覆盖文件，例如 `profile/dev.local.toml`
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub local_overrides: Vec<String>,
+}
+
+/// 产物中记录的分层边界
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ProductLayers {
+  /// infra 层顶层键
+  pub infra: Vec<String>,
+  /// service 层顶层键
+  pub service: Vec<String>,
+  /// 两层共有的顶层键在各层中的取值，无法从合并结果中拆分
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub shared: BTreeMap<String, SharedLayers>,
+}
+
+/// 两层共有的顶层键在各层中的取值
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SharedLayers {
+  pub infra: Value,
+  pub service: Value,
+}
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum OutputFormat {
@@ -81,27 +120,286 @@ pub fn serialize_value(value: &Value, format: OutputFormat) -> Result<String> {
   }
 }
 
+/// 按指定格式反序列化配置值
+pub fn parse_value(content: &str, format: OutputFormat) -> Result<Value> {
+  match format {
+    OutputFormat::Toml => toml::from_str(content)
+      .map_err(Error::from_std)
+      .wrap_context("parse toml config failed"),
+    OutputFormat::Json => serde_json::from_str(content)
+      .map_err(Error::from_std)
+      .wrap_context("parse json config failed"),
+    OutputFormat::Yaml => serde_yml::from_str(content)
+      .map_err(Error::from_std)
+      .wrap_context("parse yaml config failed"),
+  }
+}
+
+/// 带维度的产物在 profile 产物目录下的子目录
+pub const PRODUCT_DIMENSIONS_DIR: &str = "dimensions";
+
+/// 把产物标签映射为产物根目录下的相对目录
+///
+/// 标签 `prod,region=eu` 映射为 `prod/dimensions/region/eu`，目录名中不出现 `,` 和 `=`，
+/// 带维度的产物也不会被当作 profile 列出。
+pub fn product_dir(label: &str) -> String {
+  let mut parts = label.split(',');
+  let mut dir = parts.next().unwrap_or_default().to_string();
+  let mut parts = parts.peekable();
+  if parts.peek().is_some() {
+    dir.push('/');
+    dir.push_str(PRODUCT_DIMENSIONS_DIR);
+  }
+  for part in parts {
+    for segment in part.splitn(2, '=') {
+      dir.push('/');
+      dir.push_str(segment);
+    }
+  }
+  dir
+}
+
+/// 获取产物文件路径
+pub fn product_path(
+  config_dir: &Path,
+  profile: &str,
+  service: &str,
+  format: OutputFormat,
+) -> PathBuf {
+  config_dir
+    .join(PRODUCT_DIR)
+    .join(product_dir(profile))
+    .join(format.as_str())
+    .join(format!("{service}.{}", format.extension()))
+}
+
+/// 获取产物元数据旁路文件路径
+pub fn product_meta_path(
+  config_dir: &Path,
+  profile: &str,
+  service: &str,
+  format: OutputFormat,
+) -> PathBuf {
+  let mut path = product_path(config_dir, profile, service, format).into_os_string();
+  path.push(format!(".{PRODUCT_META_SUFFIX}"));
+  PathBuf::from(path)
+}
+
+/// 构建产物元数据
+pub fn product_meta(
+  profile: &str,
+  service: &str,
+  layers: &ResolvedLayers,
+  local_overrides: &[String],
+) -> ProductMeta {
+  ProductMeta {
+    profile: profile.to_string(),
+    service: service.to_string(),
+    content_hash: layers.content_hash().to_hex(),
+    layers: ProductLayers {
+      infra: table_keys(layers.infra()),
+      service: table_keys(layers.service()),
+      shared: shared_layers(layers),
+    },
+    local_overrides: local_overrides.to_vec(),
+  }
+}
+
+/// 写入产物文件及其元数据旁路文件，产物本身只包含最终配置
+///
+/// 有本地覆盖文件参与时，TOML 和 YAML 产物开头带有注释提示；JSON 不支持注释，只记录在元数据中。
 pub fn write_product(
   config_dir: &Path,
   profile: &str,
   service: &str,
-  value: &Value,
+  layers: &ResolvedLayers,
+  local_overrides: &[String],
   format: OutputFormat,
 ) -> Result<()> {
-  let product_dir = config_dir
-    .join("product")
-    .join(profile)
-    .join(format.as_str());
-  fs::create_dir_all(&product_dir)
-    .map_err(Error::from_std)
-    .wrap_context("create product directory failed")
-    .wrap_context_with(|| format!("dir={}", product_dir.display()))?;
+  let path = product_path(config_dir, profile, service, format);
+  if let Some(product_dir) = path.parent() {
+    fs::create_dir_all(product_dir)
+      .map_err(Error::from_std)
+      .wrap_context("create product directory failed")
+      .wrap_context_with(|| format!("dir={}", product_dir.display()))?;
+  }
 
-  let path = product_dir.join(format!("{service}.{}", format.extension()));
-  let content = serialize_value(value, format)?;
+  let mut content = serialize_value(layers.merged(), format)?;
+  if let Some(header) = local_overrides_header(local_overrides, format) {
+    content.insert_str(0, &header);
+  }
+  let meta = product_meta(profile, service, layers, local_overrides);
+  let meta_content = toml::to_string_pretty(&meta)
+    .map_err(Error::from_std)
+    .wrap_context("serialize product meta failed")?;
 
   fs::write(&path, content)
     .map_err(Error::from_std)
     .wrap_context("write product file failed")
-    .wrap_context_with(|| format!("path={}", path.display()))
+    .wrap_context_with(|| format!("path={}", path.display()))?;
+  let meta_path = product_meta_path(config_dir, profile, service, format);
+  fs::write(&meta_path, meta_content)
+    .map_err(Error::from_std)
+    .wrap_context("write product meta file failed")
+    .wrap_context_with(|| format!("path={}", meta_path.display()))
+}
+
+/// 读取产物并按元数据记录的分层边界重建分层配置
+///
+/// 元数据取自旁路文件，缺失时视为产物无效；
+/// 重建后的内容哈希必须与元数据记录一致，否则说明产物被篡改。
+pub fn read_product(
+  source: &dyn ConfigSource,
+  profile: &str,
+  service: &str,
+  format: OutputFormat,
+) -> Result<(ProductMeta, ResolvedLayers)> {
+  let format_dir = format!("{PRODUCT_DIR}/{}/{}", product_dir(profile), format.as_str());
+  let path = format!("{format_dir}/{service}.{}", format.extension());
+  let Some(content) = source.read(&path)? else {
+    let code = if source.list(&format_dir)?.is_some() {
+      CONFIGERR_SERVICENOTFOUND
+    } else {
+      CONFIGERR_PROFILENOTFOUND
+    };
+    return Err(
+      Error::new(code)
+        .wrap_context("product file not found")
+        .wrap_context_with(|| {
+          format!(
+            "profile={profile} service={service} source={} path={path}",
+            source.describe()
+          )
+        }),
+    );
+  };
+
+  let value = parse_value(&content, format).wrap_context_with(|| format!("path={path}"))?;
+  let meta_path = format!("{path}.{PRODUCT_META_SUFFIX}");
+  let meta = source
+    .read(&meta_path)?
+    .ok_or_else(|| Error::new(CONFIGERR_PRODUCTINVALID))
+    .wrap_context("product meta file not found")
+    .wrap_context_with(|| format!("path={meta_path}"))?;
+  let meta = toml::from_str(&meta)
+    .map_err(|err: toml::de::Error| {
+      Error::new(CONFIGERR_PRODUCTINVALID).wrap_context(err.to_string())
+    })
+    .wrap_context("parse product meta failed")
+    .wrap_context_with(|| format!("path={meta_path}"))?;
+  split_product(value, meta, profile, service).wrap_context_with(|| format!("path={path}"))
+}
+
+/// 按元数据拆分产物配置值为分层配置
+pub fn split_product(
+  value: Value,
+  meta: ProductMeta,
+  profile: &str,
+  service: &str,
+) -> Result<(ProductMeta, ResolvedLayers)> {
+  let Value::Table(merged) = value else {
+    return Err(Error::new(CONFIGERR_PRODUCTINVALID).wrap_context("product root must be a table"));
+  };
+
+  if meta.profile != profile || meta.service != service {
+    return Err(
+      Error::new(CONFIGERR_PRODUCTINVALID)
+        .wrap_context("product meta does not match requested target")
+        .wrap_context_with(|| {
+          format!(
+            "product profile={} service={} requested profile={profile} service={service}",
+            meta.profile, meta.service
+          )
+        }),
+    );
+  }
+
+  let shared = &meta.layers.shared;
+  let infra = select_keys(&merged, &meta.layers.infra, |key| {
+    shared.get(key).map(|shared| &shared.infra)
+  })?;
+  let service_layer = select_keys(&merged, &meta.layers.service, |key| {
+    shared.get(key).map(|shared| &shared.service)
+  })?;
+  let layers = ResolvedLayers::new(infra, service_layer)?;
+  if layers.content_hash().to_hex() != meta.content_hash {
+    return Err(
+      Error::new(CONFIGERR_PRODUCTINVALID)
+        .wrap_context("product content hash mismatched")
+        .wrap_context_with(|| {
+          format!(
+            "recorded={} actual={}",
+            meta.content_hash,
+            layers.content_hash()
+          )
+        }),
+    );
+  }
+
+  // 共有键取自元数据，产物中的合并结果也必须与重建结果一致
+  if layers.merged() != &Value::Table(merged) {
+    return Err(
+      Error::new(CONFIGERR_PRODUCTINVALID)
+        .wrap_context("product content mismatched its recorded layers"),
+    );
+  }
+
+  Ok((meta, layers))
+}
+
+/// 本地覆盖文件参与生成时的产物头部注释
+fn local_overrides_header(local_overrides: &[String], format: OutputFormat) -> Option<String> {
+  if local_overrides.is_empty() || format == OutputFormat::Json {
+    return None;
+  }
+  let mut header = String::from(
+    "# 警告：本产物包含本机本地覆盖文件中的配置，不应提交或分发到其他环境
+",
+  );
+  for path in local_overrides {
+    header.push_str(&format!("# local override: {path}\n"));
+  }
+  header.push('\n');
+  Some(header)
+}
+
+fn table_keys(value: &Value) -> Vec<String> {
+  value
+    .as_table()
+    .map(|table| table.keys().cloned().collect())
+    .unwrap_or_default()
+}
+
+fn shared_layers(layers: &ResolvedLayers) -> BTreeMap<String, SharedLayers> {
+  let (Some(infra), Some(service)) = (layers.infra().as_table(), layers.service().as_table())
+  else {
+    return BTreeMap::new();
+  };
+  infra
+    .iter()
+    .filter_map(|(key, infra)| {
+      let shared = SharedLayers {
+        infra: infra.clone(),
+        service: service.get(key)?.clone(),
+      };
+      Some((key.clone(), shared))
+    })
+    .collect()
+}
+
+fn select_keys<'a>(
+  merged: &'a toml::map::Map<String, Value>,
+  keys: &[String],
+  shared: impl Fn(&str) -> Option<&'a Value>,
+) -> Result<Value> {
+  let mut table = toml::map::Map::new();
+  for key in keys {
+    let value = shared(key)
+      .or_else(|| merged.get(key))
+      .ok_or_else(|| Error::new(CONFIGERR_PRODUCTINVALID))
+      .wrap_context("product layer key missing")
+      .wrap_context_with(|| format!("key={key}"))?;
+    table.insert(key.clone(), value.clone());
+  }
+  Ok(Value::Table(table))
 }
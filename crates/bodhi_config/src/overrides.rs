@@ -0,0 +1,138 @@
+//! 临时覆盖模块
+
+use std::fs;
+use std::path::Path;
+
+use bodhi_error::prelude::*;
+use toml::{Table, Value};
+
+use crate::errcode::configerr::*;
+use crate::merge::deep_merge;
+
+/// 临时覆盖在解析层和来源中的标签
+pub const OVERRIDE_LAYER: &str = "override";
+
+/// 不修改配置文件的临时覆盖，作为优先级最高的覆盖层参与解析
+///
+/// 结构与 profile 相同，只允许 `infra` 和 `services`，例如
+/// `services.gateway.server.http_port = 9999`。
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Overrides {
+  table: Table,
+}
+
+impl Overrides {
+  /// 创建空的临时覆盖
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// 设置一个叶子值，`path` 以点分隔
+  pub fn set(&mut self, path: &str, value: impl Into<Value>) -> Result<()> {
+    let segments: Vec<_> = path.split('.').map(str::trim).collect();
+    if segments.iter().any(|segment| segment.is_empty()) {
+      return Err(
+        Error::new(CONFIGERR_INVALIDPATH)
+          .wrap_context("override path must not contain empty segments")
+          .wrap_context_with(|| format!("path={path}")),
+      );
+    }
+
+    let Some((leaf, parents)) = segments.split_last() else {
+      return Ok(());
+    };
+    let mut current = &mut self.table;
+    for segment in parents {
+      current = current
+        .entry(segment.to_string())
+        .or_insert_with(|| Value::Table(Default::default()))
+        .as_table_mut()
+        .ok_or_else(|| Error::new(CONFIGERR_INVALIDPATH))
+        .wrap_context("override path segment target is not a table")
+        .wrap_context_with(|| format!("path={path} segment={segment}"))?;
+    }
+    current.insert(leaf.to_string(), value.into());
+    Ok(())
+  }
+
+  /// 按 `path=value` 设置一个叶子值
+  ///
+  /// 值按 TOML 字面量解析，例如 `9999`、`true`、`"a b"`、`[1, 2]`；
+  /// 不含空白、引号、括号等符号的单词无法解析时视为字符串，便于直接写 `infra.log.level=DEBUG`，
+  /// 其余无法解析的值报错，需要加引号。
+  pub fn set_assignment(&mut self, assignment: &str) -> Result<()> {
+    let (path, literal) = assignment
+      .split_once('=')
+      .ok_or_else(|| Error::new(CONFIGERR_INVALIDSTRUCTURE))
+      .wrap_context("override must be written as path=value")
+      .wrap_context_with(|| format!("override={assignment}"))?;
+    let value =
+      parse_literal(literal.trim()).wrap_context_with(|| format!("override={assignment}"))?;
+    self.set(path.trim(), value)
+  }
+
+  /// 合并一个与 profile 结构相同的覆盖表，后合并的优先
+  pub fn merge(&mut self, overlay: &Value) -> Result<()> {
+    if !overlay.is_table() {
+      return Err(
+        Error::new(CONFIGERR_INVALIDSTRUCTURE)
+          .wrap_context("override overlay root must be a table"),
+      );
+    }
+    let mut merged = Value::Table(std::mem::take(&mut self.table));
+    deep_merge(&mut merged, overlay);
+    if let Value::Table(table) = merged {
+      self.table = table;
+    }
+    Ok(())
+  }
+
+  /// 读取覆盖文件并合并，后合并的优先
+  pub fn merge_file(&mut self, path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path)
+      .map_err(Error::from_std)
+      .wrap_context("read override file failed")
+      .wrap_context_with(|| format!("path={}", path.display()))?;
+    let overlay = toml::from_str::<Value>(&content)
+      .map_err(Error::from_std)
+      .wrap_context("parse override file failed")
+      .wrap_context_with(|| format!("path={}", path.display()))?;
+    self
+      .merge(&overlay)
+      .wrap_context_with(|| format!("path={}", path.display()))
+  }
+
+  /// 是否没有任何覆盖
+  pub fn is_empty(&self) -> bool {
+    self.table.is_empty()
+  }
+
+  /// 获取覆盖表
+  pub fn table(&self) -> &Table {
+    &self.table
+  }
+}
+
+fn parse_literal(literal: &str) -> Result<Value> {
+  let parsed =
+    toml::from_str::<Table>(&format!("value = {literal}")).map(|mut table| table.remove("value"));
+  match parsed {
+    Ok(Some(value)) => Ok(value),
+    _ if is_bare_word(literal) => Ok(Value::String(literal.to_string())),
+    Ok(None) => Err(Error::new(CONFIGERR_PARSEFAILED).wrap_context("override value is empty")),
+    Err(err) => Err(
+      Error::new(CONFIGERR_PARSEFAILED)
+        .wrap_context(err.message().to_string())
+        .wrap_context("override value is not a valid TOML literal, quote strings like \"a b\""),
+    ),
+  }
+}
+
+/// 可以不加引号当作字符串的单词
+fn is_bare_word(literal: &str) -> bool {
+  !literal.is_empty()
+    && !literal.chars().any(|ch| {
+      ch.is_whitespace() || matches!(ch, '"' | '\'' | '[' | ']' | '{' | '}' | ',' | '=' | '#')
+    })
+}
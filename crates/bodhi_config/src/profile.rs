@@ -0,0 +1,58 @@
+//! Profile 选择模块
+
+use std::env;
+
+/// 默认读取 profile 的环境变量
+pub const DEFAULT_PROFILE_ENV: &str = "BODHI_PROFILE";
+
+/// 按命令行 `--profile`、环境变量、默认值的顺序选择当前进程的 profile
+pub fn select_profile(profile_env: &str, default_profile: &str) -> String {
+  select_profile_from(
+    env::args().skip(1),
+    env::var(profile_env).ok(),
+    default_profile,
+  )
+}
+
+/// 按命令行参数、环境变量值、默认值的顺序选择 profile
+pub fn select_profile_from<I, A>(
+  args: I,
+  env_value: Option<String>,
+  default_profile: &str,
+) -> String
+where
+  I: IntoIterator<Item = A>,
+  A: AsRef<str>,
+{
+  profile_from_args(args)
+    .or_else(|| env_value.filter(|value| !value.trim().is_empty()))
+    .unwrap_or_else(|| default_profile.to_string())
+}
+
+/// 从命令行参数中提取 `--profile <name>` 或 `--profile=<name>`
+///
+/// 遇到 `--` 后停止解析，其后的参数留给应用自身。
+pub fn profile_from_args<I, A>(args: I) -> Option<String>
+where
+  I: IntoIterator<Item = A>,
+  A: AsRef<str>,
+{
+  let mut args = args.into_iter();
+  while let Some(arg) = args.next() {
+    let arg = arg.as_ref();
+    if arg == "--" {
+      break;
+    }
+    if arg == "--profile" {
+      return args
+        .next()
+        .map(|value| value.as_ref().to_string())
+        .filter(|value| !value.is_empty());
+    }
+    if let Some(value) = arg.strip_prefix("--profile=") {
+      return (!value.is_empty()).then(|| value.to_string());
+    }
+  }
+
+  None
+}
@@ -1,25 +1,256 @@
 //! 配置解析模块
 
-use std::collections::BTreeMap;
-use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet};
 
 use bodhi_error::prelude::*;
+use serde::Serialize;
 use toml::Value;
 
+use crate::constraint::Constraints;
+use crate::dimension::{
+  Dimensions, load_dimension_overlay, load_dimension_specs, split_profile_stack,
+};
 use crate::engine::{ResolvedConfig, ResolvedLayers};
 use crate::errcode::configerr::*;
-use crate::loader::{load_infra_configs, load_profile, load_service_templates};
-use crate::merge::deep_merge;
-use crate::validate::{service_schema, validate_profile, validate_service_template};
+use crate::loader::{
+  discover_services, load_constraints, load_infra_configs, load_merge_rules, load_profile,
+  load_profile_locals, load_service_templates,
+};
+use crate::merge::{MergeRules, MergeStrategy, UNSET_KEY, deep_merge, deep_merge_with, unset_keys};
+use crate::overrides::{OVERRIDE_LAYER, Overrides};
+use crate::source::ConfigSource;
+use crate::validate::{
+  INSTANCES_KEY, MAP_KEY, is_service_enabled, map_entry_default, schema_child, service_infra,
+  service_schema, strip_template_markers, validate_constraints, validate_dimension_overlay,
+  validate_merge_overlay, validate_merge_rules, validate_overrides, validate_profile_layer,
+  validate_required_fields, validate_service_template, validate_template_markers,
+};
 
-pub fn resolve(config_dir: &Path, profile: &str, service: &str) -> Result<ResolvedConfig> {
-  Ok(resolve_layers(config_dir, profile, service)?.into_resolved_config())
+/// 服务名与实例名之间的分隔符，例如 `gateway@gw-2`
+pub const INSTANCE_SEPARATOR: char = '@';
+
+/// 拆分解析目标为服务名和可选的实例名
+pub fn split_service_target(target: &str) -> (&str, Option<&str>) {
+  match target.split_once(INSTANCE_SEPARATOR) {
+    Some((service, instance)) => (service, Some(instance)),
+    None => (target, None),
+  }
+}
+
+/// 拼接服务名和实例名为解析目标
+pub fn service_target(service: &str, instance: &str) -> String {
+  format!("{service}{INSTANCE_SEPARATOR}{instance}")
+}
+
+/// 列出 profile 启用的服务，profile 叠加时后者的声明优先
+pub fn profile_services(source: &dyn ConfigSource, profile: &str) -> Result<Vec<String>> {
+  let mut stacked_profile = empty_table();
+  for profile in split_profile_stack(profile) {
+    deep_merge(&mut stacked_profile, &load_profile(source, profile)?);
+  }
+  let mut services = discover_services(source)?;
+  services.retain(|service| is_service_enabled(&stacked_profile, service));
+  Ok(services)
+}
+
+/// 列出 profile 中为指定服务声明的实例，profile 叠加时取并集
+pub fn profile_instances(
+  source: &dyn ConfigSource,
+  profile: &str,
+  service: &str,
+) -> Result<Vec<String>> {
+  let mut instances = BTreeSet::new();
+  for profile in split_profile_stack(profile) {
+    let profile_cfg = load_profile(source, profile)?;
+    if let Some(Value::Table(table)) =
+      clone_path(&profile_cfg, &["services", service, INSTANCES_KEY])
+    {
+      instances.extend(table.keys().cloned());
+    }
+  }
+  Ok(instances.into_iter().collect())
+}
+
+pub fn resolve(source: &dyn ConfigSource, profile: &str, service: &str) -> Result<ResolvedConfig> {
+  Ok(resolve_layers(source, profile, service)?.into_resolved_config())
+}
+
+pub fn resolve_layers(
+  source: &dyn ConfigSource,
+  profile: &str,
+  service: &str,
+) -> Result<ResolvedLayers> {
+  resolve_layers_with(source, &Dimensions::new(profile), service)
+}
+
+/// 按维度取值解析分层配置
+pub fn resolve_layers_with(
+  source: &dyn ConfigSource,
+  dimensions: &Dimensions,
+  service: &str,
+) -> Result<ResolvedLayers> {
+  resolve_layers_overridden(source, dimensions, &Overrides::new(), service)
+}
+
+/// 按维度取值解析分层配置，并以临时覆盖作为优先级最高的覆盖层
+pub fn resolve_layers_overridden(
+  source: &dyn ConfigSource,
+  dimensions: &Dimensions,
+  overrides: &Overrides,
+  service: &str,
+) -> Result<ResolvedLayers> {
+  let stack = overlay_layers(source, dimensions, overrides, service)?;
+  let (infra, service_value) = fold_layers(&stack, None);
+  check_constraints(&stack, &infra, &service_value, service)?;
+  ResolvedLayers::new(infra, service_value)
+}
+
+/// 按约束文件检查解析结果，汇总全部违反项后一次报告
+fn check_constraints(
+  stack: &OverlayStack,
+  infra: &Value,
+  service: &Value,
+  target: &str,
+) -> Result<()> {
+  let (service_name, _) = split_service_target(target);
+  let mut violations = stack.constraints.infra.check(infra, "infra");
+  violations.extend(
+    stack
+      .constraints
+      .service
+      .check(service, &format!("services.{service_name}")),
+  );
+  if violations.is_empty() {
+    return Ok(());
+  }
+
+  let count = violations.len();
+  let err = Error::new(CONFIGERR_VALIDATIONFAILED)
+    .wrap_context("resolved config violates schema constraints")
+    .wrap_context_with(|| format!("service={target} violations={count}"));
+  Err(violations.into_iter().fold(err, |err, violation| {
+    err.wrap_context(format!("{violation} rule={}", violation.rule))
+  }))
 }
 
-pub fn resolve_layers(config_dir: &Path, profile: &str, service: &str) -> Result<ResolvedLayers> {
-  let base_infra = load_infra_configs(config_dir)?;
-  let profile_cfg = load_profile(config_dir, profile)?;
-  let service_templates = load_service_templates(config_dir)?;
+/// 解析分层配置并记录每个叶子值的来源层
+pub fn resolve_provenance(
+  source: &dyn ConfigSource,
+  dimensions: &Dimensions,
+  overrides: &Overrides,
+  service: &str,
+) -> Result<(ResolvedLayers, Provenance)> {
+  let mut provenance = Provenance::default();
+  let (infra, service) = fold_layers(
+    &overlay_layers(source, dimensions, overrides, service)?,
+    Some(&mut provenance),
+  );
+  Ok((ResolvedLayers::new(infra, service)?, provenance))
+}
+
+/// 配置叶子值的来源层
+///
+/// 键为叶子路径，值为最后写入该叶子的层，例如 `region.eu.infra`。
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct Provenance {
+  infra: BTreeMap<String, String>,
+  service: BTreeMap<String, String>,
+}
+
+impl Provenance {
+  /// 获取 infra 层叶子来源
+  pub fn infra(&self) -> &BTreeMap<String, String> {
+    &self.infra
+  }
+
+  /// 获取 service 层叶子来源
+  pub fn service(&self) -> &BTreeMap<String, String> {
+    &self.service
+  }
+
+  /// 获取最终合并配置的叶子来源，与合并配置一样 service 层优先
+  pub fn merged(&self) -> BTreeMap<String, String> {
+    let mut merged = self.infra.clone();
+    merged.extend(self.service.clone());
+    merged
+  }
+
+  /// 查询最终合并配置中指定叶子的来源层
+  pub fn layer_of(&self, path: &str) -> Option<&str> {
+    self
+      .service
+      .get(path)
+      .or_else(|| self.infra.get(path))
+      .map(String::as_str)
+  }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum LayerTarget {
+  Infra,
+  Service,
+}
+
+/// 按优先级从低到高排列的一个覆盖层
+#[derive(Debug)]
+struct OverlayLayer {
+  label: String,
+  target: LayerTarget,
+  value: Value,
+}
+
+/// 全部覆盖层及各层使用的合并规则
+#[derive(Debug)]
+struct OverlayStack {
+  layers: Vec<OverlayLayer>,
+  /// 未去掉标记的模板，用于给覆盖层新增的映射条目补全默认值
+  templates: Vec<OverlayLayer>,
+  constraints: Constraints,
+  infra_rules: MergeRules,
+  service_rules: MergeRules,
+}
+
+impl OverlayStack {
+  fn template(&self, target: LayerTarget) -> Option<&OverlayLayer> {
+    self
+      .templates
+      .iter()
+      .find(|template| template.target == target)
+  }
+
+  fn rules(&self, target: LayerTarget) -> &MergeRules {
+    match target {
+      LayerTarget::Infra => &self.infra_rules,
+      LayerTarget::Service => &self.service_rules,
+    }
+  }
+}
+
+/// 收集参与解析的全部覆盖层
+///
+/// 顺序：infra 模板、service 模板、profile（叠加时从左到右）、各维度（按声明顺序）、实例、临时覆盖。
+fn overlay_layers(
+  source: &dyn ConfigSource,
+  dimensions: &Dimensions,
+  overrides: &Overrides,
+  service: &str,
+) -> Result<OverlayStack> {
+  let (service, instance) = split_service_target(service);
+  let base_infra = load_infra_configs(source)?;
+  if dimensions.profiles().is_empty() {
+    return Err(
+      Error::new(CONFIGERR_PROFILENOTFOUND)
+        .wrap_context("resolve profile stack is empty")
+        .wrap_context_with(|| format!("service={service}")),
+    );
+  }
+  // 每个 profile 后紧跟其本地覆盖文件，例如 `dev`、`dev.local`
+  let mut profile_cfgs = Vec::new();
+  for profile in dimensions.profiles() {
+    profile_cfgs.push((profile.to_string(), source.profile(profile)?));
+    profile_cfgs.extend(load_profile_locals(source, profile)?);
+  }
+  let service_templates = load_service_templates(source)?;
   let service_cfg = service_templates.get(service).ok_or_else(|| {
     Error::new(CONFIGERR_SERVICENOTFOUND)
       .wrap_context("resolve target service not found")
@@ -27,39 +258,323 @@ pub fn resolve_layers(config_dir: &Path, profile: &str, service: &str) -> Result
   })?;
 
   validate_templates(&base_infra, &service_templates)?;
-  validate_profile(profile, &profile_cfg, &base_infra, &service_templates)?;
+  // 只保留 service 声明使用的 infra 模块，全局的 infra 覆盖也按此筛选
+  let service_infra = service_infra(service, &base_infra, service_cfg)?;
+  // 叠加 profile 和本地覆盖文件各自只覆盖一部分，必填字段按合并后的 profile 检查
+  let mut stacked_profile = Value::Table(Default::default());
+  for (profile, profile_cfg) in &profile_cfgs {
+    validate_profile_layer(profile, profile_cfg, &base_infra, &service_templates)?;
+    deep_merge(&mut stacked_profile, profile_cfg);
+  }
+  if !is_service_enabled(&stacked_profile, service) {
+    return Err(
+      Error::new(CONFIGERR_SERVICEDISABLED)
+        .wrap_context("resolve target service disabled by profile")
+        .wrap_context_with(|| format!("profile={} service={service}", dimensions.profile())),
+    );
+  }
+  validate_required_fields(
+    dimensions.profile(),
+    &stacked_profile,
+    &base_infra,
+    &service_templates,
+  )?;
+  let profile_cfgs: Vec<_> = profile_cfgs
+    .into_iter()
+    .map(|(profile, profile_cfg)| (format!("profile.{profile}"), profile_cfg))
+    .collect();
 
-  let profile_infra = clone_path(&profile_cfg, &["infra"]);
-  let service_infra = clone_path(service_cfg, &["infra"]);
-  let profile_service_infra = clone_path(&profile_cfg, &["services", service, "infra"]);
+  let overrides_cfg = Value::Table(overrides.table().clone());
+  validate_overrides(&overrides_cfg, &base_infra, &service_templates)?;
+  let merge_rules = load_merge_rules(source)?;
+  validate_merge_rules(&merge_rules, &base_infra, &service_templates)?;
+  let constraints = load_constraints(source)?;
+  validate_constraints(&constraints, &base_infra, &service_templates)?;
 
-  let mut merged_infra = base_infra;
-  if let Some(value) = profile_infra.as_ref() {
-    deep_merge(&mut merged_infra, value);
+  let specs = load_dimension_specs(source)?;
+  let mut dimension_cfgs = Vec::new();
+  for (spec, value) in dimensions.ordered(&specs)? {
+    let overlay_cfg = load_dimension_overlay(source, spec, value)?;
+    validate_dimension_overlay(
+      &spec.name,
+      value,
+      &overlay_cfg,
+      &base_infra,
+      &service_templates,
+    )?;
+    dimension_cfgs.push((format!("{}.{value}", spec.name), overlay_cfg));
   }
-  if let Some(value) = service_infra.as_ref() {
-    deep_merge(&mut merged_infra, value);
+
+  let mut instance_cfgs = Vec::new();
+  if let Some(instance) = instance {
+    for (label, profile_cfg) in &profile_cfgs {
+      if let Some(instance_cfg) =
+        clone_path(profile_cfg, &["services", service, INSTANCES_KEY, instance])
+      {
+        let label = format!("{label}.services.{service}.{INSTANCES_KEY}.{instance}");
+        instance_cfgs.push((label, instance_cfg));
+      }
+    }
+
+    if instance_cfgs.is_empty() {
+      return Err(
+        Error::new(CONFIGERR_INSTANCENOTFOUND)
+          .wrap_context("resolve target service instance not found")
+          .wrap_context_with(|| {
+            format!(
+              "profile={} service={service} instance={instance}",
+              dimensions.profile()
+            )
+          }),
+      );
+    }
   }
-  if let Some(value) = profile_service_infra.as_ref() {
-    deep_merge(&mut merged_infra, value);
+
+  let mut layers = vec![
+    OverlayLayer {
+      label: String::from("template.infra"),
+      target: LayerTarget::Infra,
+      value: strip_template_markers(&service_infra),
+    },
+    OverlayLayer {
+      label: format!("template.service.{service}"),
+      target: LayerTarget::Service,
+      value: strip_template_markers(&service_schema(service_cfg)),
+    },
+  ];
+  let mut push = |label: String, target: LayerTarget, value: Option<Value>| {
+    let value = match target {
+      LayerTarget::Infra => value.map(|value| select_modules(&value, &service_infra)),
+      LayerTarget::Service => value,
+    };
+    if let Some(value) = value {
+      layers.push(OverlayLayer {
+        label,
+        target,
+        value,
+      });
+    }
+  };
+
+  for (label, profile_cfg) in &profile_cfgs {
+    push(
+      format!("{label}.infra"),
+      LayerTarget::Infra,
+      clone_path(profile_cfg, &["infra"]),
+    );
+  }
+  push(
+    format!("template.service.{service}.infra"),
+    LayerTarget::Infra,
+    clone_path(service_cfg, &["infra"]),
+  );
+  for (label, profile_cfg) in &profile_cfgs {
+    push_service_overlay(&mut push, label, profile_cfg, service);
+  }
+
+  for (label, overlay_cfg) in &dimension_cfgs {
+    push(
+      format!("{label}.infra"),
+      LayerTarget::Infra,
+      clone_path(overlay_cfg, &["infra"]),
+    );
+    push_service_overlay(&mut push, label, overlay_cfg, service);
   }
 
-  let mut merged_service = service_schema(service_cfg);
-  let profile_service =
-    clone_path(&profile_cfg, &["services", service]).unwrap_or_else(empty_table);
-  let profile_service_without_infra = strip_top_level_key(&profile_service, "infra");
-  deep_merge(&mut merged_service, &profile_service_without_infra);
+  for (label, instance_cfg) in instance_cfgs {
+    push(
+      format!("{label}.infra"),
+      LayerTarget::Infra,
+      clone_path(&instance_cfg, &["infra"]),
+    );
+    push(
+      label,
+      LayerTarget::Service,
+      Some(strip_top_level_key(&instance_cfg, "infra")),
+    );
+  }
+
+  push(
+    format!("{OVERRIDE_LAYER}.infra"),
+    LayerTarget::Infra,
+    clone_path(&overrides_cfg, &["infra"]),
+  );
+  push_service_overlay(&mut push, OVERRIDE_LAYER, &overrides_cfg, service);
 
-  ResolvedLayers::new(merged_infra, merged_service)
+  let templates = vec![
+    OverlayLayer {
+      label: String::from("template.infra"),
+      target: LayerTarget::Infra,
+      value: service_infra,
+    },
+    OverlayLayer {
+      label: format!("template.service.{service}"),
+      target: LayerTarget::Service,
+      value: service_schema(service_cfg),
+    },
+  ];
+  let stack = OverlayStack {
+    layers,
+    templates,
+    constraints: constraints.service(service),
+    infra_rules: merge_rules.infra.clone(),
+    service_rules: merge_rules.service(service),
+  };
+  for layer in &stack.layers {
+    validate_merge_overlay(stack.rules(layer.target), &layer.value, &layer.label)?;
+  }
+  Ok(stack)
 }
 
-pub fn resolve_service_schema(config_dir: &Path, service: &str) -> Result<ResolvedConfig> {
-  Ok(resolve_service_schema_layers(config_dir, service)?.into_resolved_config())
+/// 追加覆盖文件中某服务的 infra 覆盖和 service 覆盖
+fn push_service_overlay(
+  push: &mut impl FnMut(String, LayerTarget, Option<Value>),
+  label: &str,
+  overlay_cfg: &Value,
+  service: &str,
+) {
+  let Some(service_overlay) = clone_path(overlay_cfg, &["services", service]) else {
+    return;
+  };
+
+  let label = format!("{label}.services.{service}");
+  push(
+    format!("{label}.infra"),
+    LayerTarget::Infra,
+    clone_path(&service_overlay, &["infra"]),
+  );
+  push(
+    label,
+    LayerTarget::Service,
+    Some(strip_top_level_key(
+      &strip_top_level_key(&service_overlay, "infra"),
+      INSTANCES_KEY,
+    )),
+  );
 }
 
-pub fn resolve_service_schema_layers(config_dir: &Path, service: &str) -> Result<ResolvedLayers> {
-  let base_infra = load_infra_configs(config_dir)?;
-  let service_templates = load_service_templates(config_dir)?;
+fn fold_layers(stack: &OverlayStack, mut provenance: Option<&mut Provenance>) -> (Value, Value) {
+  let mut infra = empty_table();
+  let mut service = empty_table();
+
+  for layer in &stack.layers {
+    let rules = stack.rules(layer.target);
+    let (target, origins) = match layer.target {
+      LayerTarget::Infra => (&mut infra, provenance.as_mut().map(|p| &mut p.infra)),
+      LayerTarget::Service => (&mut service, provenance.as_mut().map(|p| &mut p.service)),
+    };
+    let mut seeded = Vec::new();
+    if let Some(template) = stack.template(layer.target) {
+      seed_map_entries(target, &layer.value, &template.value, "", &mut seeded);
+    }
+    deep_merge_with(target, &layer.value, rules);
+    if let Some(origins) = origins {
+      if let Some(template) = stack.template(layer.target) {
+        for (path, default) in &seeded {
+          record_leaves(origins, default, path, &template.label, rules);
+        }
+      }
+      record_leaves(origins, &layer.value, "", &layer.label, rules);
+    }
+  }
+
+  (infra, service)
+}
+
+/// 覆盖层在映射表中新增的条目先补上模板 `"*"` 声明的默认值，返回补全的路径和默认值
+fn seed_map_entries(
+  target: &mut Value,
+  overlay: &Value,
+  schema: &Value,
+  path: &str,
+  seeded: &mut Vec<(String, Value)>,
+) {
+  let (Some(target_table), Some(overlay_table), Some(schema_table)) =
+    (target.as_table_mut(), overlay.as_table(), schema.as_table())
+  else {
+    return;
+  };
+
+  let is_map = schema_table.contains_key(MAP_KEY);
+  for (key, overlay_child) in overlay_table {
+    let Some(child_schema) = schema_child(schema_table, key) else {
+      continue;
+    };
+    let child_path = if path.is_empty() {
+      key.clone()
+    } else {
+      format!("{path}.{key}")
+    };
+    if is_map && !target_table.contains_key(key) {
+      let default = map_entry_default(child_schema);
+      target_table.insert(key.clone(), default.clone());
+      seeded.push((child_path.clone(), default));
+    }
+    if let Some(target_child) = target_table.get_mut(key) {
+      seed_map_entries(
+        target_child,
+        overlay_child,
+        child_schema,
+        &child_path,
+        seeded,
+      );
+    }
+  }
+}
+
+/// 记录叶子来源，按非替换策略合并的数组记录全部参与合并的层，例如 `a + b`
+fn record_leaves(
+  origins: &mut BTreeMap<String, String>,
+  value: &Value,
+  path: &str,
+  label: &str,
+  rules: &MergeRules,
+) {
+  match value {
+    Value::Table(table) => {
+      let child_path = |key: &str| {
+        if path.is_empty() {
+          key.to_string()
+        } else {
+          format!("{path}.{key}")
+        }
+      };
+      // 被删除的字段不再有来源
+      for key in unset_keys(value) {
+        let removed = child_path(key);
+        let nested = format!("{removed}.");
+        origins
+          .retain(|origin_path, _| origin_path != &removed && !origin_path.starts_with(&nested));
+      }
+      for (key, child) in table {
+        if key != UNSET_KEY {
+          record_leaves(origins, child, &child_path(key), label, rules);
+        }
+      }
+    }
+    Value::Array(_) if *rules.strategy(path) != MergeStrategy::Replace => {
+      let origin = match origins.get(path) {
+        Some(previous) => format!("{previous} + {label}"),
+        None => label.to_string(),
+      };
+      origins.insert(path.to_string(), origin);
+    }
+    _ => {
+      origins.insert(path.to_string(), label.to_string());
+    }
+  }
+}
+
+pub fn resolve_service_schema(source: &dyn ConfigSource, service: &str) -> Result<ResolvedConfig> {
+  Ok(resolve_service_schema_layers(source, service)?.into_resolved_config())
+}
+
+pub fn resolve_service_schema_layers(
+  source: &dyn ConfigSource,
+  service: &str,
+) -> Result<ResolvedLayers> {
+  let base_infra = load_infra_configs(source)?;
+  let service_templates = load_service_templates(source)?;
   let service_cfg = service_templates.get(service).ok_or_else(|| {
     Error::new(CONFIGERR_SERVICENOTFOUND)
       .wrap_context("resolve target service schema not found")
@@ -68,10 +583,10 @@ pub fn resolve_service_schema_layers(config_dir: &Path, service: &str) -> Result
 
   validate_templates(&base_infra, &service_templates)?;
 
-  let service_infra = clone_path(service_cfg, &["infra"]);
+  let service_infra_cfg = clone_path(service_cfg, &["infra"]);
 
-  let mut merged_infra = base_infra;
-  if let Some(value) = service_infra.as_ref() {
+  let mut merged_infra = service_infra(service, &base_infra, service_cfg)?;
+  if let Some(value) = service_infra_cfg.as_ref() {
     deep_merge(&mut merged_infra, value);
   }
 
@@ -82,6 +597,7 @@ fn validate_templates(
   base_infra: &Value,
   service_templates: &BTreeMap<String, Value>,
 ) -> Result<()> {
+  validate_template_markers(base_infra, "template.infra")?;
   for (service, service_cfg) in service_templates {
     validate_service_template(service, base_infra, service_cfg)?;
   }
@@ -89,6 +605,20 @@ fn validate_templates(
   Ok(())
 }
 
+/// 只保留 infra 覆盖中 service 使用的模块
+fn select_modules(infra: &Value, service_infra: &Value) -> Value {
+  match (infra, service_infra) {
+    (Value::Table(table), Value::Table(modules)) => Value::Table(
+      table
+        .iter()
+        .filter(|(module, _)| modules.contains_key(*module))
+        .map(|(module, value)| (module.clone(), value.clone()))
+        .collect(),
+    ),
+    _ => infra.clone(),
+  }
+}
+
 fn clone_path(root: &Value, path: &[&str]) -> Option<Value> {
   let mut current = root;
   for segment in path {
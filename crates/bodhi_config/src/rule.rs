@@ -0,0 +1,297 @@
+//! 配置规则模块
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use bodhi_error::prelude::*;
+use toml::Value;
+
+use crate::engine::ResolvedLayers;
+use crate::errcode::configerr::*;
+use crate::validate::value_at;
+
+/// 跨字段或跨服务的配置规则，解析完成后由 `ConfigEngine` 执行
+///
+/// 单个服务内的规则实现 `check_service`，跨服务的规则实现 `check_profile`，
+/// 未实现的一侧默认不报告违反项。
+pub trait ConfigRule: Send + Sync {
+  /// 规则名称，出现在错误上下文中
+  fn name(&self) -> &str;
+
+  /// 检查单个服务的解析结果，`target` 可能带实例，例如 `gateway@gw-2`
+  fn check_service(&self, _target: &str, _layers: &ResolvedLayers) -> Vec<RuleViolation> {
+    Vec::new()
+  }
+
+  /// 检查同一 profile 下全部服务及实例的解析结果，键为 `target`
+  fn check_profile(&self, _services: &BTreeMap<String, ResolvedLayers>) -> Vec<RuleViolation> {
+    Vec::new()
+  }
+}
+
+/// 一项规则违反，`paths` 为涉及的全部配置路径
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RuleViolation {
+  pub paths: Vec<String>,
+  pub message: String,
+}
+
+impl RuleViolation {
+  pub fn new(paths: Vec<String>, message: impl Into<String>) -> Self {
+    Self {
+      paths,
+      message: message.into(),
+    }
+  }
+}
+
+impl fmt::Display for RuleViolation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}: {}", self.paths.join(", "), self.message)
+  }
+}
+
+/// 服务配置字段的完整路径，例如 `services.gateway@gw-2.server.http_port`
+pub fn service_path(target: &str, path: &str) -> String {
+  format!("services.{target}.{path}")
+}
+
+/// 已注册的配置规则，按注册顺序执行
+#[derive(Clone, Default)]
+pub struct RuleRegistry {
+  rules: Vec<Arc<dyn ConfigRule>>,
+}
+
+impl RuleRegistry {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// 注册一条规则
+  pub fn register(&mut self, rule: impl ConfigRule + 'static) {
+    self.rules.push(Arc::new(rule));
+  }
+
+  /// 注册一条规则并返回自身
+  pub fn with_rule(mut self, rule: impl ConfigRule + 'static) -> Self {
+    self.register(rule);
+    self
+  }
+
+  /// 已注册规则的名称
+  pub fn names(&self) -> Vec<&str> {
+    self.rules.iter().map(|rule| rule.name()).collect()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.rules.is_empty()
+  }
+
+  /// 执行全部单服务规则，汇总违反项后一次报告
+  pub fn check_service(&self, target: &str, layers: &ResolvedLayers) -> Result<()> {
+    let violations = self
+      .rules
+      .iter()
+      .flat_map(|rule| named(rule.as_ref(), rule.check_service(target, layers)))
+      .collect();
+    report(violations, || format!("service={target}"))
+  }
+
+  /// 执行全部跨服务规则，`label` 为解析维度标签
+  pub fn check_profile(
+    &self,
+    label: &str,
+    services: &BTreeMap<String, ResolvedLayers>,
+  ) -> Result<()> {
+    let violations = self
+      .rules
+      .iter()
+      .flat_map(|rule| named(rule.as_ref(), rule.check_profile(services)))
+      .collect();
+    report(violations, || format!("profile={label}"))
+  }
+}
+
+impl fmt::Debug for RuleRegistry {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_list().entries(self.names()).finish()
+  }
+}
+
+fn named(
+  rule: &dyn ConfigRule,
+  violations: Vec<RuleViolation>,
+) -> impl Iterator<Item = (String, RuleViolation)> {
+  let name = rule.name().to_string();
+  violations
+    .into_iter()
+    .map(move |violation| (name.clone(), violation))
+}
+
+fn report(violations: Vec<(String, RuleViolation)>, scope: impl FnOnce() -> String) -> Result<()> {
+  if violations.is_empty() {
+    return Ok(());
+  }
+
+  let count = violations.len();
+  let err = Error::new(CONFIGERR_RULEVIOLATED)
+    .wrap_context("resolved config violates config rules")
+    .wrap_context_with(|| format!("{} violations={count}", scope()));
+  Err(violations.into_iter().fold(err, |err, (rule, violation)| {
+    err.wrap_context(format!("{violation} rule={rule}"))
+  }))
+}
+
+/// 比较运算符
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CompareOp {
+  Lt,
+  Le,
+  Gt,
+  Ge,
+  Eq,
+  Ne,
+}
+
+impl CompareOp {
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Self::Lt => "<",
+      Self::Le => "<=",
+      Self::Gt => ">",
+      Self::Ge => ">=",
+      Self::Eq => "==",
+      Self::Ne => "!=",
+    }
+  }
+
+  fn holds(self, ordering: Ordering) -> bool {
+    match self {
+      Self::Lt => ordering.is_lt(),
+      Self::Le => ordering.is_le(),
+      Self::Gt => ordering.is_gt(),
+      Self::Ge => ordering.is_ge(),
+      Self::Eq => ordering.is_eq(),
+      Self::Ne => ordering.is_ne(),
+    }
+  }
+}
+
+/// 比较同一服务配置中的两个字段，例如 `client.request_timeout_ms >= client.connect_timeout_ms`
+///
+/// 任一字段缺失或两者无法比较时不报告。
+#[derive(Clone, Debug)]
+pub struct CompareFields {
+  name: String,
+  left: String,
+  op: CompareOp,
+  right: String,
+}
+
+impl CompareFields {
+  pub fn new(
+    name: impl Into<String>,
+    left: impl Into<String>,
+    op: CompareOp,
+    right: impl Into<String>,
+  ) -> Self {
+    Self {
+      name: name.into(),
+      left: left.into(),
+      op,
+      right: right.into(),
+    }
+  }
+}
+
+impl ConfigRule for CompareFields {
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn check_service(&self, target: &str, layers: &ResolvedLayers) -> Vec<RuleViolation> {
+    let service = layers.service();
+    let (Some(left), Some(right)) = (
+      value_at(service, &self.left),
+      value_at(service, &self.right),
+    ) else {
+      return Vec::new();
+    };
+    match compare_values(left, right) {
+      Some(ordering) if !self.op.holds(ordering) => vec![RuleViolation::new(
+        vec![
+          service_path(target, &self.left),
+          service_path(target, &self.right),
+        ],
+        format!(
+          "{}={left} must be {} {}={right}",
+          self.left,
+          self.op.as_str(),
+          self.right
+        ),
+      )],
+      _ => Vec::new(),
+    }
+  }
+}
+
+fn compare_values(left: &Value, right: &Value) -> Option<Ordering> {
+  let number = |value: &Value| match value {
+    Value::Integer(number) => Some(*number as f64),
+    Value::Float(number) => Some(*number),
+    _ => None,
+  };
+  match (left, right) {
+    (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+    _ => number(left)?.partial_cmp(&number(right)?),
+  }
+}
+
+/// 同一 profile 下全部服务及实例在指定字段上的取值互不相同，例如 `server.http_port`
+///
+/// 多个字段共享同一取值空间，`http_port` 与另一服务的 `grpc_port` 相同也视为冲突。
+#[derive(Clone, Debug)]
+pub struct UniqueValues {
+  name: String,
+  paths: Vec<String>,
+}
+
+impl UniqueValues {
+  pub fn new(name: impl Into<String>, paths: impl IntoIterator<Item = impl Into<String>>) -> Self {
+    Self {
+      name: name.into(),
+      paths: paths.into_iter().map(Into::into).collect(),
+    }
+  }
+}
+
+impl ConfigRule for UniqueValues {
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn check_profile(&self, services: &BTreeMap<String, ResolvedLayers>) -> Vec<RuleViolation> {
+    let mut owners: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (target, layers) in services {
+      for path in &self.paths {
+        if let Some(value) = value_at(layers.service(), path) {
+          owners
+            .entry(value.to_string())
+            .or_default()
+            .push(service_path(target, path));
+        }
+      }
+    }
+
+    owners
+      .into_iter()
+      .filter(|(_, paths)| paths.len() > 1)
+      .map(|(value, paths)| {
+        let message = format!("value {value} is shared by {} fields", paths.len());
+        RuleViolation::new(paths, message)
+      })
+      .collect()
+  }
+}
@@ -1,19 +1,80 @@
-use std::path::Path;
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, Weak};
 
+use arc_swap::{ArcSwap, Guard};
 use bodhi_error::prelude::*;
 use serde::de::DeserializeOwned;
 
+use crate::cache::{read_cache, write_cache};
+use crate::dimension::Dimensions;
 use crate::engine::{ConfigEngine, ResolvedLayers};
+use crate::errcode::configerr::*;
+use crate::hash::ContentHash;
+use crate::output::OutputFormat;
+use crate::overrides::Overrides;
+use crate::resolve::{service_target, split_service_target};
+use crate::source::ConfigSource;
+
+/// 默认保留的历史快照数量
+pub const DEFAULT_HISTORY_LIMIT: usize = 8;
+
+/// 尚未发布的快照版本号，发布时替换为递增的正式版本号
+const UNPUBLISHED_VERSION: u64 = 0;
+
+/// 配置存储的进程内唯一标识，用作线程局部快照缓存的键
+static NEXT_STORE_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+  /// 当前线程为各配置存储缓存的快照
+  static LOCAL_SNAPSHOTS: RefCell<Vec<LocalSnapshot>> = const { RefCell::new(Vec::new()) };
+}
+
+/// 线程局部缓存的单个配置存储快照
+struct LocalSnapshot {
+  store_id: u64,
+  alive: Weak<()>,
+  snapshot: Box<dyn Any>,
+}
+
+/// 借用当前快照的读取守卫，不修改引用计数
+pub type SnapshotGuard<I, S> = Guard<Arc<ConfigSnapshot<I, S>>>;
+
+/// 配置快照校验钩子
+pub type ConfigValidator<I, S> = dyn Fn(&ConfigSnapshot<I, S>) -> Result<()> + Send + Sync;
 
 /// 单次装载后的配置快照
-#[derive(Clone)]
 pub struct ConfigSnapshot<I, S> {
   version: u64,
   layers: Arc<ResolvedLayers>,
   infra: Arc<I>,
   service: Arc<S>,
+  warning: Option<Arc<str>>,
+}
+
+impl<I, S> Clone for ConfigSnapshot<I, S> {
+  fn clone(&self) -> Self {
+    self.with_version(self.version)
+  }
+}
+
+impl<I, S> fmt::Debug for ConfigSnapshot<I, S>
+where
+  I: fmt::Debug,
+  S: fmt::Debug,
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("ConfigSnapshot")
+      .field("version", &self.version)
+      .field("warning", &self.warning)
+      .field("infra", &self.infra)
+      .field("service", &self.service)
+      .finish()
+  }
 }
 
 impl<I, S> ConfigSnapshot<I, S> {
@@ -21,6 +82,21 @@ impl<I, S> ConfigSnapshot<I, S> {
     self.version
   }
 
+  /// 获取快照内容哈希，内容相同的快照哈希相同
+  pub fn content_hash(&self) -> ContentHash {
+    self.layers.content_hash()
+  }
+
+  /// 是否为解析失败后从缓存恢复的降级快照
+  pub fn is_degraded(&self) -> bool {
+    self.warning.is_some()
+  }
+
+  /// 获取降级快照的告警信息
+  pub fn warning(&self) -> Option<&str> {
+    self.warning.as_deref()
+  }
+
   pub fn layers(&self) -> &ResolvedLayers {
     self.layers.as_ref()
   }
@@ -44,15 +120,72 @@ impl<I, S> ConfigSnapshot<I, S> {
   pub fn service_arc(&self) -> Arc<S> {
     Arc::clone(&self.service)
   }
+
+  fn with_version(&self, version: u64) -> Self {
+    Self {
+      version,
+      layers: Arc::clone(&self.layers),
+      infra: Arc::clone(&self.infra),
+      service: Arc::clone(&self.service),
+      warning: self.warning.clone(),
+    }
+  }
+}
+
+/// 配置存储诊断信息
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ConfigStoreStats {
+  /// 当前发布的快照版本
+  pub current_version: u64,
+  /// 累计重载次数
+  pub reload_attempts: u64,
+  /// 累计重载失败次数
+  pub reload_failures: u64,
+  /// 累计因内容未变化而跳过发布的重载次数
+  pub reload_unchanged: u64,
+  /// 最近一次重载失败的错误信息
+  pub last_error: Option<String>,
+  /// 可回滚的历史快照版本，按发布顺序排列
+  pub history_versions: Vec<u64>,
+  /// 最近一次写入缓存失败的错误信息
+  pub cache_error: Option<String>,
 }
 
 /// 线程安全的配置运行时存储
+///
+/// 当前快照通过原子指针交换发布，读取无需加锁；历史快照仅在写路径上加锁维护。
 pub struct ConfigStore<I, S> {
+  id: u64,
+  alive: Arc<()>,
   engine: ConfigEngine,
-  profile: String,
+  dimensions: Dimensions,
   service: String,
   next_version: AtomicU64,
-  state: RwLock<Arc<ConfigSnapshot<I, S>>>,
+  history_limit: usize,
+  validators: RwLock<Vec<NamedValidator<I, S>>>,
+  reload_attempts: AtomicU64,
+  reload_failures: AtomicU64,
+  reload_unchanged: AtomicU64,
+  last_error: Mutex<Option<String>>,
+  cache_path: Option<PathBuf>,
+  cache_error: Mutex<Option<String>>,
+  current: ArcSwap<ConfigSnapshot<I, S>>,
+  published_version: AtomicU64,
+  history: Mutex<VecDeque<Arc<ConfigSnapshot<I, S>>>>,
+}
+
+impl<I, S> fmt::Debug for ConfigStore<I, S> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("ConfigStore")
+      .field("dimensions", &self.dimensions.label())
+      .field("service", &self.service)
+      .field("source", &self.engine.source().describe())
+      .field(
+        "current_version",
+        &self.published_version.load(Ordering::Acquire),
+      )
+      .finish()
+  }
 }
 
 impl<I, S> ConfigStore<I, S>
@@ -61,88 +194,550 @@ where
   S: DeserializeOwned + Send + Sync + 'static,
 {
   pub fn load(profile: &str, service: &str) -> Result<Self> {
-    Self::load_from("config", profile, service)
+    Self::builder(profile, service).build()
   }
 
   pub fn load_from(config_dir: impl AsRef<Path>, profile: &str, service: &str) -> Result<Self> {
-    let engine = ConfigEngine::find(config_dir)?;
-    Self::from_engine(engine, profile, service)
+    Self::builder(profile, service)
+      .config_dir(config_dir)
+      .build()
   }
 
   pub fn from_engine(engine: ConfigEngine, profile: &str, service: &str) -> Result<Self> {
-    let initial_version = 1;
-    let snapshot = Arc::new(Self::load_snapshot(
-      &engine,
-      profile,
-      service,
-      initial_version,
-    )?);
-
-    Ok(Self {
-      engine,
-      profile: profile.to_string(),
-      service: service.to_string(),
-      next_version: AtomicU64::new(initial_version + 1),
-      state: RwLock::new(snapshot),
-    })
+    Self::builder(profile, service).engine(engine).build()
+  }
+
+  /// 创建配置存储构建器
+  pub fn builder(profile: &str, service: &str) -> ConfigStoreBuilder<I, S> {
+    ConfigStoreBuilder::new(profile, service)
   }
 
   pub fn profile(&self) -> &str {
-    &self.profile
+    self.dimensions.profile()
+  }
+
+  /// 获取解析使用的维度取值
+  pub fn dimensions(&self) -> &Dimensions {
+    &self.dimensions
   }
 
   pub fn service(&self) -> &str {
     &self.service
   }
 
-  pub fn config_dir(&self) -> &Path {
+  /// 获取本地配置根目录，非文件系统来源或尚未找到配置目录时返回 `None`
+  pub fn config_dir(&self) -> Option<&Path> {
     self.engine.config_dir()
   }
 
+  /// 获取配置来源
+  pub fn source(&self) -> &dyn ConfigSource {
+    self.engine.source()
+  }
+
+  /// 获取最近一次有效配置的缓存路径
+  pub fn cache_path(&self) -> Option<&Path> {
+    self.cache_path.as_deref()
+  }
+
   pub fn snapshot(&self) -> Arc<ConfigSnapshot<I, S>> {
-    Arc::clone(&self.read_state())
+    self.current.load_full()
+  }
+
+  /// 借用当前快照，适合短时间读取，避免引用计数竞争
+  pub fn snapshot_guard(&self) -> SnapshotGuard<I, S> {
+    self.current.load()
+  }
+
+  /// 创建带缓存的快照句柄，仅在版本变化时重新读取快照
+  pub fn handle(self: &Arc<Self>) -> SnapshotHandle<I, S> {
+    SnapshotHandle {
+      store: Arc::clone(self),
+      cached: self.snapshot(),
+    }
   }
 
+  /// 在当前线程缓存的快照上执行 `f`
+  ///
+  /// 每个线程为每个配置存储缓存一份快照，读取时只比较一次原子版本号，
+  /// 重载发布新版本后，同一线程的下一次读取自动刷新缓存。
+  pub fn with_local<R>(&self, f: impl FnOnce(&ConfigSnapshot<I, S>) -> R) -> R {
+    LOCAL_SNAPSHOTS.with(|cache| {
+      // 嵌套调用时外层仍借用着缓存，无法刷新，过期时直接读取当前快照
+      if let Ok(mut entries) = cache.try_borrow_mut() {
+        self.refresh_local(&mut entries);
+      }
+      let entries = cache.borrow();
+      match self.find_local(&entries) {
+        Some(snapshot) if snapshot.version() == self.current_version() => f(snapshot),
+        _ => f(&self.snapshot()),
+      }
+    })
+  }
+
+  /// 注册校验钩子，之后的每次重载都会在发布前执行
+  pub fn register_validator<F>(&self, name: &str, validator: F)
+  where
+    F: Fn(&ConfigSnapshot<I, S>) -> Result<()> + Send + Sync + 'static,
+  {
+    self
+      .validators
+      .write()
+      .unwrap_or_else(|err| err.into_inner())
+      .push(NamedValidator::new(name, validator));
+  }
+
+  /// 重新解析并发布快照
+  ///
+  /// 内容哈希未变化时仍执行校验钩子，但不分配新版本也不发布，直接返回当前快照。
   pub fn reload(&self) -> Result<Arc<ConfigSnapshot<I, S>>> {
-    let version = self.next_version.fetch_add(1, Ordering::AcqRel);
-    let snapshot = Arc::new(Self::load_snapshot(
-      &self.engine,
-      &self.profile,
-      &self.service,
-      version,
-    )?);
+    self.reload_attempts.fetch_add(1, Ordering::AcqRel);
+
+    match self.load_validated_snapshot() {
+      Ok(Some(snapshot)) => Ok(self.publish(snapshot)),
+      Ok(None) => {
+        self.reload_unchanged.fetch_add(1, Ordering::AcqRel);
+        Ok(self.snapshot())
+      }
+      Err(err) => {
+        self.reload_failures.fetch_add(1, Ordering::AcqRel);
+        *self
+          .last_error
+          .lock()
+          .unwrap_or_else(|err| err.into_inner()) = Some(err.to_string());
+        Err(err)
+      }
+    }
+  }
+
+  /// 回滚到指定版本的历史快照
+  ///
+  /// 回滚结果以新版本号重新发布，保证版本号单调递增。
+  pub fn rollback(&self, version: u64) -> Result<Arc<ConfigSnapshot<I, S>>> {
+    let current = self.snapshot();
+    let target = if current.version() == version {
+      Some(current)
+    } else {
+      self
+        .lock_history()
+        .iter()
+        .find(|snapshot| snapshot.version() == version)
+        .map(Arc::clone)
+    };
+
+    let target = target.ok_or_else(|| {
+      Error::new(CONFIGERR_VERSIONNOTFOUND)
+        .wrap_context("rollback target version not found in history")
+        .wrap_context_with(|| {
+          format!(
+            "dimensions={} service={} version={version}",
+            self.dimensions, self.service
+          )
+        })
+    })?;
 
-    *self.write_state() = Arc::clone(&snapshot);
-    Ok(snapshot)
+    Ok(self.publish(target.as_ref().clone()))
   }
 
   pub fn current_version(&self) -> u64 {
-    self.snapshot().version()
+    self.published_version.load(Ordering::Acquire)
   }
 
-  fn load_snapshot(
-    engine: &ConfigEngine,
-    profile: &str,
-    service: &str,
-    version: u64,
-  ) -> Result<ConfigSnapshot<I, S>> {
-    let layers = engine.resolve_layers(profile, service)?;
-    let infra = layers.extract_infra(".")?;
-    let service_cfg = layers.extract_service(".")?;
+  /// 获取可回滚的历史快照版本
+  pub fn history_versions(&self) -> Vec<u64> {
+    self
+      .lock_history()
+      .iter()
+      .map(|snapshot| snapshot.version())
+      .collect()
+  }
 
-    Ok(ConfigSnapshot {
-      version,
-      layers: Arc::new(layers),
-      infra: Arc::new(infra),
-      service: Arc::new(service_cfg),
-    })
+  /// 获取配置存储诊断信息
+  pub fn stats(&self) -> ConfigStoreStats {
+    ConfigStoreStats {
+      current_version: self.current_version(),
+      reload_attempts: self.reload_attempts.load(Ordering::Acquire),
+      reload_failures: self.reload_failures.load(Ordering::Acquire),
+      reload_unchanged: self.reload_unchanged.load(Ordering::Acquire),
+      last_error: self
+        .last_error
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone(),
+      history_versions: self.history_versions(),
+      cache_error: self
+        .cache_error
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone(),
+    }
+  }
+
+  fn load_validated_snapshot(&self) -> Result<Option<ConfigSnapshot<I, S>>> {
+    let layers = self
+      .engine
+      .resolve_layers_with(&self.dimensions, &self.service)?;
+    let current = self.snapshot_guard();
+    // 降级快照即使内容相同也需要重新发布，以清除降级标记
+    if !current.is_degraded() && layers.content_hash() == current.content_hash() {
+      // 内容未变化仍需执行校验钩子，上次发布之后注册的钩子也要覆盖当前快照
+      run_validators(&self.lock_validators(), &current)?;
+      return Ok(None);
+    }
+
+    // 版本号在发布时分配，被拒绝的重载不占用版本号
+    let snapshot = snapshot_from_layers(layers, UNPUBLISHED_VERSION, None)?;
+    run_validators(&self.lock_validators(), &snapshot)?;
+    self.persist_cache(&snapshot);
+    Ok(Some(snapshot))
+  }
+
+  fn persist_cache(&self, snapshot: &ConfigSnapshot<I, S>) {
+    let Some(cache_path) = &self.cache_path else {
+      return;
+    };
+
+    // 缓存仅用于降级启动，写入失败不影响本次装载
+    let result = write_cache(
+      cache_path,
+      &self.dimensions.label(),
+      &self.service,
+      snapshot.layers(),
+    );
+    *self
+      .cache_error
+      .lock()
+      .unwrap_or_else(|err| err.into_inner()) = result.err().map(|err| err.to_string());
+  }
+
+  fn publish(&self, mut snapshot: ConfigSnapshot<I, S>) -> Arc<ConfigSnapshot<I, S>> {
+    // 历史锁同时串行化所有发布者，保证版本号与历史顺序都与发布顺序一致
+    let mut history = self.lock_history();
+    let version = self.next_version.fetch_add(1, Ordering::AcqRel);
+    snapshot.version = version;
+    let snapshot = Arc::new(snapshot);
+    let previous = self.current.swap(Arc::clone(&snapshot));
+    self.published_version.store(version, Ordering::Release);
+
+    history.push_back(previous);
+    while history.len() > self.history_limit {
+      history.pop_front();
+    }
+    snapshot
+  }
+
+  fn lock_validators(&self) -> RwLockReadGuard<'_, Vec<NamedValidator<I, S>>> {
+    self
+      .validators
+      .read()
+      .unwrap_or_else(|err| err.into_inner())
   }
 
-  fn read_state(&self) -> std::sync::RwLockReadGuard<'_, Arc<ConfigSnapshot<I, S>>> {
-    self.state.read().unwrap_or_else(|err| err.into_inner())
+  fn lock_history(&self) -> MutexGuard<'_, VecDeque<Arc<ConfigSnapshot<I, S>>>> {
+    self.history.lock().unwrap_or_else(|err| err.into_inner())
   }
 
-  fn write_state(&self) -> std::sync::RwLockWriteGuard<'_, Arc<ConfigSnapshot<I, S>>> {
-    self.state.write().unwrap_or_else(|err| err.into_inner())
+  fn refresh_local(&self, entries: &mut Vec<LocalSnapshot>) {
+    let version = self.current_version();
+    if self
+      .find_local(entries)
+      .is_some_and(|snapshot| snapshot.version() == version)
+    {
+      return;
+    }
+
+    // 刷新时顺带清理已销毁的配置存储留下的缓存
+    entries.retain(|entry| entry.store_id != self.id && entry.alive.strong_count() > 0);
+    entries.push(LocalSnapshot {
+      store_id: self.id,
+      alive: Arc::downgrade(&self.alive),
+      snapshot: Box::new(self.snapshot()),
+    });
+  }
+
+  fn find_local<'a>(&self, entries: &'a [LocalSnapshot]) -> Option<&'a Arc<ConfigSnapshot<I, S>>> {
+    entries
+      .iter()
+      .find(|entry| entry.store_id == self.id)
+      .and_then(|entry| entry.snapshot.downcast_ref())
+  }
+}
+
+/// 带缓存的配置快照句柄
+///
+/// 由持有者自行保存，每次读取只比较一次原子版本号，版本未变化时直接复用缓存的快照；
+/// 无需自行保存句柄时使用 [`ConfigStore::with_local`]。
+pub struct SnapshotHandle<I, S> {
+  store: Arc<ConfigStore<I, S>>,
+  cached: Arc<ConfigSnapshot<I, S>>,
+}
+
+impl<I, S> SnapshotHandle<I, S>
+where
+  I: DeserializeOwned + Send + Sync + 'static,
+  S: DeserializeOwned + Send + Sync + 'static,
+{
+  /// 获取最新快照，版本变化时刷新缓存
+  pub fn get(&mut self) -> &Arc<ConfigSnapshot<I, S>> {
+    if self.store.current_version() != self.cached.version() {
+      self.cached = self.store.snapshot();
+    }
+    &self.cached
+  }
+
+  /// 获取所属的配置存储
+  pub fn store(&self) -> &Arc<ConfigStore<I, S>> {
+    &self.store
+  }
+}
+
+/// 配置运行时存储构建器
+pub struct ConfigStoreBuilder<I, S> {
+  dimensions: Dimensions,
+  service: String,
+  config_dir: PathBuf,
+  engine: Option<ConfigEngine>,
+  history_limit: usize,
+  validators: Vec<NamedValidator<I, S>>,
+  cache_path: Option<PathBuf>,
+  product_format: Option<OutputFormat>,
+  overrides: Overrides,
+}
+
+impl<I, S> ConfigStoreBuilder<I, S>
+where
+  I: DeserializeOwned + Send + Sync + 'static,
+  S: DeserializeOwned + Send + Sync + 'static,
+{
+  /// 创建构建器，默认从 `config` 目录查找配置
+  pub fn new(profile: &str, service: &str) -> Self {
+    Self {
+      dimensions: Dimensions::new(profile),
+      service: service.to_string(),
+      config_dir: PathBuf::from("config"),
+      engine: None,
+      history_limit: DEFAULT_HISTORY_LIMIT,
+      validators: Vec::new(),
+      cache_path: None,
+      product_format: None,
+      overrides: Overrides::new(),
+    }
+  }
+
+  /// 选择 profile 以外的解析维度取值，例如 `dimension("region", "eu")`
+  pub fn dimension(mut self, name: &str, value: &str) -> Self {
+    self.dimensions.set(name, value);
+    self
+  }
+
+  /// 整体替换解析维度，例如由 [`Dimensions::with_profiles`] 构造的叠加 profile
+  pub fn dimensions(mut self, dimensions: Dimensions) -> Self {
+    self.dimensions = dimensions;
+    self
+  }
+
+  /// 选择 profile 中声明的服务实例，等价于以 `service@instance` 创建构建器
+  pub fn instance(mut self, instance: &str) -> Self {
+    let (service, _) = split_service_target(&self.service);
+    self.service = service_target(service, instance);
+    self
+  }
+
+  /// 指定配置目录，从当前工作目录向上查找
+  pub fn config_dir(mut self, config_dir: impl AsRef<Path>) -> Self {
+    self.config_dir = config_dir.as_ref().to_path_buf();
+    self
+  }
+
+  /// 使用已创建的配置引擎，优先于配置目录
+  pub fn engine(mut self, engine: ConfigEngine) -> Self {
+    self.engine = Some(engine);
+    self
+  }
+
+  /// 从任意配置来源装载，优先于配置目录
+  pub fn source(self, source: impl ConfigSource + 'static) -> Self {
+    self.engine(ConfigEngine::from_source(source))
+  }
+
+  /// 设置保留的历史快照数量
+  pub fn history_limit(mut self, history_limit: usize) -> Self {
+    self.history_limit = history_limit;
+    self
+  }
+
+  /// 注册校验钩子，首次装载和每次重载都会在发布前执行
+  pub fn validator<F>(mut self, name: &str, validator: F) -> Self
+  where
+    F: Fn(&ConfigSnapshot<I, S>) -> Result<()> + Send + Sync + 'static,
+  {
+    self.validators.push(NamedValidator::new(name, validator));
+    self
+  }
+
+  /// 持久化每次成功装载的配置，解析失败时从该缓存降级启动
+  pub fn cache_path(mut self, cache_path: impl AsRef<Path>) -> Self {
+    self.cache_path = Some(cache_path.as_ref().to_path_buf());
+    self
+  }
+
+  /// 从指定格式的预生成产物装载配置，而非模板和 profile
+  pub fn products(mut self, format: OutputFormat) -> Self {
+    self.product_format = Some(format);
+    self
+  }
+
+  /// 附加临时覆盖，作为优先级最高的覆盖层参与首次装载和每次重载
+  pub fn overrides(mut self, overrides: Overrides) -> Self {
+    self.overrides = overrides;
+    self
+  }
+
+  /// 装载首个快照并创建配置存储
+  pub fn build(mut self) -> Result<ConfigStore<I, S>> {
+    let engine = match self.engine.take() {
+      Some(engine) => Ok(engine),
+      None => ConfigEngine::find(&self.config_dir),
+    };
+    let (engine, resolved) = match engine {
+      Ok(engine) => {
+        let engine = self.configure_engine(engine);
+        let resolved = engine.resolve_layers_with(&self.dimensions, &self.service);
+        (engine, resolved)
+      }
+      Err(err) => (
+        self.configure_engine(ConfigEngine::deferred(&self.config_dir)),
+        Err(err),
+      ),
+    };
+
+    let (layers, warning) = match resolved {
+      Ok(layers) => (layers, None),
+      Err(err) => self.recover_from_cache(err)?,
+    };
+
+    let initial_version = 1;
+    let degraded = warning.is_some();
+    let snapshot = snapshot_from_layers(layers, initial_version, warning)?;
+    run_validators(&self.validators, &snapshot)?;
+
+    let store = ConfigStore {
+      id: NEXT_STORE_ID.fetch_add(1, Ordering::Relaxed),
+      alive: Arc::new(()),
+      engine,
+      dimensions: self.dimensions,
+      service: self.service,
+      next_version: AtomicU64::new(initial_version + 1),
+      history_limit: self.history_limit,
+      validators: RwLock::new(self.validators),
+      reload_attempts: AtomicU64::new(0),
+      reload_failures: AtomicU64::new(0),
+      reload_unchanged: AtomicU64::new(0),
+      last_error: Mutex::new(None),
+      cache_path: self.cache_path,
+      cache_error: Mutex::new(None),
+      current: ArcSwap::from_pointee(snapshot),
+      published_version: AtomicU64::new(initial_version),
+      history: Mutex::new(VecDeque::new()),
+    };
+
+    if !degraded {
+      store.persist_cache(&store.snapshot_guard());
+    }
+    Ok(store)
+  }
+
+  fn configure_engine(&self, mut engine: ConfigEngine) -> ConfigEngine {
+    if let Some(format) = self.product_format {
+      engine = engine.with_products(format);
+    }
+    if !self.overrides.is_empty() {
+      engine = engine.with_overrides(self.overrides.clone());
+    }
+    engine
+  }
+
+  fn recover_from_cache(&self, err: Error) -> Result<(ResolvedLayers, Option<Arc<str>>)> {
+    let Some(cache_path) = &self.cache_path else {
+      return Err(err);
+    };
+
+    match read_cache(cache_path, &self.dimensions.label(), &self.service) {
+      Ok(layers) => {
+        let warning = format!(
+          "config resolution failed, started from last-known-good cache path={} content_hash={}: {err}",
+          cache_path.display(),
+          layers.content_hash().short()
+        );
+        log::warn!(
+          "config store degraded dimensions={} service={}: {warning}",
+          self.dimensions,
+          self.service
+        );
+        Ok((layers, Some(Arc::from(warning))))
+      }
+      Err(cache_err) => Err(
+        err
+          .wrap_context("last-known-good config cache unavailable")
+          .wrap_context_with(|| cache_err.to_string()),
+      ),
+    }
+  }
+}
+
+struct NamedValidator<I, S> {
+  name: String,
+  validator: Box<ConfigValidator<I, S>>,
+}
+
+impl<I, S> NamedValidator<I, S> {
+  fn new<F>(name: &str, validator: F) -> Self
+  where
+    F: Fn(&ConfigSnapshot<I, S>) -> Result<()> + Send + Sync + 'static,
+  {
+    Self {
+      name: name.to_string(),
+      validator: Box::new(validator),
+    }
   }
 }
+
+fn snapshot_from_layers<I, S>(
+  layers: ResolvedLayers,
+  version: u64,
+  warning: Option<Arc<str>>,
+) -> Result<ConfigSnapshot<I, S>>
+where
+  I: DeserializeOwned,
+  S: DeserializeOwned,
+{
+  let infra = layers.extract_infra(".")?;
+  let service_cfg = layers.extract_service(".")?;
+
+  Ok(ConfigSnapshot {
+    version,
+    layers: Arc::new(layers),
+    infra: Arc::new(infra),
+    service: Arc::new(service_cfg),
+    warning,
+  })
+}
+
+fn run_validators<I, S>(
+  validators: &[NamedValidator<I, S>],
+  snapshot: &ConfigSnapshot<I, S>,
+) -> Result<()> {
+  for validator in validators {
+    (validator.validator)(snapshot)
+      .wrap_context("config validator rejected snapshot")
+      .wrap_context_with(|| {
+        format!(
+          "validator={} content_hash={}",
+          validator.name,
+          snapshot.content_hash().short()
+        )
+      })?;
+  }
+
+  Ok(())
+}
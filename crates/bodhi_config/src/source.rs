@@ -0,0 +1,852 @@
+//! 配置来源模块
+//!
+//! 配置来源以 `/` 分隔的相对路径寻址，目录布局与文件系统下的配置目录一致：
+//! `template/infra/*.toml`、`template/service/*.toml`、`template/base/*.toml`、`profile/*.toml`。
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+use bodhi_error::prelude::*;
+use toml::Value;
+
+use crate::constraint::is_schema_sidecar;
+use crate::errcode::configerr::*;
+use crate::loader::find_config_dir;
+use crate::validate::expand_optional_entries;
+
+/// infra 模板目录
+pub const INFRA_TEMPLATE_DIR: &str = "template/infra";
+/// service 模板目录
+pub const SERVICE_TEMPLATE_DIR: &str = "template/service";
+/// 基础模板目录，基础模板只供 service 模板 `extends`，本身不是 service
+pub const BASE_TEMPLATE_DIR: &str = "template/base";
+/// profile 目录
+pub const PROFILE_DIR: &str = "profile";
+/// 产物目录
+pub const PRODUCT_DIR: &str = "product";
+
+/// 配置来源
+///
+/// 实现方只需提供按路径列目录和读文件的能力，模板和 profile 的读取由默认方法按标准布局完成；
+/// 布局不同的来源可以覆盖对应的默认方法。
+pub trait ConfigSource: fmt::Debug + Send + Sync {
+  /// 描述配置来源，用于错误上下文
+  fn describe(&self) -> String;
+
+  /// 列出目录下的直接条目名，目录不存在时返回 `None`
+  fn list(&self, dir: &str) -> Result<Option<Vec<String>>>;
+
+  /// 读取文件内容，文件不存在时返回 `None`
+  fn read(&self, path: &str) -> Result<Option<String>>;
+
+  /// 获取本地配置根目录，非文件系统来源返回 `None`
+  fn local_dir(&self) -> Option<&Path> {
+    None
+  }
+
+  /// 列出 infra 模板名，约束文件 `<name>.schema.toml` 不计入
+  fn infra_template_names(&self) -> Result<Vec<String>> {
+    let mut names = list_toml_stems(self, INFRA_TEMPLATE_DIR, CONFIGERR_TEMPLATEDIRNOTFOUND)?;
+    names.retain(|name| !is_schema_sidecar(name));
+    Ok(names)
+  }
+
+  /// 读取 infra 模板
+  fn infra_template(&self, name: &str) -> Result<Value> {
+    let path = format!("{INFRA_TEMPLATE_DIR}/{name}.toml");
+    let mut template = read_toml(self, &path)?
+      .ok_or_else(|| Error::new(CONFIGERR_FILELOADFAILED))
+      .wrap_context_with(|| format!("source={} path={path} not found", self.describe()))?;
+    expand_optional_entries(&mut template, "template.infra")
+      .wrap_context_with(|| format!("path={path}"))?;
+    Ok(template)
+  }
+
+  /// 列出 service 名，约束文件 `<name>.schema.toml` 不计入
+  fn service_names(&self) -> Result<Vec<String>> {
+    let mut names = list_toml_stems(self, SERVICE_TEMPLATE_DIR, CONFIGERR_TEMPLATEDIRNOTFOUND)?;
+    names.retain(|name| !is_schema_sidecar(name));
+    Ok(names)
+  }
+
+  /// 读取 service 模板
+  fn service_template(&self, service: &str) -> Result<Value> {
+    let path = format!("{SERVICE_TEMPLATE_DIR}/{service}.toml");
+    let mut template = read_toml(self, &path)?
+      .ok_or_else(|| Error::new(CONFIGERR_SERVICENOTFOUND))
+      .wrap_context_with(|| {
+        format!(
+          "service={service} source={} path={path} not found",
+          self.describe()
+        )
+      })?;
+    expand_optional_entries(&mut template, &format!("template.service.{service}"))
+      .wrap_context_with(|| format!("path={path}"))?;
+    Ok(template)
+  }
+
+  /// 读取基础模板
+  fn base_template(&self, name: &str) -> Result<Value> {
+    let path = format!("{BASE_TEMPLATE_DIR}/{name}.toml");
+    let mut template = read_toml(self, &path)?
+      .ok_or_else(|| Error::new(CONFIGERR_FILELOADFAILED))
+      .wrap_context_with(|| {
+        format!(
+          "base={name} source={} path={path} not found",
+          self.describe()
+        )
+      })?;
+    expand_optional_entries(&mut template, &format!("template.base.{name}"))
+      .wrap_context_with(|| format!("path={path}"))?;
+    Ok(template)
+  }
+
+  /// 列出 profile 名
+  fn profile_names(&self) -> Result<Vec<String>> {
+    list_toml_stems(self, PROFILE_DIR, CONFIGERR_PROFILEDIRNOTFOUND)
+  }
+
+  /// 读取 profile
+  fn profile(&self, profile: &str) -> Result<Value> {
+    let path = format!("{PROFILE_DIR}/{profile}.toml");
+    read_toml(self, &path)?
+      .ok_or_else(|| Error::new(CONFIGERR_PROFILENOTFOUND))
+      .wrap_context_with(|| {
+        format!(
+          "profile={profile} source={} path={path} not found",
+          self.describe()
+        )
+      })
+  }
+}
+
+/// 读取并解析 TOML 文件，文件不存在时返回 `None`
+pub fn read_toml<S>(source: &S, path: &str) -> Result<Option<Value>>
+where
+  S: ConfigSource + ?Sized,
+{
+  let Some(content) = source.read(path)? else {
+    return Ok(None);
+  };
+
+  toml::from_str::<Value>(&content)
+    .map(Some)
+    .map_err(Error::from_std)
+    .wrap_context("parse toml file failed")
+    .wrap_context_with(|| format!("source={} path={path}", source.describe()))
+}
+
+pub(crate) fn list_toml_stems<S>(source: &S, dir: &str, missing_code: i32) -> Result<Vec<String>>
+where
+  S: ConfigSource + ?Sized,
+{
+  let entries = source
+    .list(dir)?
+    .ok_or_else(|| Error::new(missing_code))
+    .wrap_context_with(|| format!("source={} dir={dir} not found", source.describe()))?;
+
+  let mut names: Vec<_> = entries
+    .iter()
+    .filter_map(|entry| entry.strip_suffix(".toml"))
+    .filter(|stem| !stem.is_empty())
+    .map(str::to_string)
+    .collect();
+  names.sort();
+  Ok(names)
+}
+
+/// 文件系统配置来源
+#[derive(Clone, Debug)]
+pub struct FsSource {
+  root: PathBuf,
+}
+
+impl FsSource {
+  /// 以指定配置根目录创建来源
+  pub fn new(root: impl AsRef<Path>) -> Self {
+    Self {
+      root: root.as_ref().to_path_buf(),
+    }
+  }
+
+  /// 获取配置根目录
+  pub fn root(&self) -> &Path {
+    &self.root
+  }
+}
+
+impl ConfigSource for FsSource {
+  fn describe(&self) -> String {
+    self.root.display().to_string()
+  }
+
+  fn list(&self, dir: &str) -> Result<Option<Vec<String>>> {
+    let dir = self.root.join(dir);
+    if !dir.is_dir() {
+      return Ok(None);
+    }
+
+    let mut names = Vec::new();
+    for entry in fs::read_dir(&dir)
+      .map_err(Error::from_std)
+      .wrap_context("read config directory failed")
+      .wrap_context_with(|| format!("dir={}", dir.display()))?
+    {
+      let entry = entry
+        .map_err(Error::from_std)
+        .wrap_context("read directory entry failed")
+        .wrap_context_with(|| format!("dir={}", dir.display()))?;
+      if let Some(name) = entry.file_name().to_str() {
+        names.push(name.to_string());
+      }
+    }
+
+    names.sort();
+    Ok(Some(names))
+  }
+
+  fn read(&self, path: &str) -> Result<Option<String>> {
+    let path = self.root.join(path);
+    if !path.is_file() {
+      return Ok(None);
+    }
+
+    fs::read_to_string(&path)
+      .map(Some)
+      .map_err(Error::from_std)
+      .wrap_context("read config file failed")
+      .wrap_context_with(|| format!("path={}", path.display()))
+  }
+
+  fn local_dir(&self) -> Option<&Path> {
+    Some(&self.root)
+  }
+}
+
+/// 内存配置来源，主要用于测试
+#[derive(Clone, Debug, Default)]
+pub struct MemorySource {
+  files: BTreeMap<String, String>,
+}
+
+impl MemorySource {
+  /// 创建空来源
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// 添加文件并返回自身
+  pub fn with_file(mut self, path: &str, content: impl Into<String>) -> Self {
+    self.insert(path, content);
+    self
+  }
+
+  /// 添加或替换文件
+  pub fn insert(&mut self, path: &str, content: impl Into<String>) {
+    self
+      .files
+      .insert(path.trim_matches('/').to_string(), content.into());
+  }
+
+  /// 删除文件
+  pub fn remove(&mut self, path: &str) -> Option<String> {
+    self.files.remove(path.trim_matches('/'))
+  }
+}
+
+impl ConfigSource for MemorySource {
+  fn describe(&self) -> String {
+    "memory".to_string()
+  }
+
+  fn list(&self, dir: &str) -> Result<Option<Vec<String>>> {
+    Ok(list_children(self.files.keys().map(String::as_str), dir))
+  }
+
+  fn read(&self, path: &str) -> Result<Option<String>> {
+    Ok(self.files.get(path.trim_matches('/')).cloned())
+  }
+}
+
+/// 编译期嵌入的配置来源，通常由 `embed_config!` 生成
+#[derive(Clone, Copy, Debug)]
+pub struct EmbeddedSource {
+  files: &'static [(&'static str, &'static str)],
+}
+
+impl EmbeddedSource {
+  /// 以 `(相对路径, 文件内容)` 列表创建来源
+  pub const fn new(files: &'static [(&'static str, &'static str)]) -> Self {
+    Self { files }
+  }
+
+  /// 获取嵌入的文件路径
+  pub fn paths(&self) -> impl Iterator<Item = &'static str> + '_ {
+    self.files.iter().map(|(path, _)| *path)
+  }
+}
+
+impl ConfigSource for EmbeddedSource {
+  fn describe(&self) -> String {
+    "embedded".to_string()
+  }
+
+  fn list(&self, dir: &str) -> Result<Option<Vec<String>>> {
+    Ok(list_children(self.paths(), dir))
+  }
+
+  fn read(&self, path: &str) -> Result<Option<String>> {
+    let path = path.trim_matches('/');
+    Ok(
+      self
+        .files
+        .iter()
+        .find(|(candidate, _)| *candidate == path)
+        .map(|(_, content)| content.to_string()),
+    )
+  }
+}
+
+/// 分层配置来源
+///
+/// 先添加的来源优先：读取文件时返回第一个命中的来源，列目录时合并全部来源的条目。
+#[derive(Clone, Debug, Default)]
+pub struct LayeredSource {
+  layers: Vec<Arc<dyn ConfigSource>>,
+}
+
+impl LayeredSource {
+  /// 创建空的分层来源
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// 追加优先级低于已有来源的来源
+  pub fn with_layer(self, source: impl ConfigSource + 'static) -> Self {
+    self.with_shared_layer(Arc::new(source))
+  }
+
+  /// 追加共享的来源
+  pub fn with_shared_layer(mut self, source: Arc<dyn ConfigSource>) -> Self {
+    self.layers.push(source);
+    self
+  }
+
+  /// 获取按优先级排列的来源
+  pub fn layers(&self) -> &[Arc<dyn ConfigSource>] {
+    &self.layers
+  }
+}
+
+impl ConfigSource for LayeredSource {
+  fn describe(&self) -> String {
+    let layers: Vec<_> = self.layers.iter().map(|layer| layer.describe()).collect();
+    format!("layered[{}]", layers.join(" > "))
+  }
+
+  fn list(&self, dir: &str) -> Result<Option<Vec<String>>> {
+    let mut names = BTreeSet::new();
+    let mut found = false;
+    for layer in &self.layers {
+      if let Some(entries) = layer.list(dir)? {
+        found = true;
+        names.extend(entries);
+      }
+    }
+
+    Ok(found.then(|| names.into_iter().collect()))
+  }
+
+  fn read(&self, path: &str) -> Result<Option<String>> {
+    for layer in &self.layers {
+      if let Some(content) = layer.read(path)? {
+        return Ok(Some(content));
+      }
+    }
+    Ok(None)
+  }
+
+  fn local_dir(&self) -> Option<&Path> {
+    self.layers.iter().find_map(|layer| layer.local_dir())
+  }
+}
+
+/// 按需查找配置目录的文件系统来源
+///
+/// 每次访问时从起始目录向上查找配置目录，找到后固定使用该目录；
+/// 启动时目录尚不存在的服务可在之后的重载中自动接入。
+#[derive(Debug)]
+pub struct SearchSource {
+  start_dir: PathBuf,
+  config_dir: PathBuf,
+  found: OnceLock<FsSource>,
+}
+
+impl SearchSource {
+  /// 以起始目录和相对或绝对的配置目录创建来源
+  pub fn new(start_dir: impl AsRef<Path>, config_dir: impl AsRef<Path>) -> Self {
+    Self {
+      start_dir: start_dir.as_ref().to_path_buf(),
+      config_dir: config_dir.as_ref().to_path_buf(),
+      found: OnceLock::new(),
+    }
+  }
+
+  /// 获取已找到的配置目录，尚未找到时返回 `None`
+  pub fn found_dir(&self) -> Option<&Path> {
+    self.found.get().map(FsSource::root)
+  }
+
+  fn locate(&self) -> Result<&FsSource> {
+    if let Some(found) = self.found.get() {
+      return Ok(found);
+    }
+
+    let config_dir = find_config_dir(&self.start_dir, &self.config_dir)?;
+    Ok(self.found.get_or_init(|| FsSource::new(config_dir)))
+  }
+}
+
+impl ConfigSource for SearchSource {
+  fn describe(&self) -> String {
+    match self.found.get() {
+      Some(found) => found.describe(),
+      None => format!(
+        "search:{} from {}",
+        self.config_dir.display(),
+        self.start_dir.display()
+      ),
+    }
+  }
+
+  fn list(&self, dir: &str) -> Result<Option<Vec<String>>> {
+    self.locate()?.list(dir)
+  }
+
+  fn read(&self, path: &str) -> Result<Option<String>> {
+    self.locate()?.read(path)
+  }
+
+  fn local_dir(&self) -> Option<&Path> {
+    self.found_dir()
+  }
+}
+
+/// 记录访问过的文件和目录的配置来源，用于构建期追踪依赖
+#[derive(Debug)]
+pub struct RecordingSource<S> {
+  inner: S,
+  files: Mutex<BTreeSet<String>>,
+  missing: Mutex<BTreeSet<String>>,
+  dirs: Mutex<BTreeSet<String>>,
+}
+
+impl<S> RecordingSource<S>
+where
+  S: ConfigSource,
+{
+  /// 包装配置来源
+  pub fn new(inner: S) -> Self {
+    Self {
+      inner,
+      files: Mutex::new(BTreeSet::new()),
+      missing: Mutex::new(BTreeSet::new()),
+      dirs: Mutex::new(BTreeSet::new()),
+    }
+  }
+
+  /// 获取读取成功的文件路径
+  pub fn read_files(&self) -> Vec<String> {
+    lock_set(&self.files).iter().cloned().collect()
+  }
+
+  /// 获取读取时尚不存在的文件路径
+  pub fn missing_files(&self) -> Vec<String> {
+    lock_set(&self.missing).iter().cloned().collect()
+  }
+
+  /// 获取列出成功的目录路径
+  pub fn listed_dirs(&self) -> Vec<String> {
+    lock_set(&self.dirs).iter().cloned().collect()
+  }
+}
+
+impl<S> ConfigSource for RecordingSource<S>
+where
+  S: ConfigSource,
+{
+  fn describe(&self) -> String {
+    self.inner.describe()
+  }
+
+  fn list(&self, dir: &str) -> Result<Option<Vec<String>>> {
+    let entries = self.inner.list(dir)?;
+    if entries.is_some() {
+      lock_set(&self.dirs).insert(dir.trim_matches('/').to_string());
+    }
+    Ok(entries)
+  }
+
+  fn read(&self, path: &str) -> Result<Option<String>> {
+    let content = self.inner.read(path)?;
+    let recorded = if content.is_some() {
+      &self.files
+    } else {
+      &self.missing
+    };
+    lock_set(recorded).insert(path.trim_matches('/').to_string());
+    Ok(content)
+  }
+
+  fn local_dir(&self) -> Option<&Path> {
+    self.inner.local_dir()
+  }
+}
+
+fn lock_set(set: &Mutex<BTreeSet<String>>) -> MutexGuard<'_, BTreeSet<String>> {
+  set.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+fn list_children<'a>(paths: impl Iterator<Item = &'a str>, dir: &str) -> Option<Vec<String>> {
+  let dir = dir.trim_matches('/');
+  let prefix = if dir.is_empty() {
+    String::new()
+  } else {
+    format!("{dir}/")
+  };
+
+  let names: BTreeSet<_> = paths
+    .filter_map(|path| path.strip_prefix(prefix.as_str()))
+    .filter_map(|rest| rest.split('/').next())
+    .filter(|name| !name.is_empty())
+    .map(str::to_string)
+    .collect();
+
+  if names.is_empty() {
+    None
+  } else {
+    Some(names.into_iter().collect())
+  }
+}
+
+/// HTTP 来源最多跟随的重定向次数
+const MAX_HTTP_REDIRECTS: usize = 5;
+
+/// HTTP 配置来源
+///
+/// 按 `GET <base>/<path>` 读取文件；目录请求以 `/` 结尾，响应体为按行分隔的条目名。
+/// 路径按段做百分号编码，404 视为不存在，3xx 按 `Location` 最多跟随 5 次同源重定向。
+/// 每次读取受总时限和响应大小上限约束，超出即失败。
+/// 仅支持明文 HTTP/1.0，不支持 HTTPS、认证信息和查询参数，主机可为 `[::1]` 形式的 IPv6 地址。
+#[derive(Clone, Debug)]
+pub struct HttpSource {
+  base_url: String,
+  host: String,
+  port: u16,
+  base_path: String,
+  timeout: Duration,
+  max_response_size: usize,
+}
+
+/// 单次 HTTP 请求的结果
+enum HttpResponse {
+  Body(String),
+  NotFound,
+  Redirect(String),
+}
+
+impl HttpSource {
+  const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+  const DEFAULT_MAX_RESPONSE_SIZE: usize = 16 * 1024 * 1024;
+
+  /// 以 `http://host[:port][/prefix]` 形式的基础地址创建来源
+  pub fn new(base_url: &str) -> Result<Self> {
+    let (host, port, base_path) = split_http_url(base_url).ok_or_else(|| {
+      Error::new(CONFIGERR_INVALIDPATH)
+        .wrap_context("invalid http source url")
+        .wrap_context_with(|| format!("url={base_url}"))
+    })?;
+
+    Ok(Self {
+      base_url: base_url.trim_end_matches('/').to_string(),
+      host,
+      port,
+      base_path: base_path.trim_end_matches('/').to_string(),
+      timeout: Self::DEFAULT_TIMEOUT,
+      max_response_size: Self::DEFAULT_MAX_RESPONSE_SIZE,
+    })
+  }
+
+  /// 设置单次读取的总时限，涵盖连接、跟随重定向和读取响应
+  pub fn with_timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = timeout;
+    self
+  }
+
+  /// 设置单个响应（含响应头）的字节数上限
+  pub fn with_max_response_size(mut self, max_response_size: usize) -> Self {
+    self.max_response_size = max_response_size;
+    self
+  }
+
+  fn get(&self, path: &str) -> Result<Option<String>> {
+    let deadline = Instant::now() + self.timeout;
+    let origin = http_origin(&self.host, self.port);
+    let mut target = format!(
+      "{}/{}",
+      self.base_path,
+      encode_path(path.trim_start_matches('/'))
+    );
+
+    for _ in 0..=MAX_HTTP_REDIRECTS {
+      let url = format!("{origin}{target}");
+      let location = match self
+        .request(&target, deadline)
+        .wrap_context_with(|| format!("url={url}"))?
+      {
+        HttpResponse::Body(body) => return Ok(Some(body)),
+        HttpResponse::NotFound => return Ok(None),
+        HttpResponse::Redirect(location) => location,
+      };
+
+      if location.starts_with('/') && !location.starts_with("//") {
+        target = location;
+        continue;
+      }
+      let (host, port, next_path) = split_http_url(&location).ok_or_else(|| {
+        Error::new(CONFIGERR_SOURCEUNAVAILABLE)
+          .wrap_context("unsupported http redirect location")
+          .wrap_context_with(|| format!("url={url} location={location}"))
+      })?;
+      // 只跟随同源重定向，避免配置来源把请求引向任意主机
+      if host != self.host || port != self.port {
+        return Err(
+          Error::new(CONFIGERR_SOURCEUNAVAILABLE)
+            .wrap_context("cross-origin http redirect rejected")
+            .wrap_context_with(|| format!("url={url} location={location}")),
+        );
+      }
+      target = if next_path.is_empty() {
+        "/".to_string()
+      } else {
+        next_path
+      };
+    }
+
+    Err(
+      Error::new(CONFIGERR_SOURCEUNAVAILABLE)
+        .wrap_context("too many http redirects")
+        .wrap_context_with(|| format!("path={path} max={MAX_HTTP_REDIRECTS}")),
+    )
+  }
+
+  fn request(&self, target: &str, deadline: Instant) -> Result<HttpResponse> {
+    let remaining = || {
+      deadline
+        .checked_duration_since(Instant::now())
+        .filter(|remaining| !remaining.is_zero())
+        .ok_or_else(|| Error::new(CONFIGERR_SOURCEUNAVAILABLE))
+        .wrap_context("http request timed out")
+        .wrap_context_with(|| format!("timeout={:?}", self.timeout))
+    };
+    let unavailable =
+      |err: std::io::Error| Error::new(CONFIGERR_SOURCEUNAVAILABLE).wrap_context(err.to_string());
+
+    let addrs = (self.host.as_str(), self.port)
+      .to_socket_addrs()
+      .map_err(unavailable)
+      .wrap_context("resolve http config source failed")?;
+    let mut connect_err = None;
+    let mut stream = None;
+    for addr in addrs {
+      match TcpStream::connect_timeout(&addr, remaining()?) {
+        Ok(connected) => {
+          stream = Some(connected);
+          break;
+        }
+        Err(err) => connect_err = Some(err),
+      }
+    }
+    let mut stream = stream
+      .ok_or_else(|| {
+        connect_err.map_or_else(
+          || Error::new(CONFIGERR_SOURCEUNAVAILABLE).wrap_context("no address resolved"),
+          unavailable,
+        )
+      })
+      .wrap_context("connect http config source failed")?;
+
+    // HTTP/1.0 请求，服务端不会使用分块编码，读到连接关闭即为完整响应
+    stream
+      .set_write_timeout(Some(remaining()?))
+      .map_err(Error::from_std)
+      .wrap_context("set http timeout failed")?;
+    write!(
+      stream,
+      "GET {target} HTTP/1.0\r\nHost: {}\r\nAccept: */*\r\n\r\n",
+      http_authority(&self.host, self.port)
+    )
+    .map_err(unavailable)
+    .wrap_context("send http request failed")?;
+
+    // 每次读取前按剩余时间重设超时，慢速服务端无法让请求超出总时限
+    let mut response = Vec::new();
+    let mut buf = [0; 8192];
+    loop {
+      stream
+        .set_read_timeout(Some(remaining()?))
+        .map_err(Error::from_std)
+        .wrap_context("set http timeout failed")?;
+      let read = match stream.read(&mut buf) {
+        Ok(0) => break,
+        Ok(read) => read,
+        Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+        Err(err)
+          if matches!(
+            err.kind(),
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+          ) =>
+        {
+          remaining()?;
+          continue;
+        }
+        Err(err) => {
+          return Err(unavailable(err)).wrap_context("read http response failed");
+        }
+      };
+      if response.len() + read > self.max_response_size {
+        return Err(
+          Error::new(CONFIGERR_SOURCEUNAVAILABLE)
+            .wrap_context("http response too large")
+            .wrap_context_with(|| format!("max={}", self.max_response_size)),
+        );
+      }
+      response.extend_from_slice(&buf[..read]);
+    }
+
+    let malformed =
+      || Error::new(CONFIGERR_SOURCEUNAVAILABLE).wrap_context("malformed http response");
+    let header_end = response
+      .windows(4)
+      .position(|window| window == b"\r\n\r\n")
+      .ok_or_else(malformed)?;
+    let head = std::str::from_utf8(&response[..header_end]).map_err(|_| malformed())?;
+    let mut lines = head.lines();
+    let status: u16 = lines
+      .next()
+      .and_then(|line| line.split_whitespace().nth(1))
+      .and_then(|status| status.parse().ok())
+      .ok_or_else(malformed)?;
+
+    match status {
+      200 => String::from_utf8(response[header_end + 4..].to_vec())
+        .map(HttpResponse::Body)
+        .map_err(Error::from_std)
+        .wrap_context("http response body is not utf-8"),
+      404 => Ok(HttpResponse::NotFound),
+      301 | 302 | 303 | 307 | 308 => lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("location"))
+        .map(|(_, location)| HttpResponse::Redirect(location.trim().to_string()))
+        .ok_or_else(|| {
+          malformed().wrap_context_with(|| format!("status={status} without location"))
+        }),
+      _ => Err(
+        Error::new(CONFIGERR_SOURCEUNAVAILABLE)
+          .wrap_context("unexpected http status")
+          .wrap_context_with(|| format!("status={status}")),
+      ),
+    }
+  }
+}
+
+/// 拆分 `http://host[:port][/path]` 为主机、端口和路径，IPv6 主机需写作 `[::1]`
+fn split_http_url(url: &str) -> Option<(String, u16, String)> {
+  let rest = url.strip_prefix("http://")?;
+  let (authority, path) = match rest.find('/') {
+    Some(index) => (&rest[..index], &rest[index..]),
+    None => (rest, ""),
+  };
+  if authority.contains('@') || path.contains(['?', '#']) {
+    return None;
+  }
+
+  let (host, port) = match authority.strip_prefix('[') {
+    Some(bracketed) => {
+      let (host, rest) = bracketed.split_once(']')?;
+      let port = match rest {
+        "" => 80,
+        _ => rest.strip_prefix(':')?.parse().ok()?,
+      };
+      (host, port)
+    }
+    // 未加方括号的主机不能再包含冒号，端口解析失败即可拒绝裸 IPv6 地址
+    None => match authority.split_once(':') {
+      Some((host, port)) => (host, port.parse().ok()?),
+      None => (authority, 80),
+    },
+  };
+  if host.is_empty() {
+    return None;
+  }
+
+  Some((host.to_string(), port, path.to_string()))
+}
+
+fn http_authority(host: &str, port: u16) -> String {
+  if host.contains(':') {
+    format!("[{host}]:{port}")
+  } else {
+    format!("{host}:{port}")
+  }
+}
+
+fn http_origin(host: &str, port: u16) -> String {
+  format!("http://{}", http_authority(host, port))
+}
+
+/// 对路径中非保留字符以外的字节做百分号编码，保留 `/` 分隔符
+fn encode_path(path: &str) -> String {
+  let mut encoded = String::with_capacity(path.len());
+  for byte in path.bytes() {
+    match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+        encoded.push(char::from(byte));
+      }
+      _ => encoded.push_str(&format!("%{byte:02X}")),
+    }
+  }
+  encoded
+}
+
+impl ConfigSource for HttpSource {
+  fn describe(&self) -> String {
+    self.base_url.clone()
+  }
+
+  fn list(&self, dir: &str) -> Result<Option<Vec<String>>> {
+    let dir = dir.trim_matches('/');
+    let Some(body) = self.get(&format!("{dir}/"))? else {
+      return Ok(None);
+    };
+
+    let mut names: Vec<_> = body
+      .lines()
+      .map(str::trim)
+      .filter(|name| !name.is_empty())
+      .map(str::to_string)
+      .collect();
+    names.sort();
+    Ok(Some(names))
+  }
+
+  fn read(&self, path: &str) -> Result<Option<String>> {
+    self.get(path.trim_matches('/'))
+  }
+}
@@ -0,0 +1,342 @@
+//! 手写配置结构绑定模块
+//!
+//! 配合 `bodhi_config_derive::BodhiConfig` 使用：派生宏在编译期调用 [`check_struct_fields`]
+//! 比对结构字段与模板，运行时通过 [`BodhiConfig`] 从分层配置中提取。
+
+use std::fmt;
+
+use bodhi_error::prelude::*;
+use serde::de::DeserializeOwned;
+use syn::{GenericArgument, PathArguments, Type};
+use toml::Value;
+
+use crate::engine::{ConfigEngine, ResolvedLayers};
+use crate::errcode::configerr::*;
+use crate::merge::is_marker_key;
+use crate::validate::{FieldMarker, is_optional_field, value_kind};
+
+/// 绑定到模板路径的手写配置结构
+pub trait BodhiConfig: DeserializeOwned {
+  /// 所属服务
+  const SERVICE: &'static str;
+  /// 在最终合并配置中的路径，空串表示根
+  const PATH: &'static str;
+
+  /// 从分层配置中提取
+  fn from_layers(layers: &ResolvedLayers) -> Result<Self> {
+    let path = if Self::PATH.is_empty() {
+      "."
+    } else {
+      Self::PATH
+    };
+    layers.extract_merged(path)
+  }
+
+  /// 按 profile 解析所属服务并提取
+  fn load(engine: &ConfigEngine, profile: &str) -> Result<Self> {
+    Self::from_layers(&engine.resolve_layers(profile, Self::SERVICE)?)
+  }
+}
+
+/// 手写结构字段描述
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FieldSpec {
+  /// 反序列化时使用的键名
+  pub name: String,
+  /// Rust 类型表达式
+  pub rust_type: String,
+  /// 模板缺少该键时能否反序列化成功，例如 `Option<T>` 或 `#[serde(default)]`
+  pub optional: bool,
+  /// 字段类型是否为绑定到该字段路径的 `BodhiConfig` 结构，其字段由自身的派生检查
+  pub nested: bool,
+}
+
+/// 结构与模板的不一致项
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FieldIssue {
+  /// 模板中存在而结构中缺失的字段
+  Missing { path: String },
+  /// 结构中存在而模板中缺失的必填字段
+  Extra { field: String, path: String },
+  /// 字段类型与模板值不兼容
+  Incompatible {
+    field: String,
+    path: String,
+    rust_type: String,
+    reason: String,
+  },
+}
+
+impl fmt::Display for FieldIssue {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Missing { path } => write!(f, "template field `{path}` is missing from the struct"),
+      Self::Extra { field, path } => write!(
+        f,
+        "field `{field}` has no template value at `{path}`, make it Option<T> or #[serde(default)]"
+      ),
+      Self::Incompatible {
+        field,
+        path,
+        rust_type,
+        reason,
+      } => write!(
+        f,
+        "field `{field}: {rust_type}` is incompatible with template value at `{path}`: {reason}"
+      ),
+    }
+  }
+}
+
+/// 比对结构字段与模板表
+///
+/// `allow_unlisted` 为真时不报告缺失字段，用于含 `#[serde(flatten)]` 的结构。
+pub fn check_struct_fields(
+  schema: &Value,
+  path: &str,
+  fields: &[FieldSpec],
+  allow_unlisted: bool,
+) -> Result<Vec<FieldIssue>> {
+  let target = lookup(schema, path)?;
+  let table = target
+    .as_table()
+    .ok_or_else(|| Error::new(CONFIGERR_TYPEMISMATCH))
+    .wrap_context("bound template path must be a table")
+    .wrap_context_with(|| format!("path={path} kind={}", value_kind(target)))?;
+
+  let mut issues = Vec::new();
+  for field in fields {
+    let field_path = join_path(path, &field.name);
+    let Some(value) = table
+      .get(&field.name)
+      .filter(|_| !is_marker_key(&field.name))
+    else {
+      if !field.optional {
+        issues.push(FieldIssue::Extra {
+          field: field.name.clone(),
+          path: field_path,
+        });
+      }
+      continue;
+    };
+
+    let ty = syn::parse_str::<Type>(&field.rust_type)
+      .map_err(|err| Error::new(CONFIGERR_CODEGENFAILED).wrap_context(err.to_string()))
+      .wrap_context("parse field type failed")
+      .wrap_context_with(|| format!("field={} type={}", field.name, field.rust_type))?;
+    if is_optional_field(table, &field.name) && !field.optional {
+      issues.push(FieldIssue::Incompatible {
+        field: field.name.clone(),
+        path: field_path,
+        rust_type: field.rust_type.clone(),
+        reason: String::from(
+          "template declares the field optional, use Option<T> or #[serde(default)]",
+        ),
+      });
+      continue;
+    }
+    // 没有默认值的字段按标记声明的类型检查
+    let sample = FieldMarker::of(value).map(|marker| marker.sample());
+    let value = sample.as_ref().unwrap_or(value);
+    let checked = if field.nested {
+      check_nested(value)
+    } else {
+      check_type(&ty, value)
+    };
+    if let Err(reason) = checked {
+      issues.push(FieldIssue::Incompatible {
+        field: field.name.clone(),
+        path: field_path,
+        rust_type: field.rust_type.clone(),
+        reason,
+      });
+    }
+  }
+
+  if !allow_unlisted {
+    for key in table.keys().filter(|key| !is_marker_key(key)) {
+      if !fields.iter().any(|field| &field.name == key) {
+        issues.push(FieldIssue::Missing {
+          path: join_path(path, key),
+        });
+      }
+    }
+  }
+
+  Ok(issues)
+}
+
+fn lookup<'a>(schema: &'a Value, path: &str) -> Result<&'a Value> {
+  let mut current = schema;
+  if path.is_empty() || path == "." {
+    return Ok(current);
+  }
+
+  for segment in path.split('.') {
+    current = current
+      .as_table()
+      .and_then(|table| table.get(segment))
+      .ok_or_else(|| Error::new(CONFIGERR_INVALIDPATH))
+      .wrap_context("bound template path not found")
+      .wrap_context_with(|| format!("path={path} segment={segment}"))?;
+  }
+  Ok(current)
+}
+
+fn join_path(path: &str, key: &str) -> String {
+  if path.is_empty() || path == "." {
+    key.to_string()
+  } else {
+    format!("{path}.{key}")
+  }
+}
+
+/// 判断两个字符串是否相同，供派生宏在常量上下文中校验嵌套结构的绑定
+#[doc(hidden)]
+pub const fn same_binding(left: &str, right: &str) -> bool {
+  let (left, right) = (left.as_bytes(), right.as_bytes());
+  if left.len() != right.len() {
+    return false;
+  }
+  let mut index = 0;
+  while index < left.len() {
+    if left[index] != right[index] {
+      return false;
+    }
+    index += 1;
+  }
+  true
+}
+
+fn check_nested(value: &Value) -> std::result::Result<(), String> {
+  match value {
+    Value::Table(_) => Ok(()),
+    _ => Err(format!(
+      "expected table for nested BodhiConfig struct, found {}",
+      value_kind(value)
+    )),
+  }
+}
+
+/// 校验 Rust 类型能否接收模板值
+///
+/// 无法识别的路径类型视为结构或枚举，只接受表或字符串（单元变体）。
+fn check_type(ty: &Type, value: &Value) -> std::result::Result<(), String> {
+  let (name, args) = match ty {
+    Type::Path(type_path) if type_path.qself.is_none() => {
+      let Some(segment) = type_path.path.segments.last() else {
+        return Ok(());
+      };
+      let args: Vec<&Type> = match &segment.arguments {
+        PathArguments::AngleBracketed(arguments) => arguments
+          .args
+          .iter()
+          .filter_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+          })
+          .collect(),
+        _ => Vec::new(),
+      };
+      (segment.ident.to_string(), args)
+    }
+    Type::Reference(reference) => return check_type(&reference.elem, value),
+    Type::Paren(paren) => return check_type(&paren.elem, value),
+    Type::Group(group) => return check_type(&group.elem, value),
+    Type::Array(array) => return check_items(&array.elem, value),
+    Type::Slice(slice) => return check_items(&slice.elem, value),
+    Type::Tuple(tuple) if !tuple.elems.is_empty() => {
+      let Value::Array(items) = value else {
+        return Err(format!("expected array, found {}", value_kind(value)));
+      };
+      if items.len() != tuple.elems.len() {
+        return Err(format!(
+          "expected array of {} items, found {}",
+          tuple.elems.len(),
+          items.len()
+        ));
+      }
+      return tuple
+        .elems
+        .iter()
+        .zip(items)
+        .enumerate()
+        .try_for_each(|(index, (ty, item))| {
+          check_type(ty, item).map_err(|reason| format!("item {index}: {reason}"))
+        });
+    }
+    _ => return Ok(()),
+  };
+
+  let found = || format!("found {}", value_kind(value));
+  if args.is_empty()
+    && let Some((min, max)) = integer_range(&name)
+  {
+    return match value {
+      Value::Integer(number) if (min..=max).contains(&i128::from(*number)) => Ok(()),
+      Value::Integer(number) => Err(format!("{number} is out of range for {name}")),
+      _ => Err(format!("expected integer, {}", found())),
+    };
+  }
+  match (name.as_str(), args.as_slice()) {
+    ("Value", []) => Ok(()),
+    ("Option" | "Box" | "Arc" | "Rc", [inner]) => check_type(inner, value),
+    ("Vec" | "VecDeque" | "HashSet" | "BTreeSet" | "IndexSet", [inner]) => {
+      check_items(inner, value)
+    }
+    ("HashMap" | "BTreeMap" | "IndexMap", [_, inner]) => match value {
+      Value::Table(table) => table.iter().try_for_each(|(key, item)| {
+        check_type(inner, item).map_err(|reason| format!("key `{key}`: {reason}"))
+      }),
+      _ => Err(format!("expected table, {}", found())),
+    },
+    ("bool", []) => match value {
+      Value::Boolean(_) => Ok(()),
+      _ => Err(format!("expected boolean, {}", found())),
+    },
+    ("f32" | "f64", []) => match value {
+      Value::Float(_) | Value::Integer(_) => Ok(()),
+      _ => Err(format!("expected float, {}", found())),
+    },
+    (
+      "String" | "str" | "PathBuf" | "Path" | "IpAddr" | "Ipv4Addr" | "Ipv6Addr" | "SocketAddr"
+      | "SocketAddrV4" | "SocketAddrV6",
+      [],
+    ) => match value {
+      Value::String(_) => Ok(()),
+      _ => Err(format!("expected string, {}", found())),
+    },
+    ("char", []) => match value {
+      Value::String(text) if text.chars().count() == 1 => Ok(()),
+      _ => Err(format!("expected single-character string, {}", found())),
+    },
+    _ => match value {
+      Value::Table(_) | Value::String(_) => Ok(()),
+      _ => Err(format!("expected table or string for {name}, {}", found())),
+    },
+  }
+}
+
+fn check_items(inner: &Type, value: &Value) -> std::result::Result<(), String> {
+  match value {
+    Value::Array(items) => items.iter().enumerate().try_for_each(|(index, item)| {
+      check_type(inner, item).map_err(|reason| format!("item {index}: {reason}"))
+    }),
+    _ => Err(format!("expected array, found {}", value_kind(value))),
+  }
+}
+
+fn integer_range(name: &str) -> Option<(i128, i128)> {
+  let range = match name {
+    "i8" => (i8::MIN.into(), i8::MAX.into()),
+    "i16" => (i16::MIN.into(), i16::MAX.into()),
+    "i32" => (i32::MIN.into(), i32::MAX.into()),
+    "i64" | "isize" | "i128" => (i64::MIN.into(), i64::MAX.into()),
+    "u8" => (0, u8::MAX.into()),
+    "u16" => (0, u16::MAX.into()),
+    "u32" => (0, u32::MAX.into()),
+    "u64" | "usize" | "u128" => (0, i64::MAX.into()),
+    _ => return None,
+  };
+  Some(range)
+}
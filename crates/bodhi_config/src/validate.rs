@@ -5,7 +5,369 @@ use std::collections::BTreeMap;
 use bodhi_error::prelude::*;
 use toml::Value;
 
+use crate::constraint::{ConstraintSchema, ConstraintSet};
 use crate::errcode::configerr::*;
+use crate::merge::{
+  MERGE_RULES_FILE, MergeRuleSet, MergeRules, MergeStrategy, OPTIONAL_KEY, UNSET_KEY, deep_merge,
+  is_marker_key,
+};
+use crate::overrides::OVERRIDE_LAYER;
+
+/// profile 中服务实例覆盖所在的键，例如 `[services.gateway.instances.gw-2]`
+pub const INSTANCES_KEY: &str = "instances";
+
+/// service 模板中声明所用 infra 模块的键，例如 `infra_modules = ["log", "net"]`
+///
+/// infra 模块即 infra 模板的顶层配置段，未声明时使用全部模块。
+pub const INFRA_MODULES_KEY: &str = "infra_modules";
+
+/// profile 中声明服务启用状态的键，例如 `[enabled_services]` 下的 `lobby = false`
+///
+/// `"*"` 为未列出服务的默认值，未声明时全部服务启用。
+pub const ENABLED_SERVICES_KEY: &str = "enabled_services";
+
+/// 模板中声明映射表值结构的键，例如 `[routes."*"]`，profile 可在 `routes` 下新增任意键
+pub const MAP_KEY: &str = "*";
+
+/// 模板字段标记中声明字段种类的保留键，例如 `endpoint = { __kind = "required", type = "string" }`
+///
+/// 可选字段统一在 [`OPTIONAL_KEY`] 中声明，没有默认值的可选字段写作
+/// `__optional = [{ name = "idle_timeout", type = "integer" }]`，读取模板时展开为可选字段标记。
+pub const FIELD_KIND_KEY: &str = "__kind";
+
+/// 可选字段声明中带类型条目的字段名键
+pub const OPTIONAL_NAME_KEY: &str = "name";
+
+/// 模板字段标记中声明字段类型的键
+pub const FIELD_TYPE_KEY: &str = "type";
+
+/// 字段标记可声明的标量类型
+pub const FIELD_TYPES: [&str; 5] = ["string", "integer", "float", "boolean", "datetime"];
+
+/// 没有默认值的模板字段种类
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FieldKind {
+  /// 可选字段，可以缺省，生成 `Option<T>`
+  Optional,
+  /// 必填字段，每个 profile 都必须提供
+  Required,
+}
+
+/// 模板中没有默认值的字段标记
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FieldMarker {
+  pub kind: FieldKind,
+  pub value_type: String,
+}
+
+impl FieldMarker {
+  /// 读取已校验的字段标记，非标记值返回 `None`
+  pub fn of(value: &Value) -> Option<Self> {
+    let table = value.as_table()?;
+    let kind = match table.get(FIELD_KIND_KEY)?.as_str()? {
+      "optional" => FieldKind::Optional,
+      "required" => FieldKind::Required,
+      _ => return None,
+    };
+    let value_type = table.get(FIELD_TYPE_KEY)?.as_str()?.to_string();
+    Some(Self { kind, value_type })
+  }
+
+  /// 该类型的示例值，用于类型比较
+  pub fn sample(&self) -> Value {
+    match self.value_type.as_str() {
+      "integer" => Value::Integer(0),
+      "float" => Value::Float(0.0),
+      "boolean" => Value::Boolean(false),
+      "datetime" => Value::Datetime(toml::value::Datetime {
+        date: Some(toml::value::Date {
+          year: 1970,
+          month: 1,
+          day: 1,
+        }),
+        time: None,
+        offset: None,
+      }),
+      _ => Value::String(String::new()),
+    }
+  }
+
+  /// 该类型对应的 Rust 类型
+  pub fn rust_type(&self) -> &'static str {
+    match self.value_type.as_str() {
+      "integer" => "i64",
+      "float" => "f64",
+      "boolean" => "bool",
+      "datetime" => "bodhi_config::toml::value::Datetime",
+      _ => "String",
+    }
+  }
+}
+
+/// 判断值是否为模板字段标记
+pub fn is_field_marker(value: &Value) -> bool {
+  value
+    .as_table()
+    .is_some_and(|table| table.contains_key(FIELD_KIND_KEY))
+}
+
+/// 展开可选字段声明中带类型的条目
+///
+/// `__optional = [{ name = "idle_timeout", type = "integer" }]` 展开为同名的可选字段标记，
+/// `__optional` 中只保留字段名；重复展开结果不变。
+pub fn expand_optional_entries(template: &mut Value, path: &str) -> Result<()> {
+  let Some(table) = template.as_table_mut() else {
+    return Ok(());
+  };
+
+  if let Some(Value::Array(entries)) = table.get(OPTIONAL_KEY) {
+    let marker_path = format!("{path}.{OPTIONAL_KEY}");
+    let mut names = Vec::with_capacity(entries.len());
+    let mut markers = Vec::new();
+    for entry in entries {
+      let Value::Table(typed) = entry else {
+        names.push(entry.clone());
+        continue;
+      };
+      let name = typed.get(OPTIONAL_NAME_KEY).and_then(Value::as_str);
+      let value_type = typed.get(FIELD_TYPE_KEY).and_then(Value::as_str);
+      let (Some(name), Some(value_type), 2) = (name, value_type, typed.len()) else {
+        return Err(
+          Error::new(CONFIGERR_INVALIDSTRUCTURE)
+            .wrap_context("typed optional entry must be { name = \"...\", type = \"...\" }")
+            .wrap_context_with(|| format!("path={marker_path}")),
+        );
+      };
+      if !FIELD_TYPES.contains(&value_type) {
+        return Err(
+          Error::new(CONFIGERR_INVALIDSTRUCTURE)
+            .wrap_context(
+              "optional field type must be one of string, integer, float, boolean, datetime",
+            )
+            .wrap_context_with(|| format!("path={path}.{name}")),
+        );
+      }
+      let mut marker = toml::map::Map::new();
+      marker.insert(FIELD_KIND_KEY.to_string(), Value::from("optional"));
+      marker.insert(FIELD_TYPE_KEY.to_string(), Value::from(value_type));
+      markers.push((name.to_string(), Value::Table(marker)));
+      names.push(Value::from(name));
+    }
+
+    for (name, marker) in markers {
+      match table.get(&name) {
+        Some(existing) if *existing != marker => {
+          return Err(
+            Error::new(CONFIGERR_INVALIDSTRUCTURE)
+              .wrap_context("typed optional field must not also declare a template value")
+              .wrap_context_with(|| format!("path={path}.{name}")),
+          );
+        }
+        Some(_) => {}
+        None => {
+          table.insert(name, marker);
+        }
+      }
+    }
+    table.insert(OPTIONAL_KEY.to_string(), Value::Array(names));
+  }
+
+  for (key, child) in table.iter_mut() {
+    if key != OPTIONAL_KEY && !is_field_marker(child) {
+      expand_optional_entries(child, &format!("{path}.{key}"))?;
+    }
+  }
+  Ok(())
+}
+
+/// 校验模板中的可选字段声明和字段标记
+///
+/// 只有可选字段允许被覆盖层通过 `__unset` 删除。
+pub fn validate_template_markers(template: &Value, path: &str) -> Result<()> {
+  let Some(table) = template.as_table() else {
+    return Ok(());
+  };
+
+  if let Some(optional) = table.get(OPTIONAL_KEY) {
+    let marker_path = format!("{path}.{OPTIONAL_KEY}");
+    let keys = optional
+      .as_array()
+      .filter(|keys| keys.iter().all(Value::is_str))
+      .ok_or_else(|| Error::new(CONFIGERR_INVALIDSTRUCTURE))
+      .wrap_context("optional marker must be an array of field names")
+      .wrap_context_with(|| format!("path={marker_path}"))?;
+    for key in keys.iter().filter_map(Value::as_str) {
+      if key == OPTIONAL_KEY || !table.contains_key(key) {
+        return unknown_field(
+          &format!("{path}.{key}"),
+          "optional field not declared in template",
+        );
+      }
+    }
+  }
+
+  if let Some(value_schema) = table.get(MAP_KEY) {
+    return validate_map_template(table, value_schema, path);
+  }
+
+  for (key, child) in table {
+    let child_path = format!("{path}.{key}");
+    if is_field_marker(child) {
+      validate_field_marker(child, &child_path, is_optional_field(table, key))?;
+    } else {
+      validate_template_markers(child, &child_path)?;
+    }
+  }
+  Ok(())
+}
+
+/// 校验映射表模板，`"*"` 声明条目的默认值，其余键是按 `"*"` 校验的默认条目
+fn validate_map_template(
+  table: &toml::map::Map<String, Value>,
+  value_schema: &Value,
+  path: &str,
+) -> Result<()> {
+  let schema_path = format!("{path}.{MAP_KEY}");
+  if is_field_marker(value_schema) || !required_fields(value_schema).is_empty() {
+    return Err(
+      Error::new(CONFIGERR_INVALIDSTRUCTURE)
+        .wrap_context("map value schema must declare defaults, not required field markers")
+        .wrap_context_with(|| format!("path={schema_path}")),
+    );
+  }
+  validate_template_markers(value_schema, &schema_path)?;
+
+  for (key, entry) in table {
+    if key != MAP_KEY && !is_marker_key(key) {
+      validate_overlay(entry, value_schema, &format!("{path}.{key}"))?;
+    }
+  }
+  Ok(())
+}
+
+/// 映射条目的默认值，由 `"*"` 声明的值结构去掉标记得到
+pub fn map_entry_default(value_schema: &Value) -> Value {
+  strip_template_markers(value_schema)
+}
+
+/// 查找覆盖层字段对应的模板结构，映射表中的任意键都对应 `"*"`
+pub fn schema_child<'a>(
+  schema_table: &'a toml::map::Map<String, Value>,
+  key: &str,
+) -> Option<&'a Value> {
+  if key == MAP_KEY || is_marker_key(key) {
+    return None;
+  }
+  schema_table.get(MAP_KEY).or_else(|| schema_table.get(key))
+}
+
+fn validate_field_marker(marker: &Value, path: &str, listed_optional: bool) -> Result<()> {
+  let invalid = |reason: &str| {
+    Err(
+      Error::new(CONFIGERR_INVALIDSTRUCTURE)
+        .wrap_context(reason.to_string())
+        .wrap_context_with(|| format!("path={path}")),
+    )
+  };
+  let Some(table) = marker.as_table() else {
+    return invalid("field marker must be a table");
+  };
+  if let Some(key) = table
+    .keys()
+    .find(|key| *key != FIELD_KIND_KEY && *key != FIELD_TYPE_KEY)
+  {
+    return unknown_field(
+      &format!("{path}.{key}"),
+      "field marker only allows __kind and type",
+    );
+  }
+  match table.get(FIELD_KIND_KEY).and_then(Value::as_str) {
+    Some("required") => {}
+    // 可选字段标记只由 `__optional` 的带类型条目展开得到
+    Some("optional") if listed_optional => {}
+    Some("optional") => {
+      return invalid(
+        "declare optional fields in __optional, e.g. __optional = [{ name = \"...\", type = \"integer\" }]",
+      );
+    }
+    _ => return invalid("field marker kind must be required"),
+  }
+  if !table
+    .get(FIELD_TYPE_KEY)
+    .and_then(Value::as_str)
+    .is_some_and(|value_type| FIELD_TYPES.contains(&value_type))
+  {
+    return invalid("field marker type must be one of string, integer, float, boolean, datetime");
+  }
+  Ok(())
+}
+
+/// 去掉模板中的可选字段声明和字段标记，得到参与合并的默认值
+///
+/// 映射表去掉 `"*"`，默认条目补全 `"*"` 声明的默认值。
+pub fn strip_template_markers(template: &Value) -> Value {
+  match template {
+    Value::Table(table) if table.contains_key(MAP_KEY) => {
+      let default = map_entry_default(&table[MAP_KEY]);
+      Value::Table(
+        table
+          .iter()
+          .filter(|(key, _)| *key != MAP_KEY && !is_marker_key(key))
+          .map(|(key, entry)| {
+            let mut merged = default.clone();
+            deep_merge(&mut merged, &strip_template_markers(entry));
+            (key.clone(), merged)
+          })
+          .collect(),
+      )
+    }
+    Value::Table(table) => Value::Table(
+      table
+        .iter()
+        .filter(|(key, child)| *key != OPTIONAL_KEY && !is_field_marker(child))
+        .map(|(key, child)| (key.clone(), strip_template_markers(child)))
+        .collect(),
+    ),
+    _ => template.clone(),
+  }
+}
+
+/// 判断模板表是否将字段声明为可选，可选字段均列在 `__optional` 中
+pub fn is_optional_field(schema_table: &toml::map::Map<String, Value>, key: &str) -> bool {
+  schema_table
+    .get(OPTIONAL_KEY)
+    .and_then(Value::as_array)
+    .is_some_and(|keys| keys.iter().any(|optional| optional.as_str() == Some(key)))
+}
+
+/// 收集模板中的必填字段路径，路径以点分隔
+pub fn required_fields(template: &Value) -> Vec<String> {
+  let mut paths = Vec::new();
+  collect_required_fields(template, "", &mut paths);
+  paths
+}
+
+fn collect_required_fields(template: &Value, prefix: &str, paths: &mut Vec<String>) {
+  // 映射条目由 profile 决定，不参与必填检查
+  let Some(table) = template
+    .as_table()
+    .filter(|table| !table.contains_key(MAP_KEY))
+  else {
+    return;
+  };
+  for (key, child) in table {
+    let path = if prefix.is_empty() {
+      key.clone()
+    } else {
+      format!("{prefix}.{key}")
+    };
+    match FieldMarker::of(child) {
+      Some(marker) if marker.kind == FieldKind::Required => paths.push(path),
+      Some(_) => {}
+      None => collect_required_fields(child, &path, paths),
+    }
+  }
+}
 
 pub fn validate_service_template(
   service: &str,
@@ -18,10 +380,21 @@ pub fn validate_service_template(
     "service template root must be a table",
   )?;
 
+  validate_template_markers(service_cfg, &format!("template.service.{service}"))?;
+
+  if service_table.contains_key(INSTANCES_KEY) {
+    return Err(
+      Error::new(CONFIGERR_INVALIDSTRUCTURE)
+        .wrap_context("service template must not define reserved key")
+        .wrap_context_with(|| format!("path=template.service.{service}.{INSTANCES_KEY}")),
+    );
+  }
+
+  let service_infra = service_infra(service, base_infra, service_cfg)?;
   if let Some(infra) = service_table.get("infra") {
     validate_overlay(
       infra,
-      base_infra,
+      &service_infra,
       &format!("template.service.{service}.infra"),
     )?;
   }
@@ -29,26 +402,342 @@ pub fn validate_service_template(
   Ok(())
 }
 
+/// 按 service 模板声明的 infra 模块筛选 infra 模板，未声明时返回全部模块
+pub fn service_infra(service: &str, base_infra: &Value, service_cfg: &Value) -> Result<Value> {
+  let Some(modules) = service_cfg.get(INFRA_MODULES_KEY) else {
+    return Ok(base_infra.clone());
+  };
+  let path = format!("template.service.{service}.{INFRA_MODULES_KEY}");
+  let modules = modules
+    .as_array()
+    .filter(|modules| modules.iter().all(Value::is_str))
+    .ok_or_else(|| Error::new(CONFIGERR_INVALIDSTRUCTURE))
+    .wrap_context("infra modules must be an array of module names")
+    .wrap_context_with(|| format!("path={path}"))?;
+
+  let base_table = expect_table(
+    base_infra,
+    "template.infra",
+    "infra template must be a table",
+  )?;
+  let mut selected = toml::map::Map::new();
+  for module in modules.iter().filter_map(Value::as_str) {
+    let Some(module_cfg) = base_table.get(module) else {
+      return Err(
+        Error::new(CONFIGERR_INFRAMODULENOTFOUND)
+          .wrap_context("service template references unknown infra module")
+          .wrap_context_with(|| format!("path={path} module={module}")),
+      );
+    };
+    selected.insert(module.to_string(), module_cfg.clone());
+  }
+  Ok(Value::Table(selected))
+}
+
+/// 校验完整的 profile，包括结构和模板声明的必填字段
 pub fn validate_profile(
   profile: &str,
   profile_cfg: &Value,
   base_infra: &Value,
   service_templates: &BTreeMap<String, Value>,
 ) -> Result<()> {
-  let profile_table = expect_table(
-    profile_cfg,
+  validate_profile_layer(profile, profile_cfg, base_infra, service_templates)?;
+  validate_required_fields(profile, profile_cfg, base_infra, service_templates)
+}
+
+/// 只校验 profile 文件结构，用于叠加 profile 和本地覆盖文件等部分覆盖
+pub fn validate_profile_layer(
+  profile: &str,
+  profile_cfg: &Value,
+  base_infra: &Value,
+  service_templates: &BTreeMap<String, Value>,
+) -> Result<()> {
+  validate_overlay_file(
+    OverlayKind::Profile,
     &format!("profile.{profile}"),
-    "profile root must be a table",
+    profile_cfg,
+    base_infra,
+    service_templates,
+  )
+}
+
+/// 判断服务在 profile 中是否启用
+pub fn is_service_enabled(profile_cfg: &Value, service: &str) -> bool {
+  profile_cfg
+    .get(ENABLED_SERVICES_KEY)
+    .and_then(|flags| flags.get(service).or_else(|| flags.get(MAP_KEY)))
+    .and_then(Value::as_bool)
+    .unwrap_or(true)
+}
+
+/// 校验 profile 提供了模板声明的全部必填字段，未启用的服务及其未使用的 infra 模块不检查
+pub fn validate_required_fields(
+  profile: &str,
+  profile_cfg: &Value,
+  base_infra: &Value,
+  service_templates: &BTreeMap<String, Value>,
+) -> Result<()> {
+  let enabled_services: Vec<_> = service_templates
+    .iter()
+    .filter(|(service, _)| is_service_enabled(profile_cfg, service))
+    .collect();
+  let mut used_infra = toml::map::Map::new();
+  for (service, service_cfg) in &enabled_services {
+    if let Value::Table(modules) = service_infra(service, base_infra, service_cfg)? {
+      used_infra.extend(modules);
+    }
+  }
+  let infra_paths = required_fields(&Value::Table(used_infra))
+    .into_iter()
+    .map(|path| format!("infra.{path}"));
+  let service_paths = enabled_services
+    .into_iter()
+    .flat_map(|(service, service_cfg)| {
+      required_fields(&service_schema(service_cfg))
+        .into_iter()
+        .map(move |path| format!("services.{service}.{path}"))
+    });
+
+  for path in infra_paths.chain(service_paths) {
+    if value_at(profile_cfg, &path).is_none() {
+      return Err(
+        Error::new(CONFIGERR_REQUIREDFIELDMISSING)
+          .wrap_context("profile must supply field required by templates")
+          .wrap_context_with(|| format!("profile={profile} path=profile.{profile}.{path}")),
+      );
+    }
+  }
+  Ok(())
+}
+
+/// 校验维度覆盖文件，结构与 profile 相同但不允许声明实例
+pub fn validate_dimension_overlay(
+  dimension: &str,
+  value: &str,
+  overlay_cfg: &Value,
+  base_infra: &Value,
+  service_templates: &BTreeMap<String, Value>,
+) -> Result<()> {
+  validate_overlay_file(
+    OverlayKind::Dimension,
+    &format!("{dimension}.{value}"),
+    overlay_cfg,
+    base_infra,
+    service_templates,
+  )
+}
+
+/// 校验临时覆盖，结构与 profile 相同但不允许声明实例
+pub fn validate_overrides(
+  overrides_cfg: &Value,
+  base_infra: &Value,
+  service_templates: &BTreeMap<String, Value>,
+) -> Result<()> {
+  validate_overlay_file(
+    OverlayKind::Override,
+    OVERRIDE_LAYER,
+    overrides_cfg,
+    base_infra,
+    service_templates,
+  )
+}
+
+/// 校验合并规则，规则路径必须指向模板中的数组
+pub fn validate_merge_rules(
+  rule_set: &MergeRuleSet,
+  base_infra: &Value,
+  service_templates: &BTreeMap<String, Value>,
+) -> Result<()> {
+  validate_rule_paths(&rule_set.infra, base_infra, "infra")?;
+  for (service, rules) in &rule_set.services {
+    let Some(service_template) = service_templates.get(service) else {
+      return Err(
+        Error::new(CONFIGERR_SERVICENOTFOUND)
+          .wrap_context("merge rules reference unknown service")
+          .wrap_context_with(|| format!("file={MERGE_RULES_FILE} service={service}")),
+      );
+    };
+    validate_rule_paths(
+      rules,
+      &service_schema(service_template),
+      &format!("services.{service}"),
+    )?;
+  }
+  Ok(())
+}
+
+/// 校验约束文件，约束路径必须指向模板中的字段且适用于字段类型
+pub fn validate_constraints(
+  schema: &ConstraintSchema,
+  base_infra: &Value,
+  service_templates: &BTreeMap<String, Value>,
+) -> Result<()> {
+  validate_constraint_paths(&schema.infra, base_infra, "infra")?;
+  for (service, constraints) in &schema.services {
+    if let Some(service_template) = service_templates.get(service) {
+      validate_constraint_paths(
+        constraints,
+        &service_schema(service_template),
+        &format!("services.{service}"),
+      )?;
+    }
+  }
+  Ok(())
+}
+
+fn validate_constraint_paths(
+  constraints: &ConstraintSet,
+  schema: &Value,
+  section: &str,
+) -> Result<()> {
+  for (path, constraint) in constraints.iter() {
+    let field = path.split('.').try_fold(schema, |current, segment| {
+      let table = current.as_table()?;
+      if segment == MAP_KEY {
+        table.get(MAP_KEY)
+      } else {
+        table.get(segment)
+      }
+    });
+    let Some(field) = field else {
+      return unknown_field(
+        &format!("{section}.{path}"),
+        "constraint path not found in templates",
+      );
+    };
+
+    let field = FieldMarker::of(field).map_or_else(|| field.clone(), |marker| marker.sample());
+    let kind = value_kind(&field);
+    let applies = |rule: &str| match rule {
+      "min" | "max" => matches!(kind, "integer" | "float"),
+      "pattern" | "format" => kind == "string",
+      _ => matches!(kind, "string" | "array" | "table"),
+    };
+    let rules = [
+      ("min", constraint.min.is_some()),
+      ("max", constraint.max.is_some()),
+      ("min_length", constraint.min_length.is_some()),
+      ("max_length", constraint.max_length.is_some()),
+      ("non_empty", constraint.non_empty),
+      ("pattern", constraint.pattern.is_some()),
+      ("format", constraint.format.is_some()),
+    ];
+    let mismatched = rules
+      .iter()
+      .find(|(rule, declared)| *declared && !applies(rule))
+      .map(|(rule, _)| *rule)
+      .or_else(|| {
+        let mismatched_item = constraint
+          .one_of
+          .iter()
+          .any(|item| value_kind(item) != kind);
+        mismatched_item.then_some("one_of")
+      });
+    if let Some(rule) = mismatched {
+      return Err(
+        Error::new(CONFIGERR_INVALIDSTRUCTURE)
+          .wrap_context("constraint does not apply to the template field type")
+          .wrap_context_with(|| format!("path={section}.{path} rule={rule} field_type={kind}")),
+      );
+    }
+  }
+  Ok(())
+}
+
+/// 校验覆盖层中按键合并的数组，每个元素都必须是带合并键的表
+pub fn validate_merge_overlay(rules: &MergeRules, overlay: &Value, path: &str) -> Result<()> {
+  for (rule_path, strategy) in rules.iter() {
+    let MergeStrategy::MergeByKey(key) = strategy else {
+      continue;
+    };
+    let Some(Value::Array(items)) = value_at(overlay, rule_path) else {
+      continue;
+    };
+
+    for (index, item) in items.iter().enumerate() {
+      if item.get(key).is_none_or(Value::is_table) {
+        return Err(
+          Error::new(CONFIGERR_INVALIDSTRUCTURE)
+            .wrap_context("merge_by_key array item must be a table with the merge key")
+            .wrap_context_with(|| format!("path={path}.{rule_path}[{index}] key={key}")),
+        );
+      }
+    }
+  }
+  Ok(())
+}
+
+fn validate_rule_paths(rules: &MergeRules, schema: &Value, section: &str) -> Result<()> {
+  for (rule_path, strategy) in rules.iter() {
+    if !matches!(value_at(schema, rule_path), Some(Value::Array(_))) {
+      return Err(
+        Error::new(CONFIGERR_INVALIDSTRUCTURE)
+          .wrap_context("merge rule path must refer to an array in templates")
+          .wrap_context_with(|| format!("file={MERGE_RULES_FILE} path={section}.{rule_path}")),
+      );
+    }
+    if matches!(strategy, MergeStrategy::MergeByKey(_)) {
+      validate_merge_overlay(
+        &MergeRules::new().with(rule_path, strategy.clone()),
+        schema,
+        &format!("template.{section}"),
+      )?;
+    }
+  }
+  Ok(())
+}
+
+pub(crate) fn value_at<'a>(root: &'a Value, path: &str) -> Option<&'a Value> {
+  path
+    .split('.')
+    .try_fold(root, |current, segment| current.as_table()?.get(segment))
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum OverlayKind {
+  Profile,
+  Dimension,
+  Override,
+}
+
+impl OverlayKind {
+  fn as_str(self) -> &'static str {
+    match self {
+      Self::Profile => "profile",
+      Self::Dimension => "dimension overlay",
+      Self::Override => "override",
+    }
+  }
+}
+
+fn validate_overlay_file(
+  kind: OverlayKind,
+  path: &str,
+  overlay_cfg: &Value,
+  base_infra: &Value,
+  service_templates: &BTreeMap<String, Value>,
+) -> Result<()> {
+  let overlay_table = expect_table(
+    overlay_cfg,
+    path,
+    &format!("{} root must be a table", kind.as_str()),
   )?;
 
-  for (key, value) in profile_table {
+  for (key, value) in overlay_table {
     match key.as_str() {
-      "infra" => validate_overlay(value, base_infra, &format!("profile.{profile}.infra"))?,
-      "services" => validate_profile_services(profile, value, base_infra, service_templates)?,
+      "infra" => validate_overlay(value, base_infra, &format!("{path}.infra"))?,
+      "services" => validate_overlay_services(kind, path, value, base_infra, service_templates)?,
+      ENABLED_SERVICES_KEY if kind == OverlayKind::Profile => {
+        validate_enabled_services(value, service_templates, path)?
+      }
       _ => {
+        let allowed = match kind {
+          OverlayKind::Profile => "infra, services and enabled_services",
+          _ => "infra and services",
+        };
         return unknown_field(
-          &format!("profile.{profile}.{key}"),
-          "profile root only allows infra and services",
+          &format!("{path}.{key}"),
+          &format!("{} root only allows {allowed}", kind.as_str()),
         );
       }
     }
@@ -62,38 +751,43 @@ pub fn service_schema(service_cfg: &Value) -> Value {
     Value::Table(table) => {
       let mut cloned = table.clone();
       cloned.remove("infra");
+      cloned.remove(INFRA_MODULES_KEY);
       Value::Table(cloned)
     }
     _ => Value::Table(Default::default()),
   }
 }
 
-fn validate_profile_services(
-  profile: &str,
+fn validate_overlay_services(
+  kind: OverlayKind,
+  path: &str,
   services_value: &Value,
   base_infra: &Value,
   service_templates: &BTreeMap<String, Value>,
 ) -> Result<()> {
+  let services_path = format!("{path}.services");
   let services_table = expect_table(
     services_value,
-    &format!("profile.{profile}.services"),
-    "profile services must be a table",
+    &services_path,
+    &format!("{} services must be a table", kind.as_str()),
   )?;
 
   for (service, service_override) in services_table {
+    let service_path = format!("{services_path}.{service}");
     let Some(service_template) = service_templates.get(service) else {
       return Err(
         Error::new(CONFIGERR_SERVICENOTFOUND)
-          .wrap_context("profile references unknown service")
-          .wrap_context_with(|| format!("profile={profile} service={service}")),
+          .wrap_context(format!("{} references unknown service", kind.as_str()))
+          .wrap_context_with(|| format!("path={service_path} service={service}")),
       );
     };
 
     let service_override_table = expect_table(
       service_override,
-      &format!("profile.{profile}.services.{service}"),
-      "profile service override must be a table",
+      &service_path,
+      &format!("{} service override must be a table", kind.as_str()),
     )?;
+    let service_infra = service_infra(service, base_infra, service_template)?;
     let service_schema = service_schema(service_template);
     let service_schema_table = expect_table(
       &service_schema,
@@ -102,26 +796,87 @@ fn validate_profile_services(
     )?;
 
     for (key, value) in service_override_table {
-      if key == "infra" {
-        validate_overlay(
-          value,
-          base_infra,
-          &format!("profile.{profile}.services.{service}.infra"),
-        )?;
+      if key == INSTANCES_KEY && kind == OverlayKind::Profile {
+        validate_instances(value, &service_infra, service_schema_table, &service_path)?;
         continue;
       }
+      validate_service_field(
+        key,
+        value,
+        &service_infra,
+        service_schema_table,
+        &service_path,
+      )?;
+    }
+  }
 
-      let Some(schema_value) = service_schema_table.get(key) else {
-        return unknown_field(
-          &format!("profile.{profile}.services.{service}.{key}"),
-          "field not found in service template",
-        );
-      };
+  Ok(())
+}
+
+fn validate_enabled_services(
+  flags_value: &Value,
+  service_templates: &BTreeMap<String, Value>,
+  path: &str,
+) -> Result<()> {
+  let flags_path = format!("{path}.{ENABLED_SERVICES_KEY}");
+  let flags_table = expect_table(flags_value, &flags_path, "enabled services must be a table")?;
+
+  for (service, flag) in flags_table {
+    let flag_path = format!("{flags_path}.{service}");
+    if service != MAP_KEY && !service_templates.contains_key(service) {
+      return Err(
+        Error::new(CONFIGERR_SERVICENOTFOUND)
+          .wrap_context("enabled services reference unknown service")
+          .wrap_context_with(|| format!("path={flag_path} service={service}")),
+      );
+    }
+    if !flag.is_bool() {
+      return Err(
+        Error::new(CONFIGERR_TYPEMISMATCH)
+          .wrap_context("service enabled flag must be a boolean")
+          .wrap_context_with(|| format!("path={flag_path}")),
+      );
+    }
+  }
+
+  Ok(())
+}
 
-      validate_overlay(
+fn validate_instances(
+  instances_value: &Value,
+  service_infra: &Value,
+  service_schema_table: &toml::map::Map<String, Value>,
+  path: &str,
+) -> Result<()> {
+  let instances_path = format!("{path}.{INSTANCES_KEY}");
+  let instances_table = expect_table(
+    instances_value,
+    &instances_path,
+    "service instances must be a table",
+  )?;
+
+  for (instance, instance_override) in instances_table {
+    let instance_path = format!("{instances_path}.{instance}");
+    if !is_valid_instance_name(instance) {
+      return Err(
+        Error::new(CONFIGERR_INVALIDSTRUCTURE)
+          .wrap_context("instance name must be non-empty and free of '@', '/', '\\' and '.'")
+          .wrap_context_with(|| format!("path={instance_path}")),
+      );
+    }
+
+    let instance_table = expect_table(
+      instance_override,
+      &instance_path,
+      "service instance override must be a table",
+    )?;
+    for (key, value) in instance_table {
+      validate_service_field(
+        key,
         value,
-        schema_value,
-        &format!("profile.{profile}.services.{service}.{key}"),
+        service_infra,
+        service_schema_table,
+        &instance_path,
       )?;
     }
   }
@@ -129,11 +884,55 @@ fn validate_profile_services(
   Ok(())
 }
 
+fn validate_service_field(
+  key: &str,
+  value: &Value,
+  service_infra: &Value,
+  service_schema_table: &toml::map::Map<String, Value>,
+  path: &str,
+) -> Result<()> {
+  if key == "infra" {
+    return validate_overlay(value, service_infra, &format!("{path}.infra"));
+  }
+  if key == UNSET_KEY {
+    return validate_unset(value, service_schema_table, path);
+  }
+  if key == OPTIONAL_KEY {
+    return unknown_field(
+      &format!("{path}.{key}"),
+      "optional marker only allowed in templates",
+    );
+  }
+
+  let Some(schema_value) = schema_child(service_schema_table, key) else {
+    return unknown_field(
+      &format!("{path}.{key}"),
+      "field not found in service template",
+    );
+  };
+  validate_overlay(value, schema_value, &format!("{path}.{key}"))
+}
+
+fn is_valid_instance_name(instance: &str) -> bool {
+  !instance.is_empty()
+    && !instance
+      .chars()
+      .any(|ch| matches!(ch, '@' | '/' | '\\' | '.') || ch.is_whitespace())
+}
+
 fn validate_overlay(overlay: &Value, schema: &Value, path: &str) -> Result<()> {
+  if let Some(marker) = FieldMarker::of(schema) {
+    return validate_overlay(overlay, &marker.sample(), path);
+  }
+
   match (overlay, schema) {
     (Value::Table(overlay_table), Value::Table(schema_table)) => {
       for (key, overlay_value) in overlay_table {
-        let Some(schema_value) = schema_table.get(key) else {
+        if key == UNSET_KEY {
+          validate_unset(overlay_value, schema_table, path)?;
+          continue;
+        }
+        let Some(schema_value) = schema_child(schema_table, key) else {
           return unknown_field(&format!("{path}.{key}"), "field not found in schema");
         };
 
@@ -157,6 +956,39 @@ fn validate_overlay(overlay: &Value, schema: &Value, path: &str) -> Result<()> {
   }
 }
 
+/// 校验删除标记，只允许删除模板声明为可选的字段
+fn validate_unset(
+  unset_value: &Value,
+  schema_table: &toml::map::Map<String, Value>,
+  path: &str,
+) -> Result<()> {
+  let unset_path = format!("{path}.{UNSET_KEY}");
+  let keys = unset_value
+    .as_array()
+    .filter(|keys| keys.iter().all(Value::is_str))
+    .ok_or_else(|| Error::new(CONFIGERR_INVALIDSTRUCTURE))
+    .wrap_context("unset marker must be an array of field names")
+    .wrap_context_with(|| format!("path={unset_path}"))?;
+
+  for key in keys.iter().filter_map(Value::as_str) {
+    // 映射表的条目都可以删除
+    if schema_table.contains_key(MAP_KEY) && schema_child(schema_table, key).is_some() {
+      continue;
+    }
+    if !schema_table.contains_key(key) || key == OPTIONAL_KEY {
+      return unknown_field(&format!("{path}.{key}"), "unset field not found in schema");
+    }
+    if !is_optional_field(schema_table, key) {
+      return Err(
+        Error::new(CONFIGERR_INVALIDSTRUCTURE)
+          .wrap_context("only fields declared optional in templates can be unset")
+          .wrap_context_with(|| format!("path={path}.{key}")),
+      );
+    }
+  }
+  Ok(())
+}
+
 fn expect_table<'a>(
   value: &'a Value,
   path: &str,
@@ -189,7 +1021,7 @@ fn same_kind(left: &Value, right: &Value) -> bool {
   )
 }
 
-fn value_kind(value: &Value) -> &'static str {
+pub(crate) fn value_kind(value: &Value) -> &'static str {
   match value {
     Value::String(_) => "string",
     Value::Integer(_) => "integer",
@@ -114,6 +114,157 @@ fn gen_rust_json_report_should_include_per_service_and_global_unused_views() {
   assert!(!global_unused.contains(&"server.grpc_port".to_string()));
 }
 
+#[test]
+fn gen_rust_with_values_should_write_config_static() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  let output_path = tempdir.path().join("generated/gateway_config.rs");
+
+  write_cli_test_config(&config_dir);
+
+  let output = Command::new(env!("CARGO_BIN_EXE_bodhi_config"))
+    .arg("--config-dir")
+    .arg(&config_dir)
+    .arg("gen-rust")
+    .arg("--profile")
+    .arg("dev")
+    .arg("--service")
+    .arg("gateway")
+    .arg("--output")
+    .arg(&output_path)
+    .arg("--with-values")
+    .output()
+    .expect("run bodhi_config gen-rust --with-values");
+
+  assert!(
+    output.status.success(),
+    "stderr={}",
+    String::from_utf8_lossy(&output.stderr)
+  );
+
+  let code = fs::read_to_string(&output_path).expect("read generated rust values");
+  assert!(code.contains("pub use merged::CONFIG;"));
+  assert!(code.contains("http_port: 18080u16,"));
+  assert!(code.contains("name: String::from(\"default\"),"));
+}
+
+#[test]
+fn show_should_apply_overlay_file_then_set_overrides() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  let overlay_path = tempdir.path().join("debug.toml");
+
+  write_cli_test_config(&config_dir);
+  fs::write(
+    &overlay_path,
+    "[infra.service]\nname = \"debug\"\n\n[services.gateway.server]\nhttp_port = 1\n",
+  )
+  .expect("write overlay file");
+
+  let output = Command::new(env!("CARGO_BIN_EXE_bodhi_config"))
+    .arg("--config-dir")
+    .arg(&config_dir)
+    .arg("show")
+    .arg("--profile")
+    .arg("dev")
+    .arg("--service")
+    .arg("gateway")
+    .arg("--overlay")
+    .arg(&overlay_path)
+    .arg("--set")
+    .arg("services.gateway.server.http_port=9999")
+    .output()
+    .expect("run bodhi_config show with overrides");
+
+  assert!(
+    output.status.success(),
+    "stderr={}",
+    String::from_utf8_lossy(&output.stderr)
+  );
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  assert!(stdout.contains("http_port = 9999"));
+  assert!(stdout.contains("name = \"debug\""));
+
+  let output = Command::new(env!("CARGO_BIN_EXE_bodhi_config"))
+    .arg("--config-dir")
+    .arg(&config_dir)
+    .arg("show")
+    .arg("--profile")
+    .arg("dev")
+    .arg("--service")
+    .arg("gateway")
+    .arg("--set")
+    .arg("services.gateway.server.http_port=fast")
+    .output()
+    .expect("run bodhi_config show with mistyped override");
+  assert!(!output.status.success());
+}
+
+#[test]
+fn gen_should_nest_dimension_products_under_profile_dir() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+
+  write_cli_test_config(&config_dir);
+  fs::create_dir_all(config_dir.join("region")).expect("create region dir");
+  fs::write(
+    config_dir.join("dimensions.toml"),
+    "[[dimension]]\nname = \"region\"\n",
+  )
+  .expect("write dimensions");
+  fs::write(
+    config_dir.join("region/eu.toml"),
+    "[services.gateway.server]\nhttp_port = 1\n",
+  )
+  .expect("write region overlay");
+  fs::write(config_dir.join("profile/stanley.toml"), "").expect("write stanley profile");
+
+  let output = Command::new(env!("CARGO_BIN_EXE_bodhi_config"))
+    .arg("--config-dir")
+    .arg(&config_dir)
+    .arg("gen")
+    .arg("--profile")
+    .arg("dev,stanley")
+    .arg("--dim")
+    .arg("region=eu")
+    .arg("--format")
+    .arg("toml")
+    .output()
+    .expect("run bodhi_config gen with dimensions");
+  assert!(
+    output.status.success(),
+    "stderr={}",
+    String::from_utf8_lossy(&output.stderr)
+  );
+
+  let product = fs::read_to_string(
+    config_dir.join("product/dev+stanley/dimensions/region/eu/toml/gateway.toml"),
+  )
+  .expect("read dimension product");
+  assert!(product.contains("http_port = 1"));
+  let mut entries: Vec<_> = fs::read_dir(config_dir.join("product"))
+    .expect("list product dir")
+    .map(|entry| entry.expect("read product entry").file_name())
+    .collect();
+  entries.sort();
+  assert_eq!(entries, ["dev+stanley"]);
+
+  let output = Command::new(env!("CARGO_BIN_EXE_bodhi_config"))
+    .arg("--config-dir")
+    .arg(&config_dir)
+    .arg("show")
+    .arg("--profile")
+    .arg("dev")
+    .arg("--service")
+    .arg("gateway")
+    .arg("--dim")
+    .arg("eu")
+    .output()
+    .expect("run bodhi_config show with malformed dimension");
+  assert!(!output.status.success());
+  assert!(String::from_utf8_lossy(&output.stderr).contains("name=value"));
+}
+
 fn write_cli_test_config(config_dir: &Path) {
   fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
   fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
@@ -1,5 +1,6 @@
 use std::fs;
 
+use bodhi_config::codegen::render_rust_types;
 use bodhi_config::prelude::*;
 use bodhi_error::errcode::BODHIERR_SYS;
 use tempfile::tempdir;
@@ -263,6 +264,7 @@ fn engine_should_apply_type_override_rules_from_file() {
         root_struct_name: String::from("Config"),
         type_overrides: TypeOverrideRules::from_file(&type_rules_path)
           .expect("load type override rules"),
+        ..RustCodegenOptions::default()
       },
     )
     .expect("render rust types with overrides");
@@ -315,6 +317,7 @@ fn engine_should_apply_wildcard_path_override_rules() {
         root_struct_name: String::from("Config"),
         type_overrides: TypeOverrideRules::from_file(&type_rules_path)
           .expect("load wildcard type override rules"),
+        ..RustCodegenOptions::default()
       },
     )
     .expect("render rust types with wildcard overrides");
@@ -376,6 +379,7 @@ fn engine_should_report_matched_type_override_rules() {
         root_struct_name: String::from("Config"),
         type_overrides: TypeOverrideRules::from_file(&type_rules_path)
           .expect("load type override rules"),
+        ..RustCodegenOptions::default()
       },
     )
     .expect("render rust types report");
@@ -437,6 +441,7 @@ fn engine_should_report_unused_type_override_rules() {
         root_struct_name: String::from("Config"),
         type_overrides: TypeOverrideRules::from_file(&type_rules_path)
           .expect("load type override rules"),
+        ..RustCodegenOptions::default()
       },
     )
     .expect("render rust types report");
@@ -458,3 +463,159 @@ fn engine_should_report_unused_type_override_rules() {
       .any(|rule| rule.rule_key == "id" && rule.rule_source == TypeOverrideSource::Field)
   );
 }
+
+#[test]
+fn engine_should_render_value_literals_with_type_overrides() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\nratio = 0.5\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    "[server]\nhttp_port = 8080\nhosts = [\"a\", \"b\"]\n[[routes]]\nprefix = \"/api\"\n",
+  )
+  .expect("write gateway template");
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    "[services.gateway.server]\nhttp_port = 18080\n",
+  )
+  .expect("write dev profile");
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  let code = engine
+    .render_rust_values("dev", "gateway")
+    .expect("render rust values");
+
+  assert!(code.contains("pub static CONFIG: std::sync::LazyLock<Config>"));
+  assert!(code.contains("pub use merged::CONFIG;"));
+  assert!(code.contains("http_port: 18080u16,"));
+  assert!(code.contains("level: String::from(\"INFO\"),"));
+  assert!(code.contains("ratio: 0.5f64,"));
+  assert!(code.contains("hosts: vec![String::from(\"a\"), String::from(\"b\")],"));
+  assert!(code.contains("routes: vec![RoutesItem {"));
+
+  let mut rules = TypeOverrideRules::default();
+  rules.path_types.insert(
+    String::from("server.http_port"),
+    String::from("Option<u32>"),
+  );
+  rules.path_types.insert(
+    String::from("server.hosts"),
+    String::from("std::collections::BTreeSet<String>"),
+  );
+  let code = engine
+    .render_rust_types_with(
+      "dev",
+      "gateway",
+      &RustCodegenOptions {
+        type_overrides: rules,
+        with_values: true,
+        ..RustCodegenOptions::default()
+      },
+    )
+    .expect("render rust values with overrides");
+
+  assert!(code.contains("http_port: Some(18080u32),"));
+  assert!(code.contains(
+    "hosts: std::collections::BTreeSet::from([String::from(\"a\"), String::from(\"b\")]),"
+  ));
+  assert!(
+    !engine
+      .render_rust_types("dev", "gateway")
+      .expect("render rust types")
+      .contains("CONFIG")
+  );
+}
+
+#[test]
+fn value_literals_should_reject_unrenderable_override_types() {
+  let value: toml::Value = toml::from_str("[server]\nhttp_port = 8080\n").expect("parse config");
+  let mut rules = TypeOverrideRules::default();
+  rules.path_types.insert(
+    String::from("server.http_port"),
+    String::from("std::num::NonZeroU16"),
+  );
+
+  let err = render_rust_types(
+    &value,
+    &RustCodegenOptions {
+      type_overrides: rules,
+      with_values: true,
+      ..RustCodegenOptions::default()
+    },
+  )
+  .expect_err("unknown override type should fail");
+  assert_eq!(err.code(), CONFIGERR_CODEGENFAILED);
+}
+
+#[test]
+fn engine_should_reuse_base_template_structs() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("template/base")).expect("create template base dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/base/base_server.toml"),
+    "[server]\nhttp_port = 8000\ngrpc_port = 50000\n\n[limits]\nmax_conns = 100\n",
+  )
+  .expect("write base server");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    concat!(
+      "extends = \"base_server\"\n\n",
+      "[server]\nhttp_port = 8080\n\n",
+      "[limits]\nmax_body_bytes = 4096\n",
+    ),
+  )
+  .expect("write gateway template");
+  fs::write(config_dir.join("profile/dev.toml"), "").expect("write dev profile");
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  let base = engine
+    .render_base_rust_types("base_server")
+    .expect("render base rust types");
+  assert!(base.contains("pub struct ServerConfig {"));
+  assert!(base.contains("pub http_port: u16,"));
+
+  let options = RustCodegenOptions {
+    base_types: Some(String::from("crate::base_server")),
+    ..RustCodegenOptions::default()
+  };
+  let code = engine
+    .render_rust_types_with("dev", "gateway", &options)
+    .expect("render gateway with base types");
+  assert!(code.contains("pub server: crate::base_server::ServerConfig,"));
+  assert!(!code.contains("pub struct ServerConfig"));
+  assert!(code.contains("pub limits: LimitsConfig,"));
+  assert!(code.contains("pub max_body_bytes: u64,"));
+
+  let code = engine
+    .render_rust_types("dev", "gateway")
+    .expect("render gateway without base types");
+  assert!(code.contains("pub server: ServerConfig,"));
+
+  let options = RustCodegenOptions {
+    with_values: true,
+    ..options
+  };
+  let err = engine
+    .render_rust_types_with("dev", "gateway", &options)
+    .expect_err("base types cannot render value literals");
+  assert_eq!(err.code(), CONFIGERR_UNSUPPORTEDOPERATION);
+}
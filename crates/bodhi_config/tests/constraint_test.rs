@@ -0,0 +1,220 @@
+use std::fs;
+use std::path::Path;
+
+use bodhi_config::constraint::{Constraint, ConstraintSet, ValueFormat};
+use bodhi_config::prelude::*;
+use tempfile::tempdir;
+use toml::Value;
+
+fn write_constrained_config(config_dir: &Path, profile: &str) {
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/infra/log.schema.toml"),
+    "[log.level]\none_of = [\"DEBUG\", \"INFO\", \"WARN\"]\n",
+  )
+  .expect("write infra log constraints");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    concat!(
+      "[server]\nhttp_port = 8080\nbind = \"0.0.0.0:8080\"\n\n",
+      "[routes.\"*\"]\nprefix = \"/\"\n",
+    ),
+  )
+  .expect("write gateway template");
+  fs::write(
+    config_dir.join("template/service/gateway.schema.toml"),
+    concat!(
+      "[server.http_port]\nmin = 1\nmax = 65535\n\n",
+      "[server.bind]\nformat = \"socket_addr\"\n\n",
+      "[routes]\nmax_length = 2\n\n",
+      "[routes.\"*\".prefix]\nnon_empty = true\npattern = \"^/\"\n",
+    ),
+  )
+  .expect("write gateway constraints");
+  fs::write(config_dir.join("profile/dev.toml"), profile).expect("write dev profile");
+}
+
+#[test]
+fn resolve_should_report_all_constraint_violations() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  write_constrained_config(
+    &config_dir,
+    "[services.gateway.routes.api]\nprefix = \"/api\"\n",
+  );
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  assert_eq!(
+    engine.services().expect("list services"),
+    vec![String::from("gateway")]
+  );
+  engine
+    .resolve("dev", "gateway")
+    .expect("valid profile should resolve");
+
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    concat!(
+      "[infra.log]\nlevel = \"TRACE\"\n\n",
+      "[services.gateway.server]\nhttp_port = 0\nbind = \"localhost\"\n\n",
+      "[services.gateway.routes.api]\nprefix = \"api\"\n",
+    ),
+  )
+  .expect("write invalid profile");
+  let err = engine
+    .resolve("dev", "gateway")
+    .expect_err("invalid values should fail");
+  assert_eq!(err.code(), CONFIGERR_VALIDATIONFAILED);
+  let message = format!("{err:?}");
+  assert!(message.contains("violations=4"));
+  assert!(message.contains("infra.log.level: must be one of"));
+  assert!(message.contains("services.gateway.server.http_port: must be >= 1"));
+  assert!(message.contains("services.gateway.server.bind: must be a socket address"));
+  assert!(message.contains("services.gateway.routes.api.prefix: must match pattern `^/`"));
+
+  fs::write(
+    config_dir.join("template/service/gateway.schema.toml"),
+    "[server.bind]\nmin = 1\n",
+  )
+  .expect("write mismatched constraint");
+  let err = engine
+    .resolve("dev", "gateway")
+    .expect_err("constraint on wrong field type should fail");
+  assert_eq!(err.code(), CONFIGERR_INVALIDSTRUCTURE);
+  assert!(format!("{err}").contains("path=services.gateway.server.bind rule=min"));
+
+  fs::write(
+    config_dir.join("template/service/gateway.schema.toml"),
+    "[server.missing]\nnon_empty = true\n",
+  )
+  .expect("write unknown constraint path");
+  let err = engine
+    .resolve("dev", "gateway")
+    .expect_err("constraint on unknown field should fail");
+  assert_eq!(err.code(), CONFIGERR_UNKNOWNFIELD);
+}
+
+#[test]
+fn constraint_keys_with_table_values_should_be_field_paths() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  write_constrained_config(&config_dir, "");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    "[server]\nhttp_port = 8080\nbind = \"0.0.0.0:8080\"\n\n[pool]\nmax = 16\n",
+  )
+  .expect("write gateway template");
+  fs::write(
+    config_dir.join("template/service/gateway.schema.toml"),
+    "[pool.max]\nmin = 1\nmax = 64\n",
+  )
+  .expect("write constraints on field named max");
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  engine
+    .resolve("dev", "gateway")
+    .expect("valid pool size should resolve");
+
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    "[services.gateway.pool]\nmax = 0\n",
+  )
+  .expect("write invalid profile");
+  let err = engine
+    .resolve("dev", "gateway")
+    .expect_err("pool size below min should fail");
+  assert_eq!(err.code(), CONFIGERR_VALIDATIONFAILED);
+  assert!(format!("{err:?}").contains("services.gateway.pool.max: must be >= 1"));
+}
+
+#[test]
+fn codegen_should_emit_validate_methods_from_constraints() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  write_constrained_config(
+    &config_dir,
+    "[services.gateway.routes.api]\nprefix = \"/api\"\n",
+  );
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  let code = engine
+    .render_rust_types("dev", "gateway")
+    .expect("render rust types");
+  assert!(!code.contains("pub fn validate"));
+
+  let options = RustCodegenOptions {
+    with_validate: true,
+    ..RustCodegenOptions::default()
+  };
+  let code = engine
+    .render_rust_types_with("dev", "gateway", &options)
+    .expect("render rust types with validate");
+  assert!(code.contains("impl ServerConfig {"));
+  assert!(code.contains("pub fn validate(&self) -> Vec<String>"));
+  assert!(!code.contains("pub use merged::CONFIG;"));
+  assert!(code.contains("(*value as f64) < 1.0"));
+  assert!(code.contains("\"must be >= 1\""));
+  assert!(code.contains("bodhi_config::constraint::ValueFormat::SocketAddr.matches(value)"));
+  assert!(code.contains("bodhi_config::regex::Regex::new(\"^/\")"));
+  assert!(code.contains("static PATTERN: std::sync::LazyLock<bodhi_config::regex::Regex>"));
+  assert!(code.contains("if !PATTERN.is_match(value) {"));
+  assert!(code.contains("value.validate_at(&format!(\"{prefix}routes.{key}\"), violations);"));
+  assert!(code.contains("![\"DEBUG\", \"INFO\", \"WARN\"].contains(&value.as_str())"));
+
+  // 类型覆盖把受约束的字段改为约束无法作用的类型时报错，而不是漏掉检查
+  let type_rules_path = tempdir.path().join("type_overrides.toml");
+  fs::write(
+    &type_rules_path,
+    "[path_types]\n\"server.bind\" = \"std::net::SocketAddr\"\n",
+  )
+  .expect("write type override rules");
+  let options = RustCodegenOptions {
+    with_validate: true,
+    type_overrides: TypeOverrideRules::from_file(&type_rules_path)
+      .expect("load type override rules"),
+    ..RustCodegenOptions::default()
+  };
+  let err = engine
+    .render_rust_types_with("dev", "gateway", &options)
+    .expect_err("inapplicable constraint should fail");
+  assert_eq!(err.code(), CONFIGERR_CODEGENFAILED);
+  assert!(format!("{err:?}").contains("path=server.bind type=std::net::SocketAddr rule=format"));
+}
+
+#[test]
+fn constraint_set_should_check_wildcard_paths() {
+  let mut constraints = ConstraintSet::new();
+  let table: toml::Table = toml::from_str("min = 1\nmax = 10").expect("parse constraint");
+  constraints.insert(
+    "limits.*",
+    Constraint::from_table(&table, "limits.*").expect("build constraint"),
+  );
+
+  let value: Value = toml::from_str("[limits]\na = 5\nb = 0\nc = 11\n").expect("parse value");
+  let violations: Vec<_> = constraints
+    .check(&value, "services.gateway")
+    .iter()
+    .map(ToString::to_string)
+    .collect();
+  assert_eq!(
+    violations,
+    vec![
+      String::from("services.gateway.limits.b: must be >= 1"),
+      String::from("services.gateway.limits.c: must be <= 10"),
+    ]
+  );
+
+  assert!(ValueFormat::SocketAddr.matches("127.0.0.1:80"));
+  assert!(ValueFormat::SocketAddr.matches("localhost:8080"));
+  assert!(!ValueFormat::SocketAddr.matches("localhost"));
+  assert!(ValueFormat::Url.matches("https://example.com/api"));
+  assert!(!ValueFormat::Url.matches("example.com"));
+}
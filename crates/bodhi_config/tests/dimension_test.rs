@@ -0,0 +1,361 @@
+use std::fs;
+use std::path::Path;
+
+use bodhi_config::prelude::*;
+use serde::Deserialize;
+use tempfile::tempdir;
+
+#[derive(Debug, Deserialize)]
+struct InfraConfig {
+  net: NetConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct NetConfig {
+  listen_host: String,
+  timeout_ms: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct GatewayServiceConfig {
+  server: ServerConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServerConfig {
+  http_port: u16,
+}
+
+#[test]
+fn engine_should_apply_dimension_overlays_in_declared_order() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  write_dimension_test_config(&config_dir);
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  let names: Vec<_> = engine
+    .dimensions()
+    .expect("list dimensions")
+    .into_iter()
+    .map(|spec| spec.name)
+    .collect();
+  assert_eq!(names, ["region", "datacenter"]);
+  assert_eq!(
+    engine.dimension_values("region").expect("list regions"),
+    ["eu", "us"]
+  );
+
+  let dimensions: Dimensions = "profile=prod,datacenter=fra1,region=eu"
+    .parse()
+    .expect("parse dimension selector");
+  assert_eq!(dimensions.label(), "prod,datacenter=fra1,region=eu");
+
+  let layers = engine
+    .resolve_layers_with(&dimensions, "gateway")
+    .expect("resolve gateway with dimensions");
+  let infra: InfraConfig = layers.extract_infra(".").expect("extract infra");
+  let service: GatewayServiceConfig = layers.extract_service(".").expect("extract service");
+  // datacenter 声明在 region 之后，优先级更高
+  assert_eq!(infra.net.listen_host, "10.1.0.1");
+  assert_eq!(infra.net.timeout_ms, 500);
+  assert_eq!(service.server.http_port, 8443);
+
+  let profile_only = engine
+    .resolve_layers("prod", "gateway")
+    .expect("resolve gateway without dimensions");
+  let service: GatewayServiceConfig = profile_only.extract_service(".").expect("extract service");
+  assert_eq!(service.server.http_port, 80);
+
+  let provenance = engine
+    .provenance(&dimensions, "gateway")
+    .expect("resolve provenance");
+  assert_eq!(
+    provenance.layer_of("net.listen_host"),
+    Some("datacenter.fra1.infra")
+  );
+  assert_eq!(
+    provenance.layer_of("net.timeout_ms"),
+    Some("region.eu.infra")
+  );
+  assert_eq!(
+    provenance.layer_of("server.http_port"),
+    Some("region.eu.services.gateway")
+  );
+  assert_eq!(provenance.layer_of("log.level"), Some("profile.prod.infra"));
+  assert_eq!(provenance.layer_of("log.output"), Some("template.infra"));
+
+  engine
+    .generate_with(&dimensions, &[OutputFormat::Toml])
+    .expect("generate dimension products");
+  engine
+    .generate("prod", &[OutputFormat::Toml])
+    .expect("generate profile products");
+  assert!(
+    config_dir
+      .join("product/prod/dimensions/datacenter/fra1/region/eu/toml/gateway.toml")
+      .is_file()
+  );
+
+  let products = ConfigEngine::new(&config_dir)
+    .expect("create config engine")
+    .with_products(OutputFormat::Toml);
+  assert_eq!(
+    products.profiles().expect("list product profiles"),
+    ["prod"]
+  );
+  assert_eq!(
+    products
+      .resolve_layers_with(&dimensions, "gateway")
+      .expect("resolve dimension product")
+      .content_hash(),
+    layers.content_hash()
+  );
+  let err = products
+    .provenance(&dimensions, "gateway")
+    .expect_err("product mode has no provenance");
+  assert_eq!(err.code(), CONFIGERR_UNSUPPORTEDOPERATION);
+
+  let store = ConfigStore::<InfraConfig, GatewayServiceConfig>::builder("prod", "gateway")
+    .config_dir(&config_dir)
+    .dimension("region", "us")
+    .build()
+    .expect("load config store with dimension");
+  assert_eq!(store.dimensions().label(), "prod,region=us");
+  assert_eq!(store.snapshot().infra().net.timeout_ms, 2000);
+  assert_eq!(store.snapshot().service().server.http_port, 80);
+}
+
+#[test]
+fn engine_should_reject_invalid_dimension_selection() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  write_dimension_test_config(&config_dir);
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+
+  let err = engine
+    .resolve_with(&Dimensions::new("prod").with("tenant", "acme"), "gateway")
+    .expect_err("undeclared dimension should fail");
+  assert_eq!(err.code(), CONFIGERR_DIMENSIONNOTFOUND);
+
+  let err = engine
+    .resolve_with(&Dimensions::new("prod").with("region", "apac"), "gateway")
+    .expect_err("missing dimension value should fail");
+  assert_eq!(err.code(), CONFIGERR_DIMENSIONNOTFOUND);
+
+  fs::write(
+    config_dir.join("region/bad.toml"),
+    "[services.gateway.server]\nhttps_port = 1\n",
+  )
+  .expect("write bad region overlay");
+  let err = engine
+    .resolve_with(&Dimensions::new("prod").with("region", "bad"), "gateway")
+    .expect_err("unknown overlay field should fail");
+  assert_eq!(err.code(), CONFIGERR_UNKNOWNFIELD);
+  assert!(format!("{err}").contains("region.bad.services.gateway.server.https_port"));
+
+  fs::write(
+    config_dir.join("region/bad.toml"),
+    "[services.gateway.instances.gw-2.server]\nhttp_port = 1\n",
+  )
+  .expect("write instance region overlay");
+  let err = engine
+    .resolve_with(&Dimensions::new("prod").with("region", "bad"), "gateway")
+    .expect_err("instances in dimension overlay should fail");
+  assert_eq!(err.code(), CONFIGERR_UNKNOWNFIELD);
+
+  let err = "region=eu"
+    .parse::<Dimensions>()
+    .expect_err("selector without profile should fail");
+  assert_eq!(err.code(), CONFIGERR_INVALIDSTRUCTURE);
+
+  fs::write(
+    config_dir.join("dimensions.toml"),
+    "[[dimension]]\nname = \"profile\"\n",
+  )
+  .expect("write reserved dimension");
+  let err = engine
+    .resolve("prod", "gateway")
+    .expect_err("reserved dimension name should fail");
+  assert_eq!(err.code(), CONFIGERR_INVALIDSTRUCTURE);
+}
+
+#[test]
+fn engine_should_merge_stacked_profiles_left_to_right() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  write_dimension_test_config(&config_dir);
+  fs::write(
+    config_dir.join("profile/stanley.toml"),
+    concat!(
+      "[infra.net]\ntimeout_ms = 3000\n",
+      "[services.gateway.server]\nhttp_port = 9000\n",
+      "[services.gateway.instances.gw-2.server]\nhttp_port = 9002\n",
+    ),
+  )
+  .expect("write stanley profile");
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  let layers = engine
+    .resolve_layers("prod,stanley", "gateway")
+    .expect("resolve stacked profiles");
+  let infra: InfraConfig = layers.extract_infra(".").expect("extract infra");
+  let service: GatewayServiceConfig = layers.extract_service(".").expect("extract service");
+  assert_eq!(infra.net.timeout_ms, 3000);
+  assert_eq!(service.server.http_port, 9000);
+
+  let dimensions: Dimensions = "prod, stanley, region=eu"
+    .parse()
+    .expect("parse stacked selector");
+  assert_eq!(dimensions.profiles(), ["prod", "stanley"]);
+  assert_eq!(dimensions.label(), "prod+stanley,region=eu");
+
+  let provenance = engine
+    .provenance(&dimensions, "gateway")
+    .expect("resolve stacked provenance");
+  assert_eq!(provenance.layer_of("log.level"), Some("profile.prod.infra"));
+  assert_eq!(
+    provenance.layer_of("net.timeout_ms"),
+    Some("region.eu.infra")
+  );
+  assert_eq!(
+    provenance.layer_of("server.http_port"),
+    Some("region.eu.services.gateway")
+  );
+
+  let provenance = engine
+    .provenance(&Dimensions::new("prod,stanley"), "gateway@gw-2")
+    .expect("resolve stacked instance provenance");
+  assert_eq!(
+    provenance.layer_of("server.http_port"),
+    Some("profile.stanley.services.gateway.instances.gw-2")
+  );
+  assert_eq!(
+    engine
+      .instances("prod,stanley", "gateway")
+      .expect("list stacked instances"),
+    ["gw-2"]
+  );
+
+  engine
+    .generate("prod,stanley", &[OutputFormat::Toml])
+    .expect("generate stacked products");
+  assert!(
+    config_dir
+      .join("product/prod+stanley/toml/gateway@gw-2.toml")
+      .is_file()
+  );
+
+  let store = ConfigStore::<InfraConfig, GatewayServiceConfig>::load_from(
+    &config_dir,
+    "prod,stanley",
+    "gateway",
+  )
+  .expect("load stacked config store");
+  assert_eq!(store.profile(), "prod+stanley");
+  assert_eq!(store.snapshot().service().server.http_port, 9000);
+
+  let err = engine
+    .resolve("prod,missing", "gateway")
+    .expect_err("unknown profile in stack should fail");
+  assert_eq!(err.code(), CONFIGERR_PROFILENOTFOUND);
+}
+
+#[test]
+fn dimensions_should_stack_profiles_without_string_joining() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  write_dimension_test_config(&config_dir);
+  fs::write(
+    config_dir.join("profile/stanley.toml"),
+    "[services.gateway.server]\nhttp_port = 9000\n",
+  )
+  .expect("write stanley profile");
+
+  let dimensions = Dimensions::with_profiles(&["prod", "stanley"])
+    .expect("stack profiles")
+    .with("region", "eu");
+  assert_eq!(dimensions.profiles(), ["prod", "stanley"]);
+  assert_eq!(
+    dimensions,
+    "prod,stanley,region=eu"
+      .parse::<Dimensions>()
+      .expect("parse stacked selector")
+  );
+
+  let store = ConfigStore::<InfraConfig, GatewayServiceConfig>::builder("dev", "gateway")
+    .config_dir(&config_dir)
+    .dimensions(Dimensions::with_profiles(&["prod", "stanley"]).expect("stack profiles"))
+    .build()
+    .expect("load stacked config store");
+  assert_eq!(store.profile(), "prod+stanley");
+  assert_eq!(store.snapshot().service().server.http_port, 9000);
+
+  for profiles in [&["prod+stanley"][..], &["prod", "a,b"], &[" "], &[]] {
+    let err = Dimensions::with_profiles(profiles).expect_err("invalid profile stack should fail");
+    assert_eq!(
+      err.code(),
+      CONFIGERR_INVALIDSTRUCTURE,
+      "profiles={profiles:?}"
+    );
+  }
+
+  fs::write(config_dir.join("profile/prod+eu.toml"), "").expect("write ambiguous profile");
+  let err = ConfigEngine::new(&config_dir)
+    .expect("create config engine")
+    .profiles()
+    .expect_err("profile file with `+` should fail");
+  assert_eq!(err.code(), CONFIGERR_INVALIDSTRUCTURE);
+}
+
+fn write_dimension_test_config(config_dir: &Path) {
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+  fs::create_dir_all(config_dir.join("region")).expect("create region dir");
+  fs::create_dir_all(config_dir.join("dc")).expect("create datacenter dir");
+
+  fs::write(
+    config_dir.join("dimensions.toml"),
+    "[[dimension]]\nname = \"region\"\n\n[[dimension]]\nname = \"datacenter\"\ndir = \"dc\"\n",
+  )
+  .expect("write dimensions");
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\noutput = \"stdout\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/infra/net.toml"),
+    "[net]\nlisten_host = \"0.0.0.0\"\ntimeout_ms = 1000\n",
+  )
+  .expect("write infra net");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    "[server]\nhttp_port = 80\n",
+  )
+  .expect("write gateway template");
+  fs::write(
+    config_dir.join("profile/prod.toml"),
+    "[infra.log]\nlevel = \"WARN\"\n",
+  )
+  .expect("write prod profile");
+  fs::write(
+    config_dir.join("region/eu.toml"),
+    concat!(
+      "[infra.net]\nlisten_host = \"10.0.0.1\"\ntimeout_ms = 500\n",
+      "[services.gateway.server]\nhttp_port = 8443\n",
+    ),
+  )
+  .expect("write eu region");
+  fs::write(
+    config_dir.join("region/us.toml"),
+    "[infra.net]\ntimeout_ms = 2000\n",
+  )
+  .expect("write us region");
+  fs::write(
+    config_dir.join("dc/fra1.toml"),
+    "[infra.net]\nlisten_host = \"10.1.0.1\"\n",
+  )
+  .expect("write fra1 datacenter");
+}
@@ -0,0 +1,102 @@
+use std::fs;
+
+use bodhi_config::prelude::*;
+use bodhi_config::{EmbeddedSource, LayeredSource, embed_config};
+use serde::Deserialize;
+use tempfile::tempdir;
+
+const EMBEDDED_TREE: EmbeddedSource = embed_config!(dir = "tests/fixtures/embed/config");
+const EMBEDDED_PRODUCT: EmbeddedSource =
+  embed_config!(dir = "tests/fixtures/embed/config", product = "dev");
+
+#[derive(Debug, Deserialize)]
+struct LogConfig {
+  level: String,
+  output: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServerConfig {
+  http_port: u16,
+}
+
+#[test]
+fn embedded_tree_should_resolve_without_config_dir() {
+  let paths: Vec<_> = EMBEDDED_TREE.paths().collect();
+  assert!(paths.contains(&"template/infra/log.toml"));
+  assert!(paths.contains(&"profile/dev.toml"));
+  assert!(!paths.iter().any(|path| path.starts_with("product/")));
+
+  let engine = ConfigEngine::embedded(EMBEDDED_TREE, "bodhi-embed-test-missing-overlay");
+  assert_eq!(engine.config_dir(), None);
+
+  let resolved = engine
+    .resolve("dev", "gateway")
+    .expect("resolve embedded gateway config");
+  let log: LogConfig = resolved.extract("log").expect("extract log config");
+  let server: ServerConfig = resolved.extract("server").expect("extract server config");
+  assert_eq!(log.level, "INFO");
+  assert_eq!(log.output, "stderr");
+  assert_eq!(server.http_port, 8080);
+}
+
+#[test]
+fn embedded_product_should_resolve_layers() {
+  let paths: Vec<_> = EMBEDDED_PRODUCT.paths().collect();
+  assert_eq!(
+    paths,
+    [
+      "product/dev/toml/gateway.toml",
+      "product/dev/toml/gateway.toml.meta"
+    ]
+  );
+
+  let layers = ConfigEngine::from_source(EMBEDDED_PRODUCT)
+    .with_products(OutputFormat::Toml)
+    .resolve_layers("dev", "gateway")
+    .expect("resolve embedded product");
+  let expected = ConfigEngine::from_source(EMBEDDED_TREE)
+    .resolve_layers("dev", "gateway")
+    .expect("resolve embedded tree");
+
+  assert_eq!(layers.infra(), expected.infra());
+  assert_eq!(layers.service(), expected.service());
+  assert_eq!(layers.content_hash(), expected.content_hash());
+}
+
+#[test]
+fn filesystem_overlay_should_take_precedence_over_embedded_tree() {
+  let tempdir = tempdir().expect("create tempdir");
+  let overlay_dir = tempdir.path().join("config");
+
+  fs::create_dir_all(overlay_dir.join("profile")).expect("create overlay profile dir");
+  fs::write(
+    overlay_dir.join("profile/dev.toml"),
+    "[services.gateway.server]\nhttp_port = 9090\n",
+  )
+  .expect("write overlay dev profile");
+  fs::write(overlay_dir.join("profile/prod.toml"), "").expect("write overlay prod profile");
+
+  let engine = ConfigEngine::from_source(
+    LayeredSource::new()
+      .with_layer(FsSource::new(&overlay_dir))
+      .with_layer(EMBEDDED_TREE),
+  );
+
+  assert_eq!(engine.config_dir(), Some(overlay_dir.as_path()));
+  assert_eq!(engine.profiles().expect("list profiles"), ["dev", "prod"]);
+
+  let server: ServerConfig = engine
+    .resolve("dev", "gateway")
+    .expect("resolve overlaid gateway config")
+    .extract("server")
+    .expect("extract server config");
+  assert_eq!(server.http_port, 9090);
+
+  let server: ServerConfig = engine
+    .resolve("prod", "gateway")
+    .expect("resolve prod gateway config from embedded templates")
+    .extract("server")
+    .expect("extract server config");
+  assert_eq!(server.http_port, 80);
+}
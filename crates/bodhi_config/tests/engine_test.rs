@@ -93,7 +93,7 @@ fn engine_should_find_config_dir_from_ancestor_directory() {
     .extract("service.name")
     .expect("extract service name");
 
-  assert_eq!(engine.config_dir(), config_dir.as_path());
+  assert_eq!(engine.config_dir(), Some(config_dir.as_path()));
   assert_eq!(service_name, "gateway");
 }
 
@@ -134,6 +134,27 @@ fn engine_should_generate_multiple_formats() {
   assert!(config_dir.join("product/dev/toml/gateway.toml").is_file());
   assert!(config_dir.join("product/dev/json/gateway.json").is_file());
   assert!(config_dir.join("product/dev/yaml/gateway.yaml").is_file());
+
+  let content_hash = engine
+    .resolve("dev", "gateway")
+    .expect("resolve gateway config")
+    .content_hash();
+  let product: serde_json::Value = serde_json::from_str(
+    &fs::read_to_string(config_dir.join("product/dev/json/gateway.json"))
+      .expect("read json product"),
+  )
+  .expect("parse json product");
+  assert!(product.get("__bodhi").is_none());
+  let meta: toml::Value = toml::from_str(
+    &fs::read_to_string(config_dir.join("product/dev/json/gateway.json.meta"))
+      .expect("read json product meta"),
+  )
+  .expect("parse json product meta");
+  assert_eq!(
+    meta["content_hash"].as_str(),
+    Some(content_hash.to_hex().as_str())
+  );
+  assert_eq!(meta["profile"].as_str(), Some("dev"));
 }
 
 #[test]
@@ -199,3 +220,1193 @@ fn engine_should_reject_unknown_service_template_infra_field() {
   assert_eq!(err.code(), CONFIGERR_UNKNOWNFIELD);
   assert!(format!("{err}").contains("template.service.gateway.infra.loag"));
 }
+
+#[test]
+fn engine_should_resolve_layers_from_products_of_every_format() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\noutput = \"stdout\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    "[server]\nhttp_port = 80\n",
+  )
+  .expect("write gateway template");
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    "[infra.log]\nlevel = \"DEBUG\"\n[services.gateway.server]\nhttp_port = 8080\n",
+  )
+  .expect("write dev profile");
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  let expected = engine
+    .resolve_layers("dev", "gateway")
+    .expect("resolve gateway layers");
+  engine
+    .generate("dev", OutputFormat::all())
+    .expect("generate products");
+
+  // 产物模式不依赖模板和 profile
+  fs::remove_dir_all(config_dir.join("template")).expect("remove templates");
+  fs::remove_dir_all(config_dir.join("profile")).expect("remove profiles");
+
+  for format in OutputFormat::all() {
+    let engine = ConfigEngine::new(&config_dir)
+      .expect("create config engine")
+      .with_products(*format);
+    assert_eq!(engine.profiles().expect("list product profiles"), ["dev"]);
+    assert_eq!(
+      engine.services().expect("list product services"),
+      ["gateway"]
+    );
+
+    let layers = engine
+      .resolve_layers("dev", "gateway")
+      .expect("resolve gateway layers from product");
+    assert_eq!(layers.infra(), expected.infra(), "format={format:?}");
+    assert_eq!(layers.service(), expected.service(), "format={format:?}");
+    assert_eq!(layers.content_hash(), expected.content_hash());
+
+    let log: LogConfig = layers.extract_infra("log").expect("extract log config");
+    let server: ServerConfig = layers
+      .extract_service("server")
+      .expect("extract server config");
+    assert_eq!(log.level, "DEBUG");
+    assert_eq!(server.http_port, 8080);
+
+    let err = engine
+      .resolve_service_schema("gateway")
+      .expect_err("product mode should not resolve service schema");
+    assert_eq!(err.code(), CONFIGERR_UNSUPPORTEDOPERATION);
+  }
+}
+
+#[test]
+fn engine_should_reject_tampered_product() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    "[server]\nhttp_port = 80\n",
+  )
+  .expect("write gateway template");
+  fs::write(config_dir.join("profile/dev.toml"), "").expect("write dev profile");
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  engine
+    .generate_service("dev", "gateway", &[OutputFormat::Toml])
+    .expect("generate toml product");
+
+  let product_path = config_dir.join("product/dev/toml/gateway.toml");
+  let content = fs::read_to_string(&product_path).expect("read toml product");
+  fs::write(
+    &product_path,
+    content.replace("http_port = 80", "http_port = 81"),
+  )
+  .expect("tamper toml product");
+
+  let err = engine
+    .with_products(OutputFormat::Toml)
+    .resolve("dev", "gateway")
+    .expect_err("tampered product should be rejected");
+  assert_eq!(err.code(), CONFIGERR_PRODUCTINVALID);
+  assert!(format!("{err}").contains("product content hash mismatched"));
+}
+
+#[test]
+fn engine_should_rebuild_layers_sharing_top_level_keys_from_products() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\noutput = \"stdout\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    "[log]\naccess = true\n\n[server]\nhttp_port = 80\n",
+  )
+  .expect("write gateway template");
+  fs::write(config_dir.join("profile/dev.toml"), "").expect("write dev profile");
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  let expected = engine
+    .resolve_layers("dev", "gateway")
+    .expect("resolve gateway layers");
+  engine
+    .generate("dev", OutputFormat::all())
+    .expect("generate products");
+
+  for format in OutputFormat::all() {
+    let layers = ConfigEngine::new(&config_dir)
+      .expect("create config engine")
+      .with_products(*format)
+      .resolve_layers("dev", "gateway")
+      .expect("resolve layers sharing log from product");
+    assert_eq!(layers.infra(), expected.infra(), "format={format:?}");
+    assert_eq!(layers.service(), expected.service(), "format={format:?}");
+    assert_eq!(layers.merged()["log"]["access"].as_bool(), Some(true));
+  }
+
+  let product_path = config_dir.join("product/dev/toml/gateway.toml");
+  let content = fs::read_to_string(&product_path).expect("read toml product");
+  fs::write(
+    &product_path,
+    content.replace("access = true", "access = false"),
+  )
+  .expect("tamper shared key");
+  let err = engine
+    .with_products(OutputFormat::Toml)
+    .resolve("dev", "gateway")
+    .expect_err("tampered shared key should be rejected");
+  assert_eq!(err.code(), CONFIGERR_PRODUCTINVALID);
+  assert!(format!("{err}").contains("product content mismatched its recorded layers"));
+}
+
+#[test]
+fn engine_should_keep_product_meta_out_of_product_files() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    "[server]\nhttp_port = 80\n",
+  )
+  .expect("write gateway template");
+  fs::write(config_dir.join("profile/dev.toml"), "").expect("write dev profile");
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  engine
+    .generate_service("dev", "gateway", &[OutputFormat::Toml])
+    .expect("generate toml product");
+
+  #[derive(Debug, Deserialize)]
+  #[serde(deny_unknown_fields)]
+  struct StrictProduct {
+    log: toml::Value,
+    server: toml::Value,
+  }
+  let product_path = config_dir.join("product/dev/toml/gateway.toml");
+  let content = fs::read_to_string(&product_path).expect("read toml product");
+  let product: StrictProduct = toml::from_str(&content).expect("product should have no meta key");
+  assert!(!content.starts_with('#'));
+  assert_eq!(product.server["http_port"].as_integer(), Some(80));
+  assert!(product.log.is_table());
+
+  // 产物必须带有元数据旁路文件
+  fs::remove_file(config_dir.join("product/dev/toml/gateway.toml.meta"))
+    .expect("remove product meta");
+  let err = ConfigEngine::new(&config_dir)
+    .expect("create config engine")
+    .with_products(OutputFormat::Toml)
+    .resolve("dev", "gateway")
+    .expect_err("product without meta should fail");
+  assert_eq!(err.code(), CONFIGERR_PRODUCTINVALID);
+  assert!(format!("{err:?}").contains("product meta file not found"));
+}
+
+#[test]
+fn engine_should_resolve_service_instances_after_profile_service_overlay() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\noutput = \"stdout\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    "[server]\nhttp_port = 80\nshard = 0\n",
+  )
+  .expect("write gateway template");
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    concat!(
+      "[services.gateway.server]\nhttp_port = 8080\n",
+      "[services.gateway.instances.gw-1]\n",
+      "[services.gateway.instances.gw-2.server]\nhttp_port = 8081\nshard = 1\n",
+      "[services.gateway.instances.gw-2.infra.log]\nlevel = \"DEBUG\"\n",
+    ),
+  )
+  .expect("write dev profile");
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  assert_eq!(
+    engine.instances("dev", "gateway").expect("list instances"),
+    ["gw-1", "gw-2"]
+  );
+
+  let shared = engine
+    .resolve_layers("dev", "gateway")
+    .expect("resolve gateway layers");
+  assert!(shared.service().get("instances").is_none());
+  assert_eq!(
+    engine
+      .resolve_layers("dev", "gateway@gw-1")
+      .expect("resolve gw-1 layers")
+      .content_hash(),
+    shared.content_hash()
+  );
+
+  let layers = engine
+    .resolve_layers("dev", "gateway@gw-2")
+    .expect("resolve gw-2 layers");
+  let log: LogConfig = layers.extract_infra("log").expect("extract log config");
+  let server: ServerConfig = layers
+    .extract_service("server")
+    .expect("extract server config");
+  let shard: u32 = layers
+    .extract_service("server.shard")
+    .expect("extract shard");
+  assert_eq!(log.level, "DEBUG");
+  assert_eq!(server.http_port, 8081);
+  assert_eq!(shard, 1);
+
+  let err = engine
+    .resolve("dev", "gateway@gw-3")
+    .expect_err("unknown instance should fail");
+  assert_eq!(err.code(), CONFIGERR_INSTANCENOTFOUND);
+
+  engine
+    .generate("dev", &[OutputFormat::Toml])
+    .expect("generate products");
+  assert!(config_dir.join("product/dev/toml/gateway.toml").is_file());
+  assert!(
+    config_dir
+      .join("product/dev/toml/gateway@gw-2.toml")
+      .is_file()
+  );
+  assert_eq!(
+    engine
+      .default_rust_output_path("dev", "gateway@gw-2")
+      .expect("default rust output path"),
+    config_dir.join("product/dev/rust/gateway_gw_2_config.rs")
+  );
+
+  let products = ConfigEngine::new(&config_dir)
+    .expect("create config engine")
+    .with_products(OutputFormat::Toml);
+  assert_eq!(
+    products.services().expect("list product services"),
+    ["gateway"]
+  );
+  assert_eq!(
+    products
+      .instances("dev", "gateway")
+      .expect("list product instances"),
+    ["gw-1", "gw-2"]
+  );
+  assert_eq!(
+    products
+      .resolve_layers("dev", "gateway@gw-2")
+      .expect("resolve gw-2 product")
+      .content_hash(),
+    layers.content_hash()
+  );
+}
+
+#[test]
+fn engine_should_validate_service_instances_against_schema() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    "[server]\nhttp_port = 80\n",
+  )
+  .expect("write gateway template");
+  fs::write(
+    config_dir.join("profile/unknown.toml"),
+    "[services.gateway.instances.gw-2.server]\nhttps_port = 8443\n",
+  )
+  .expect("write unknown field profile");
+  fs::write(
+    config_dir.join("profile/mismatch.toml"),
+    "[services.gateway.instances.gw-2.server]\nhttp_port = \"8081\"\n",
+  )
+  .expect("write type mismatch profile");
+  fs::write(
+    config_dir.join("profile/badname.toml"),
+    "[services.gateway.instances.\"gw@2\"]\n",
+  )
+  .expect("write bad instance name profile");
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+
+  let err = engine
+    .resolve("unknown", "gateway")
+    .expect_err("unknown instance field should fail");
+  assert_eq!(err.code(), CONFIGERR_UNKNOWNFIELD);
+  assert!(format!("{err}").contains("profile.unknown.services.gateway.instances.gw-2.server"));
+
+  let err = engine
+    .resolve("mismatch", "gateway@gw-2")
+    .expect_err("instance type mismatch should fail");
+  assert_eq!(err.code(), CONFIGERR_TYPEMISMATCH);
+
+  let err = engine
+    .resolve("badname", "gateway")
+    .expect_err("invalid instance name should fail");
+  assert_eq!(err.code(), CONFIGERR_INVALIDSTRUCTURE);
+}
+
+#[test]
+fn engine_should_merge_local_profile_overrides() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\noutput = \"stdout\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    "[server]\nhttp_port = 80\n",
+  )
+  .expect("write gateway template");
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    "[services.gateway.server]\nhttp_port = 8080\n",
+  )
+  .expect("write dev profile");
+  fs::write(
+    config_dir.join("profile/dev.local.toml"),
+    "[infra.log]\nlevel = \"DEBUG\"\n[services.gateway.server]\nhttp_port = 9090\n",
+  )
+  .expect("write dev local profile");
+
+  let local_names = bodhi_config::loader::local_profile_names("dev");
+  if let Some(user_local) = local_names.get(1) {
+    fs::write(
+      config_dir.join(format!("profile/{user_local}.toml")),
+      "[infra.log]\noutput = \"file\"\n",
+    )
+    .expect("write dev user local profile");
+  }
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  assert_eq!(engine.profiles().expect("list profiles"), ["dev"]);
+
+  let resolved = engine
+    .resolve("dev", "gateway")
+    .expect("resolve gateway config");
+  let log: LogConfig = resolved.extract("log").expect("extract log config");
+  let server: ServerConfig = resolved.extract("server").expect("extract server config");
+  assert_eq!(log.level, "DEBUG");
+  assert_eq!(server.http_port, 9090);
+
+  let provenance = engine
+    .provenance(&Dimensions::new("dev"), "gateway")
+    .expect("resolve provenance");
+  assert_eq!(
+    provenance.layer_of("server.http_port"),
+    Some("profile.dev.local.services.gateway")
+  );
+  if let Some(user_local) = local_names.get(1) {
+    assert_eq!(log.output, "file");
+    assert_eq!(
+      provenance.layer_of("log.output"),
+      Some(format!("profile.{user_local}.infra").as_str())
+    );
+  }
+
+  engine
+    .generate(
+      "dev",
+      &[OutputFormat::Json, OutputFormat::Toml, OutputFormat::Yaml],
+    )
+    .expect("generate products");
+  for format in ["toml", "yaml"] {
+    let content =
+      fs::read_to_string(config_dir.join(format!("product/dev/{format}/gateway.{format}")))
+        .expect("read product");
+    assert!(content.starts_with("# "), "format={format}");
+    for name in &local_names {
+      assert!(content.contains(&format!("# local override: profile/{name}.toml\n")));
+    }
+  }
+  ConfigEngine::new(&config_dir)
+    .expect("create config engine")
+    .with_products(OutputFormat::Toml)
+    .resolve("dev", "gateway")
+    .expect("resolve product with local override header");
+  let meta: toml::Value = toml::from_str(
+    &fs::read_to_string(config_dir.join("product/dev/json/gateway.json.meta"))
+      .expect("read json product meta"),
+  )
+  .expect("parse json product meta");
+  let expected: Vec<_> = local_names
+    .iter()
+    .map(|name| toml::Value::String(format!("profile/{name}.toml")))
+    .collect();
+  assert_eq!(meta["local_overrides"], toml::Value::Array(expected));
+
+  fs::write(
+    config_dir.join("profile/dev.local.toml"),
+    "[services.gateway.server]\nhttps_port = 9090\n",
+  )
+  .expect("write invalid dev local profile");
+  let err = engine
+    .resolve("dev", "gateway")
+    .expect_err("unknown local profile field should fail");
+  assert_eq!(err.code(), CONFIGERR_UNKNOWNFIELD);
+  assert!(format!("{err}").contains("profile.dev.local.services.gateway.server.https_port"));
+}
+
+#[test]
+fn engine_should_merge_arrays_by_declared_strategy() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/net.toml"),
+    "[net]\nallowed_origins = [\"https://a.example\"]\n",
+  )
+  .expect("write infra net");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    "[[routes]]\nname = \"login\"\npath = \"/login\"\n",
+  )
+  .expect("write gateway template");
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    concat!(
+      "[infra.net]\nallowed_origins = [\"https://a.example\", \"http://localhost\"]\n",
+      "[[services.gateway.routes]]\nname = \"login\"\npath = \"/dev/login\"\n",
+      "[[services.gateway.routes]]\nname = \"debug\"\npath = \"/debug\"\n",
+    ),
+  )
+  .expect("write dev profile");
+  fs::write(
+    config_dir.join("merge_rules.toml"),
+    concat!(
+      "[infra]\nnet.allowed_origins = \"union\"\n\n",
+      "[services.gateway]\nroutes = { strategy = \"merge_by_key\", key = \"name\" }\n",
+    ),
+  )
+  .expect("write merge rules");
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  let resolved = engine
+    .resolve("dev", "gateway")
+    .expect("resolve gateway config");
+  let origins: Vec<String> = resolved
+    .extract("net.allowed_origins")
+    .expect("extract allowed origins");
+  assert_eq!(origins, ["https://a.example", "http://localhost"]);
+  let routes: Vec<toml::Table> = resolved.extract("routes").expect("extract routes");
+  let paths: Vec<_> = routes
+    .iter()
+    .map(|route| route["path"].as_str().expect("route path"))
+    .collect();
+  assert_eq!(paths, ["/dev/login", "/debug"]);
+
+  let provenance = engine
+    .provenance(&Dimensions::new("dev"), "gateway")
+    .expect("resolve provenance");
+  assert_eq!(
+    provenance.layer_of("net.allowed_origins"),
+    Some("template.infra + profile.dev.infra")
+  );
+
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    "[[services.gateway.routes]]\npath = \"/anonymous\"\n",
+  )
+  .expect("write keyless route");
+  let err = engine
+    .resolve("dev", "gateway")
+    .expect_err("route without merge key should fail");
+  assert_eq!(err.code(), CONFIGERR_INVALIDSTRUCTURE);
+  assert!(format!("{err}").contains("profile.dev.services.gateway.routes[0]"));
+
+  fs::write(
+    config_dir.join("merge_rules.toml"),
+    "[services.gateway]\nroutes.name = \"append\"\n",
+  )
+  .expect("write non-array merge rule");
+  let err = engine
+    .resolve("dev", "gateway")
+    .expect_err("merge rule on non-array should fail");
+  assert_eq!(err.code(), CONFIGERR_INVALIDSTRUCTURE);
+}
+
+#[test]
+fn engine_should_unset_optional_template_fields() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\noutput = \"stdout\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    concat!(
+      "__optional = [\"tls\"]\n\n",
+      "[server]\nhttp_port = 80\n\n",
+      "[tls]\ncert = \"gateway.pem\"\n",
+    ),
+  )
+  .expect("write gateway template");
+  fs::write(
+    config_dir.join("profile/minimal.toml"),
+    "[services.gateway]\n__unset = [\"tls\"]\n",
+  )
+  .expect("write minimal profile");
+  fs::write(config_dir.join("profile/dev.toml"), "").expect("write dev profile");
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  let minimal = engine
+    .resolve_layers("minimal", "gateway")
+    .expect("resolve minimal gateway");
+  assert!(minimal.merged().get("tls").is_none());
+  assert!(minimal.merged().get("__optional").is_none());
+  assert!(minimal.merged().get("__unset").is_none());
+  let dev = engine
+    .resolve_layers("dev", "gateway")
+    .expect("resolve dev gateway");
+  assert_eq!(dev.merged()["tls"]["cert"].as_str(), Some("gateway.pem"));
+
+  let provenance = engine
+    .provenance(&Dimensions::new("minimal"), "gateway")
+    .expect("resolve provenance");
+  assert_eq!(provenance.layer_of("tls.cert"), None);
+
+  let code = engine
+    .render_rust_values("minimal", "gateway")
+    .expect("render minimal rust values");
+  assert!(code.contains("pub tls: Option<"));
+  assert!(code.contains("tls: None,"));
+  let code = engine
+    .render_rust_values("dev", "gateway")
+    .expect("render dev rust values");
+  assert!(code.contains("tls: Some("));
+  let code = engine
+    .render_service_rust_types("gateway")
+    .expect("render service rust types");
+  assert!(code.contains("pub tls: Option<"));
+  assert!(!code.contains("__optional"));
+
+  fs::write(
+    config_dir.join("profile/minimal.toml"),
+    "[services.gateway]\n__unset = [\"server\"]\n",
+  )
+  .expect("write profile unsetting required field");
+  let err = engine
+    .resolve("minimal", "gateway")
+    .expect_err("unsetting required field should fail");
+  assert_eq!(err.code(), CONFIGERR_INVALIDSTRUCTURE);
+  assert!(format!("{err}").contains("profile.minimal.services.gateway.server"));
+
+  fs::write(
+    config_dir.join("profile/minimal.toml"),
+    "[infra.log]\n__unset = [\"output\"]\n",
+  )
+  .expect("write profile unsetting required infra field");
+  let err = engine
+    .resolve("minimal", "gateway")
+    .expect_err("unsetting required infra field should fail");
+  assert_eq!(err.code(), CONFIGERR_INVALIDSTRUCTURE);
+}
+
+#[test]
+fn engine_should_enforce_required_and_optional_field_markers() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    concat!(
+      "[server]\nhttp_port = 80\n",
+      "__optional = [{ name = \"idle_timeout\", type = \"integer\" }]\n\n",
+      "[upstream]\nendpoint = { __kind = \"required\", type = \"string\" }\n",
+    ),
+  )
+  .expect("write gateway template");
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    "[services.gateway.upstream]\nendpoint = \"http://127.0.0.1:9000\"\n",
+  )
+  .expect("write dev profile");
+  fs::write(
+    config_dir.join("profile/prod.toml"),
+    concat!(
+      "[services.gateway.server]\nidle_timeout = 30\n\n",
+      "[services.gateway.upstream]\nendpoint = \"https://upstream.internal\"\n",
+    ),
+  )
+  .expect("write prod profile");
+  fs::write(config_dir.join("profile/test.toml"), "").expect("write test profile");
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  let dev = engine
+    .resolve_layers("dev", "gateway")
+    .expect("resolve dev gateway");
+  assert_eq!(
+    dev.merged()["upstream"]["endpoint"].as_str(),
+    Some("http://127.0.0.1:9000")
+  );
+  assert!(dev.merged()["server"].get("idle_timeout").is_none());
+  let prod = engine
+    .resolve_layers("prod", "gateway")
+    .expect("resolve prod gateway");
+  assert_eq!(
+    prod.merged()["server"]["idle_timeout"].as_integer(),
+    Some(30)
+  );
+
+  let err = engine
+    .resolve("test", "gateway")
+    .expect_err("missing required field should fail");
+  assert_eq!(err.code(), CONFIGERR_REQUIREDFIELDMISSING);
+  let message = format!("{err}");
+  assert!(message.contains("profile=test"));
+  assert!(message.contains("profile.test.services.gateway.upstream.endpoint"));
+
+  let code = engine
+    .render_rust_values("dev", "gateway")
+    .expect("render dev rust values");
+  assert!(code.contains("pub idle_timeout: Option<i64>"));
+  assert!(code.contains("idle_timeout: None,"));
+  assert!(code.contains("pub endpoint: String"));
+  let code = engine
+    .render_rust_values("prod", "gateway")
+    .expect("render prod rust values");
+  assert!(code.contains("idle_timeout: Some(30"));
+  let code = engine
+    .render_service_rust_types("gateway")
+    .expect("render service rust types");
+  assert!(code.contains("pub idle_timeout: Option<i64>"));
+  assert!(code.contains("pub endpoint: String"));
+  assert!(!code.contains("__kind"));
+  assert!(!code.contains("__optional"));
+
+  fs::write(
+    config_dir.join("profile/test.toml"),
+    "[services.gateway.upstream]\nendpoint = 9000\n",
+  )
+  .expect("write profile with mistyped required field");
+  let err = engine
+    .resolve("test", "gateway")
+    .expect_err("mistyped required field should fail");
+  assert_eq!(err.code(), CONFIGERR_TYPEMISMATCH);
+
+  // 可选字段只能在 `__optional` 中声明
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    concat!(
+      "[server]\nhttp_port = 80\n",
+      "idle_timeout = { __kind = \"optional\", type = \"integer\" }\n\n",
+      "[upstream]\nendpoint = { __kind = \"required\", type = \"string\" }\n",
+    ),
+  )
+  .expect("write gateway template with inline optional marker");
+  let err = engine
+    .resolve("dev", "gateway")
+    .expect_err("inline optional marker should fail");
+  assert_eq!(err.code(), CONFIGERR_INVALIDSTRUCTURE);
+  assert!(format!("{err:?}").contains("declare optional fields in __optional"));
+
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    concat!(
+      "[server]\nhttp_port = 80\nidle_timeout = 10\n",
+      "__optional = [{ name = \"idle_timeout\", type = \"integer\" }]\n\n",
+      "[upstream]\nendpoint = { __kind = \"required\", type = \"string\" }\n",
+    ),
+  )
+  .expect("write gateway template with conflicting optional entry");
+  let err = engine
+    .resolve("dev", "gateway")
+    .expect_err("typed optional entry with a default should fail");
+  assert_eq!(err.code(), CONFIGERR_INVALIDSTRUCTURE);
+}
+
+#[test]
+fn engine_should_accept_profile_keys_in_map_sections() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    concat!(
+      "[routes.\"*\"]\nprefix = \"/\"\ntimeout_ms = 1000\n\n",
+      "[routes.health]\nprefix = \"/health\"\n\n",
+      "[limits]\n\"*\" = 10\n",
+    ),
+  )
+  .expect("write gateway template");
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    concat!(
+      "[services.gateway.routes.api]\nprefix = \"/api\"\n\n",
+      "[services.gateway.routes.admin]\ntimeout_ms = 5000\n\n",
+      "[services.gateway.limits]\napi = 100\n",
+    ),
+  )
+  .expect("write dev profile");
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  let dev = engine
+    .resolve_layers("dev", "gateway")
+    .expect("resolve dev gateway");
+  let routes = &dev.merged()["routes"];
+  assert_eq!(routes["api"]["prefix"].as_str(), Some("/api"));
+  assert_eq!(routes["api"]["timeout_ms"].as_integer(), Some(1000));
+  assert_eq!(routes["admin"]["prefix"].as_str(), Some("/"));
+  assert_eq!(routes["admin"]["timeout_ms"].as_integer(), Some(5000));
+  assert_eq!(routes["health"]["timeout_ms"].as_integer(), Some(1000));
+  assert!(routes.get("*").is_none());
+  assert_eq!(dev.merged()["limits"]["api"].as_integer(), Some(100));
+
+  let provenance = engine
+    .provenance(&Dimensions::new("dev"), "gateway")
+    .expect("resolve provenance");
+  assert_eq!(
+    provenance.layer_of("routes.api.timeout_ms"),
+    Some("template.service.gateway")
+  );
+  assert_eq!(
+    provenance.layer_of("routes.api.prefix"),
+    Some("profile.dev.services.gateway")
+  );
+
+  let code = engine
+    .render_rust_values("dev", "gateway")
+    .expect("render dev rust values");
+  assert!(code.contains("pub routes: std::collections::BTreeMap<String, RoutesItem>"));
+  assert!(code.contains("pub limits: std::collections::BTreeMap<String, u64>"));
+  assert!(code.contains("(String::from(\"api\"), RoutesItem {"));
+  let code = engine
+    .render_service_rust_types("gateway")
+    .expect("render service rust types");
+  assert!(code.contains("pub routes: std::collections::BTreeMap<String, RoutesItem>"));
+
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    "[services.gateway.routes.api]\nretries = 3\n",
+  )
+  .expect("write profile with unknown map entry field");
+  let err = engine
+    .resolve("dev", "gateway")
+    .expect_err("unknown map entry field should fail");
+  assert_eq!(err.code(), CONFIGERR_UNKNOWNFIELD);
+  assert!(format!("{err}").contains("services.gateway.routes.api.retries"));
+
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    "[services.gateway.routes.api]\ntimeout_ms = \"slow\"\n",
+  )
+  .expect("write profile with mistyped map entry field");
+  let err = engine
+    .resolve("dev", "gateway")
+    .expect_err("mistyped map entry field should fail");
+  assert_eq!(err.code(), CONFIGERR_TYPEMISMATCH);
+}
+
+#[test]
+fn engine_should_resolve_service_templates_through_extends_chain() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("template/base")).expect("create template base dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\noutput = \"stdout\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/base/base_node.toml"),
+    "[infra.log]\nlevel = \"WARN\"\n\n[server]\nhttp_port = 80\ngrpc_port = 50000\n",
+  )
+  .expect("write base node");
+  fs::write(
+    config_dir.join("template/base/base_server.toml"),
+    "extends = \"base_node\"\n\n[infra.log]\nlevel = \"DEBUG\"\n\n[server]\nhttp_port = 8000\n",
+  )
+  .expect("write base server");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    "extends = \"base_server\"\n\n[server]\nhttp_port = 8080\n\n[routes]\nprefix = \"/api\"\n",
+  )
+  .expect("write gateway template");
+  fs::write(
+    config_dir.join("template/service/lobby.toml"),
+    "extends = \"base_server\"\n",
+  )
+  .expect("write lobby template");
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    "[services.lobby.server]\ngrpc_port = 50052\n",
+  )
+  .expect("write dev profile");
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  assert_eq!(
+    engine.services().expect("list services"),
+    vec![String::from("gateway"), String::from("lobby")]
+  );
+
+  let gateway = engine
+    .resolve_layers("dev", "gateway")
+    .expect("resolve dev gateway");
+  assert_eq!(gateway.merged()["log"]["level"].as_str(), Some("DEBUG"));
+  assert_eq!(gateway.merged()["log"]["output"].as_str(), Some("stdout"));
+  assert_eq!(
+    gateway.merged()["server"]["http_port"].as_integer(),
+    Some(8080)
+  );
+  assert_eq!(
+    gateway.merged()["server"]["grpc_port"].as_integer(),
+    Some(50000)
+  );
+  assert!(gateway.service().get("extends").is_none());
+  let lobby = engine
+    .resolve_layers("dev", "lobby")
+    .expect("resolve dev lobby");
+  assert_eq!(
+    lobby.merged()["server"]["http_port"].as_integer(),
+    Some(8000)
+  );
+  assert_eq!(
+    lobby.merged()["server"]["grpc_port"].as_integer(),
+    Some(50052)
+  );
+
+  fs::write(
+    config_dir.join("template/base/base_node.toml"),
+    "extends = \"base_server\"\n",
+  )
+  .expect("write cyclic base node");
+  let err = engine
+    .resolve("dev", "gateway")
+    .expect_err("cyclic extends should fail");
+  assert_eq!(err.code(), CONFIGERR_INVALIDSTRUCTURE);
+  assert!(format!("{err}").contains(
+    "chain=template/service/gateway.toml -> template/base/base_server.toml -> template/base/base_node.toml -> template/base/base_server.toml"
+  ));
+
+  fs::write(config_dir.join("template/base/base_node.toml"), "").expect("write empty base node");
+  fs::write(
+    config_dir.join("template/service/lobby.toml"),
+    "extends = \"missing\"\n",
+  )
+  .expect("write lobby with missing base");
+  let err = engine
+    .resolve("dev", "lobby")
+    .expect_err("missing base template should fail");
+  assert_eq!(err.code(), CONFIGERR_FILELOADFAILED);
+}
+
+#[test]
+fn engine_should_limit_infra_to_declared_modules() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/infra/metrics.toml"),
+    "[metrics]\nenabled = true\n",
+  )
+  .expect("write infra metrics");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    "infra_modules = [\"log\"]\n\n[infra.log]\nlevel = \"DEBUG\"\n\n[server]\nhttp_port = 8080\n",
+  )
+  .expect("write gateway template");
+  fs::write(
+    config_dir.join("template/service/lobby.toml"),
+    "[server]\nhttp_port = 8081\n",
+  )
+  .expect("write lobby template");
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    "[infra.metrics]\nenabled = false\n",
+  )
+  .expect("write dev profile");
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  let gateway = engine
+    .resolve_layers("dev", "gateway")
+    .expect("resolve dev gateway");
+  assert_eq!(gateway.infra()["log"]["level"].as_str(), Some("DEBUG"));
+  assert!(gateway.merged().get("metrics").is_none());
+  assert!(gateway.service().get("infra_modules").is_none());
+  let lobby = engine
+    .resolve_layers("dev", "lobby")
+    .expect("resolve dev lobby");
+  assert_eq!(lobby.merged()["metrics"]["enabled"].as_bool(), Some(false));
+
+  let code = engine
+    .render_service_rust_types("gateway")
+    .expect("render gateway service rust types");
+  assert!(code.contains("pub struct LogConfig"));
+  assert!(!code.contains("MetricsConfig"));
+
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    "[services.gateway.infra.metrics]\nenabled = false\n",
+  )
+  .expect("write profile with unused infra module");
+  let err = engine
+    .resolve("dev", "gateway")
+    .expect_err("override of unused infra module should fail");
+  assert_eq!(err.code(), CONFIGERR_UNKNOWNFIELD);
+
+  fs::write(config_dir.join("profile/dev.toml"), "").expect("write empty dev profile");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    "infra_modules = [\"log\", \"tracing\"]\n",
+  )
+  .expect("write gateway with unknown infra module");
+  let err = engine
+    .resolve("dev", "lobby")
+    .expect_err("unknown infra module should fail");
+  assert_eq!(err.code(), CONFIGERR_INFRAMODULENOTFOUND);
+  assert!(format!("{err}").contains("path=template.service.gateway.infra_modules module=tracing"));
+}
+
+#[test]
+fn engine_should_require_infra_fields_only_for_used_modules() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/infra/metrics.toml"),
+    "[metrics]\nendpoint = { __kind = \"required\", type = \"string\" }\n",
+  )
+  .expect("write infra metrics");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    "infra_modules = [\"log\"]\n\n[server]\nhttp_port = 8080\n",
+  )
+  .expect("write gateway template");
+  fs::write(
+    config_dir.join("template/service/lobby.toml"),
+    "infra_modules = [\"log\", \"metrics\"]\n\n[server]\nhttp_port = 8081\n",
+  )
+  .expect("write lobby template");
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    "[enabled_services]\nlobby = false\n",
+  )
+  .expect("write dev profile");
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  let gateway = engine
+    .resolve_layers("dev", "gateway")
+    .expect("unused infra module should not require fields");
+  assert!(gateway.merged().get("metrics").is_none());
+
+  fs::write(config_dir.join("profile/dev.toml"), "").expect("write dev profile");
+  let err = engine
+    .resolve("dev", "gateway")
+    .expect_err("enabled lobby should require metrics endpoint");
+  assert_eq!(err.code(), CONFIGERR_REQUIREDFIELDMISSING);
+  assert!(format!("{err}").contains("path=profile.dev.infra.metrics.endpoint"));
+
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    "[infra.metrics]\nendpoint = \"http://127.0.0.1:9090\"\n",
+  )
+  .expect("write dev profile with metrics endpoint");
+  let lobby = engine
+    .resolve_layers("dev", "lobby")
+    .expect("resolve dev lobby");
+  assert_eq!(
+    lobby.merged()["metrics"]["endpoint"].as_str(),
+    Some("http://127.0.0.1:9090")
+  );
+}
+
+#[test]
+fn engine_should_follow_profile_service_enablement() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    "[server]\nhttp_port = 8080\n",
+  )
+  .expect("write gateway template");
+  fs::write(
+    config_dir.join("template/service/lobby.toml"),
+    "[upstream]\nendpoint = { __kind = \"required\", type = \"string\" }\n",
+  )
+  .expect("write lobby template");
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    "[enabled_services]\nlobby = false\n",
+  )
+  .expect("write dev profile");
+  fs::write(
+    config_dir.join("profile/stanley.toml"),
+    "[enabled_services]\n\"*\" = false\nlobby = true\n\n[services.lobby.upstream]\nendpoint = \"http://127.0.0.1:9000\"\n",
+  )
+  .expect("write stanley profile");
+
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  assert_eq!(
+    engine.enabled_services("dev").expect("list dev services"),
+    vec!["gateway"]
+  );
+  assert_eq!(
+    engine
+      .enabled_services("dev,stanley")
+      .expect("list stacked services"),
+    vec!["lobby"]
+  );
+
+  engine
+    .generate("dev", &[OutputFormat::Toml])
+    .expect("disabled lobby should not require upstream endpoint");
+  assert!(config_dir.join("product/dev/toml/gateway.toml").exists());
+  assert!(!config_dir.join("product/dev/toml/lobby.toml").exists());
+
+  let err = engine
+    .resolve("dev", "lobby")
+    .expect_err("disabled service should not resolve");
+  assert_eq!(err.code(), CONFIGERR_SERVICEDISABLED);
+  assert!(format!("{err:?}").contains("profile=dev service=lobby"));
+  let err = engine
+    .resolve("dev,stanley", "gateway")
+    .expect_err("wildcard should disable gateway");
+  assert_eq!(err.code(), CONFIGERR_SERVICEDISABLED);
+  engine
+    .resolve("dev,stanley", "lobby")
+    .expect("explicit flag should override earlier profile");
+
+  let products = ConfigEngine::new(&config_dir)
+    .expect("create product engine")
+    .with_products(OutputFormat::Toml);
+  assert_eq!(
+    products.enabled_services("dev").expect("list dev products"),
+    vec!["gateway"]
+  );
+
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    "[enabled_services]\nchat = false\n",
+  )
+  .expect("write profile with unknown service");
+  let err = engine
+    .enabled_services("dev")
+    .and_then(|_| engine.resolve("dev", "gateway"))
+    .expect_err("unknown service flag should fail");
+  assert_eq!(err.code(), CONFIGERR_SERVICENOTFOUND);
+
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    "[enabled_services]\nlobby = \"no\"\n",
+  )
+  .expect("write profile with non-bool flag");
+  let err = engine
+    .resolve("dev", "gateway")
+    .expect_err("non-bool flag should fail");
+  assert_eq!(err.code(), CONFIGERR_TYPEMISMATCH);
+}
@@ -1,4 +1,4 @@
-use bodhi_config::merge::deep_merge;
+use bodhi_config::merge::{MergeRules, MergeStrategy, deep_merge, deep_merge_with};
 use toml::Value;
 
 #[test]
@@ -41,3 +41,120 @@ fn deep_merge_should_replace_arrays() {
   assert_eq!(items[0].as_integer(), Some(4));
   assert_eq!(items[1].as_integer(), Some(5));
 }
+
+#[test]
+fn deep_merge_with_should_apply_array_strategies_per_path() {
+  let mut base: Value = toml::from_str(
+    r#"
+    origins = ["a", "b"]
+    tags = ["x"]
+    hosts = ["h1"]
+    ports = [1]
+
+    [[routes]]
+    name = "login"
+    path = "/login"
+    methods = ["GET"]
+    "#,
+  )
+  .expect("parse base toml");
+  let overlay: Value = toml::from_str(
+    r#"
+    origins = ["b", "c"]
+    tags = ["y"]
+    hosts = ["h0"]
+    ports = [2]
+
+    [[routes]]
+    name = "login"
+    methods = ["POST"]
+
+    [[routes]]
+    name = "logout"
+    path = "/logout"
+    "#,
+  )
+  .expect("parse overlay toml");
+
+  let rules = MergeRules::new()
+    .with("origins", MergeStrategy::Union)
+    .with("tags", MergeStrategy::Append)
+    .with("hosts", MergeStrategy::Prepend)
+    .with("routes", MergeStrategy::MergeByKey("name".to_string()))
+    .with("routes.methods", MergeStrategy::Append);
+  deep_merge_with(&mut base, &overlay, &rules);
+
+  let expected: Value = toml::from_str(
+    r#"
+    origins = ["a", "b", "c"]
+    tags = ["x", "y"]
+    hosts = ["h0", "h1"]
+    ports = [2]
+
+    [[routes]]
+    name = "login"
+    path = "/login"
+    methods = ["GET", "POST"]
+
+    [[routes]]
+    name = "logout"
+    path = "/logout"
+    "#,
+  )
+  .expect("parse expected toml");
+  assert_eq!(base, expected);
+}
+
+#[test]
+fn merge_strategy_should_parse_rule_values() {
+  let strategy = |literal: &str| {
+    let table: toml::Table = toml::from_str(&format!("value = {literal}")).expect("parse rule");
+    MergeStrategy::from_value(&table["value"])
+  };
+
+  assert_eq!(strategy("\"union\""), Some(MergeStrategy::Union));
+  assert_eq!(
+    strategy("{ strategy = \"merge_by_key\", key = \"id\" }"),
+    Some(MergeStrategy::MergeByKey("id".to_string()))
+  );
+  assert_eq!(strategy("\"shuffle\""), None);
+  assert_eq!(strategy("{ strategy = \"merge_by_key\" }"), None);
+}
+
+#[test]
+fn deep_merge_should_remove_unset_keys_and_union_optional_markers() {
+  let mut base: Value = toml::from_str(
+    r#"
+    __optional = ["tls"]
+    [tls]
+    cert = "a.pem"
+    [server]
+    http_port = 80
+    "#,
+  )
+  .expect("parse base toml");
+  let overlay: Value = toml::from_str(
+    r#"
+    __optional = ["admin"]
+    __unset = ["tls"]
+    [admin]
+    __unset = ["token"]
+    port = 9000
+    "#,
+  )
+  .expect("parse overlay toml");
+
+  deep_merge(&mut base, &overlay);
+
+  let expected: Value = toml::from_str(
+    r#"
+    __optional = ["tls", "admin"]
+    [server]
+    http_port = 80
+    [admin]
+    port = 9000
+    "#,
+  )
+  .expect("parse expected toml");
+  assert_eq!(base, expected);
+}
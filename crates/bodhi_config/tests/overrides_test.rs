@@ -0,0 +1,172 @@
+use std::fs;
+use std::path::Path;
+
+use bodhi_config::prelude::*;
+use serde::Deserialize;
+use tempfile::tempdir;
+
+#[derive(Debug, Deserialize)]
+struct InfraConfig {
+  log: LogConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct LogConfig {
+  level: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GatewayServiceConfig {
+  server: ServerConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServerConfig {
+  http_port: u16,
+  hosts: Vec<String>,
+}
+
+#[test]
+fn engine_should_apply_overrides_as_highest_priority_layer() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  write_overrides_test_config(&config_dir);
+
+  let mut overrides = Overrides::new();
+  overrides
+    .set_assignment("services.gateway.server.http_port=9999")
+    .expect("set http port");
+  overrides
+    .set_assignment("services.gateway.server.hosts=[\"a\", \"b\"]")
+    .expect("set hosts");
+  overrides
+    .set_assignment("infra.log.level = DEBUG")
+    .expect("set bare string");
+
+  let engine = ConfigEngine::new(&config_dir)
+    .expect("create config engine")
+    .with_overrides(overrides.clone());
+  let layers = engine
+    .resolve_layers("prod", "gateway@gw-2")
+    .expect("resolve with overrides");
+  let infra: InfraConfig = layers.extract_infra(".").expect("extract infra");
+  let service: GatewayServiceConfig = layers.extract_service(".").expect("extract service");
+  assert_eq!(infra.log.level, "DEBUG");
+  assert_eq!(service.server.http_port, 9999);
+  assert_eq!(service.server.hosts, ["a", "b"]);
+
+  let provenance = engine
+    .provenance(&Dimensions::new("prod"), "gateway@gw-2")
+    .expect("resolve provenance");
+  assert_eq!(
+    provenance.layer_of("server.http_port"),
+    Some("override.services.gateway")
+  );
+  assert_eq!(provenance.layer_of("log.level"), Some("override.infra"));
+
+  let store = ConfigStore::<InfraConfig, GatewayServiceConfig>::builder("prod", "gateway")
+    .config_dir(&config_dir)
+    .overrides(overrides)
+    .build()
+    .expect("load config store with overrides");
+  assert_eq!(store.snapshot().service().server.http_port, 9999);
+}
+
+#[test]
+fn engine_should_validate_overrides_against_templates() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  write_overrides_test_config(&config_dir);
+
+  let resolve = |assignment: &str| {
+    let mut overrides = Overrides::new();
+    overrides.set_assignment(assignment).expect("set override");
+    ConfigEngine::new(&config_dir)
+      .expect("create config engine")
+      .with_overrides(overrides)
+      .resolve("prod", "gateway")
+      .expect_err("invalid override should fail")
+  };
+
+  let err = resolve("services.gateway.server.https_port=1");
+  assert_eq!(err.code(), CONFIGERR_UNKNOWNFIELD);
+  assert!(format!("{err}").contains("override.services.gateway.server.https_port"));
+  assert_eq!(
+    resolve("services.gateway.server.http_port=fast").code(),
+    CONFIGERR_TYPEMISMATCH
+  );
+  assert_eq!(
+    resolve("services.gateway.instances.gw-3.server.http_port=1").code(),
+    CONFIGERR_UNKNOWNFIELD
+  );
+
+  let err = Overrides::new()
+    .set_assignment("services..http_port=1")
+    .expect_err("empty path segment should fail");
+  assert_eq!(err.code(), CONFIGERR_INVALIDPATH);
+
+  for assignment in [
+    "services.gateway.server.hosts=[\"a\", \"b\"",
+    "infra.service.name=\"debug",
+    "infra.service.name=debug mode",
+    "infra.service.name=",
+  ] {
+    let err = Overrides::new()
+      .set_assignment(assignment)
+      .expect_err("malformed literal should fail");
+    assert_eq!(err.code(), CONFIGERR_PARSEFAILED, "assignment={assignment}");
+  }
+  let mut overrides = Overrides::new();
+  overrides
+    .set_assignment("infra.net.listen_host=0.0.0.0")
+    .expect("bare word override");
+  overrides
+    .set_assignment("infra.service.name=\"debug mode\"")
+    .expect("quoted override");
+  assert_eq!(
+    overrides.table()["infra"]["net"]["listen_host"].as_str(),
+    Some("0.0.0.0")
+  );
+  assert_eq!(
+    overrides.table()["infra"]["service"]["name"].as_str(),
+    Some("debug mode")
+  );
+
+  let overlay_path = tempdir.path().join("debug.toml");
+  fs::write(&overlay_path, "[services.gateway.server]\nhttp_port = 1\n")
+    .expect("write overlay file");
+  let mut overrides = Overrides::new();
+  overrides
+    .merge_file(&overlay_path)
+    .expect("merge overlay file");
+  let engine = ConfigEngine::new(&config_dir)
+    .expect("create config engine")
+    .with_products(OutputFormat::Toml)
+    .with_overrides(overrides);
+  let err = engine
+    .resolve("prod", "gateway")
+    .expect_err("overrides unavailable in product mode");
+  assert_eq!(err.code(), CONFIGERR_UNSUPPORTEDOPERATION);
+}
+
+fn write_overrides_test_config(config_dir: &Path) {
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    "[server]\nhttp_port = 80\nhosts = []\n",
+  )
+  .expect("write gateway template");
+  fs::write(
+    config_dir.join("profile/prod.toml"),
+    "[services.gateway.instances.gw-2.server]\nhttp_port = 8002\n",
+  )
+  .expect("write prod profile");
+}
@@ -0,0 +1,28 @@
+use bodhi_config::profile::{profile_from_args, select_profile_from};
+
+#[test]
+fn select_profile_should_prefer_cli_then_env_then_default() {
+  assert_eq!(
+    select_profile_from(["--profile", "stanley"], Some("prod".to_string()), "dev"),
+    "stanley"
+  );
+  assert_eq!(
+    select_profile_from(["--verbose", "--profile=stanley"], None, "dev"),
+    "stanley"
+  );
+  assert_eq!(
+    select_profile_from(["--verbose"], Some("prod".to_string()), "dev"),
+    "prod"
+  );
+  assert_eq!(
+    select_profile_from(Vec::<String>::new(), Some(" ".to_string()), "dev"),
+    "dev"
+  );
+}
+
+#[test]
+fn profile_from_args_should_stop_at_double_dash() {
+  assert_eq!(profile_from_args(["--", "--profile", "stanley"]), None);
+  assert_eq!(profile_from_args(["--profile"]), None);
+  assert_eq!(profile_from_args(["--profile="]), None);
+}
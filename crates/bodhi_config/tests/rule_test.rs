@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bodhi_config::prelude::*;
+use bodhi_config::rule::{CompareFields, CompareOp, UniqueValues};
+use tempfile::tempdir;
+
+fn write_ruled_config(config_dir: &Path, profile: &str) {
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    concat!(
+      "[server]\nhttp_port = 8080\ngrpc_port = 9090\n\n",
+      "[client]\nconnect_timeout_ms = 1000\nrequest_timeout_ms = 3000\n",
+    ),
+  )
+  .expect("write gateway template");
+  fs::write(
+    config_dir.join("template/service/lobby.toml"),
+    "[server]\nhttp_port = 8081\ngrpc_port = 9091\n",
+  )
+  .expect("write lobby template");
+  fs::write(config_dir.join("profile/dev.toml"), profile).expect("write dev profile");
+}
+
+fn ruled_engine(config_dir: &Path) -> ConfigEngine {
+  ConfigEngine::new(config_dir)
+    .expect("create config engine")
+    .with_rule(CompareFields::new(
+      "timeout_order",
+      "client.request_timeout_ms",
+      CompareOp::Ge,
+      "client.connect_timeout_ms",
+    ))
+    .with_rule(UniqueValues::new(
+      "unique_ports",
+      ["server.http_port", "server.grpc_port"],
+    ))
+}
+
+#[test]
+fn service_rules_should_run_after_resolution() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  write_ruled_config(&config_dir, "");
+
+  let engine = ruled_engine(&config_dir);
+  assert_eq!(
+    engine.rules().names(),
+    vec!["timeout_order", "unique_ports"]
+  );
+  engine
+    .resolve("dev", "gateway")
+    .expect("valid timeouts should resolve");
+
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    "[services.gateway.client]\nrequest_timeout_ms = 500\n",
+  )
+  .expect("write invalid timeouts");
+  let err = engine
+    .resolve("dev", "gateway")
+    .expect_err("request timeout below connect timeout should fail");
+  assert_eq!(err.code(), CONFIGERR_RULEVIOLATED);
+  let message = format!("{err:?}");
+  assert!(message.contains("service=gateway violations=1"));
+  assert!(message.contains(concat!(
+    "services.gateway.client.request_timeout_ms, services.gateway.client.connect_timeout_ms: ",
+    "client.request_timeout_ms=500 must be >= client.connect_timeout_ms=1000 rule=timeout_order",
+  )));
+
+  engine
+    .resolve("dev", "lobby")
+    .expect("rule on missing fields should be skipped");
+}
+
+#[test]
+fn profile_rules_should_detect_shared_ports_across_services() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  write_ruled_config(&config_dir, "");
+
+  let engine = ruled_engine(&config_dir);
+  engine
+    .check_rules("dev")
+    .expect("distinct ports should pass");
+
+  fs::write(
+    config_dir.join("profile/dev.toml"),
+    concat!(
+      "[services.lobby.server]\nhttp_port = 8080\n\n",
+      "[services.gateway.instances.gw-2.server]\ngrpc_port = 9091\n",
+    ),
+  )
+  .expect("write conflicting ports");
+  let err = engine
+    .check_rules("dev")
+    .expect_err("shared ports should fail");
+  assert_eq!(err.code(), CONFIGERR_RULEVIOLATED);
+  let message = format!("{err:?}");
+  assert!(message.contains("profile=dev violations=2"));
+  assert!(message.contains(
+    "services.gateway.server.http_port, services.gateway@gw-2.server.http_port, services.lobby.server.http_port: value 8080 is shared by 3 fields rule=unique_ports"
+  ));
+  assert!(message.contains(
+    "services.gateway@gw-2.server.grpc_port, services.lobby.server.grpc_port: value 9091 is shared by 2 fields"
+  ));
+
+  let err = engine
+    .generate("dev", &[OutputFormat::Toml])
+    .expect_err("generation should run profile rules");
+  assert_eq!(err.code(), CONFIGERR_RULEVIOLATED);
+  assert!(!config_dir.join("product").exists());
+
+  let rules = RuleRegistry::new();
+  assert!(rules.is_empty());
+  assert!(rules.check_profile("dev", &BTreeMap::new()).is_ok());
+}
+
+/// 统计单服务检查次数的规则，每次解析都会执行一次单服务检查
+struct CountingRule(Arc<AtomicUsize>);
+
+impl ConfigRule for CountingRule {
+  fn name(&self) -> &str {
+    "counting"
+  }
+
+  fn check_service(&self, _target: &str, _layers: &ResolvedLayers) -> Vec<RuleViolation> {
+    self.0.fetch_add(1, Ordering::SeqCst);
+    Vec::new()
+  }
+}
+
+#[test]
+fn generate_should_resolve_each_target_once() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  write_ruled_config(
+    &config_dir,
+    "[services.gateway.instances.gw-2.server]\nhttp_port = 8082\ngrpc_port = 9092\n",
+  );
+
+  let resolutions = Arc::new(AtomicUsize::new(0));
+  let engine = ruled_engine(&config_dir).with_rule(CountingRule(resolutions.clone()));
+  engine
+    .generate("dev", &[OutputFormat::Toml])
+    .expect("generate dev products");
+  assert_eq!(resolutions.load(Ordering::SeqCst), 3);
+  assert!(
+    config_dir
+      .join("product/dev/toml/gateway@gw-2.toml")
+      .is_file()
+  );
+  assert!(config_dir.join("product/dev/toml/lobby.toml").is_file());
+}
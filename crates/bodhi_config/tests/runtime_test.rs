@@ -118,6 +118,413 @@ fn config_store_should_share_snapshots_across_threads_and_reload() {
   assert_eq!(store.current_version(), 2);
 }
 
+#[test]
+fn config_store_validator_should_reject_reload_and_keep_current_snapshot() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  let profile_path = config_dir.join("profile/dev.toml");
+
+  write_runtime_test_config(&config_dir, "stderr", 18080);
+
+  let store = ConfigStore::<InfraConfig, GatewayServiceConfig>::builder("dev", "gateway")
+    .config_dir(&config_dir)
+    .validator("server.http_port", |snapshot| {
+      if snapshot.service().server.http_port < 1024 {
+        return Err(
+          Error::new(CONFIGERR_VALIDATIONFAILED).wrap_context("http_port must be >= 1024"),
+        );
+      }
+      Ok(())
+    })
+    .build()
+    .expect("build config store");
+
+  fs::write(&profile_path, "[services.gateway.server]\nhttp_port = 80\n").expect("rewrite profile");
+
+  let err = store.reload().expect_err("reload should be rejected");
+  assert_eq!(err.code(), CONFIGERR_VALIDATIONFAILED);
+  assert!(format!("{err}").contains("validator=server.http_port"));
+
+  let snapshot = store.snapshot();
+  assert_eq!(snapshot.version(), 1);
+  assert_eq!(snapshot.service().server.http_port, 18080);
+
+  let stats = store.stats();
+  assert_eq!(stats.current_version, 1);
+  assert_eq!(stats.reload_attempts, 1);
+  assert_eq!(stats.reload_failures, 1);
+  assert!(
+    stats
+      .last_error
+      .expect("last error should be recorded")
+      .contains("http_port must be >= 1024")
+  );
+
+  fs::write(
+    &profile_path,
+    "[services.gateway.server]\nhttp_port = 8443\n",
+  )
+  .expect("rewrite profile");
+  let snapshot = store.reload().expect("reload valid config");
+  assert_eq!(snapshot.version(), 2);
+  assert_eq!(store.history_versions(), vec![1]);
+}
+
+#[test]
+fn config_store_should_rollback_to_bounded_history() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  let profile_path = config_dir.join("profile/dev.toml");
+
+  write_runtime_test_config(&config_dir, "stderr", 18080);
+
+  let store = ConfigStore::<InfraConfig, GatewayServiceConfig>::builder("dev", "gateway")
+    .config_dir(&config_dir)
+    .history_limit(2)
+    .build()
+    .expect("build config store");
+
+  for port in [18081, 18082, 18083] {
+    fs::write(
+      &profile_path,
+      format!("[services.gateway.server]\nhttp_port = {port}\n"),
+    )
+    .expect("rewrite profile");
+    store.reload().expect("reload config");
+  }
+
+  assert_eq!(store.current_version(), 4);
+  assert_eq!(store.history_versions(), vec![2, 3]);
+
+  let err = store
+    .rollback(1)
+    .expect_err("evicted version should be missing");
+  assert_eq!(err.code(), CONFIGERR_VERSIONNOTFOUND);
+
+  let snapshot = store.rollback(2).expect("rollback to version 2");
+  assert_eq!(snapshot.version(), 5);
+  assert_eq!(snapshot.service().server.http_port, 18081);
+  assert_eq!(store.current_version(), 5);
+  assert_eq!(store.history_versions(), vec![3, 4]);
+}
+
+#[test]
+fn snapshot_handle_should_refresh_only_after_version_changes() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  let profile_path = config_dir.join("profile/dev.toml");
+
+  write_runtime_test_config(&config_dir, "stderr", 18080);
+
+  let store = Arc::new(
+    ConfigStore::<InfraConfig, GatewayServiceConfig>::load_from(&config_dir, "dev", "gateway")
+      .expect("load config store"),
+  );
+  let mut handle = store.handle();
+
+  let first = Arc::clone(handle.get());
+  assert!(Arc::ptr_eq(&first, handle.get()));
+
+  fs::write(
+    &profile_path,
+    "[services.gateway.server]\nhttp_port = 28080\n",
+  )
+  .expect("rewrite profile");
+  store.reload().expect("reload config");
+
+  let refreshed = handle.get();
+  assert_eq!(refreshed.version(), 2);
+  assert_eq!(refreshed.service().server.http_port, 28080);
+  assert!(!Arc::ptr_eq(&first, refreshed));
+}
+
+#[test]
+fn config_store_with_local_should_refresh_after_reload_on_same_thread() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  let profile_path = config_dir.join("profile/dev.toml");
+
+  write_runtime_test_config(&config_dir, "stderr", 18080);
+
+  let store = Arc::new(
+    ConfigStore::<InfraConfig, GatewayServiceConfig>::load_from(&config_dir, "dev", "gateway")
+      .expect("load config store"),
+  );
+  let other =
+    ConfigStore::<InfraConfig, GatewayServiceConfig>::load_from(&config_dir, "dev", "gateway")
+      .expect("load other config store");
+
+  let first = store.with_local(|snapshot| {
+    assert_eq!(snapshot.version(), 1);
+    other.with_local(|other| assert_eq!(other.service().server.http_port, 18080));
+    snapshot.content_hash()
+  });
+  assert_eq!(store.with_local(|snapshot| snapshot.content_hash()), first);
+
+  fs::write(
+    &profile_path,
+    "[services.gateway.server]\nhttp_port = 28080\n",
+  )
+  .expect("rewrite profile");
+  store.reload().expect("reload config");
+
+  store.with_local(|snapshot| {
+    assert_eq!(snapshot.version(), 2);
+    assert_eq!(snapshot.service().server.http_port, 28080);
+  });
+  other.with_local(|snapshot| {
+    assert_eq!(snapshot.version(), 1);
+    assert_eq!(snapshot.service().server.http_port, 18080);
+  });
+
+  let remote = Arc::clone(&store);
+  let port =
+    thread::spawn(move || remote.with_local(|snapshot| snapshot.service().server.http_port))
+      .join()
+      .expect("thread should finish");
+  assert_eq!(port, 28080);
+}
+
+#[test]
+fn config_store_reload_should_skip_publishing_unchanged_content() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  let profile_path = config_dir.join("profile/dev.toml");
+
+  write_runtime_test_config(&config_dir, "stderr", 18080);
+
+  let store =
+    ConfigStore::<InfraConfig, GatewayServiceConfig>::load_from(&config_dir, "dev", "gateway")
+      .expect("load config store");
+  let initial = store.snapshot();
+
+  // 仅调整键顺序和空白，内容哈希保持不变
+  fs::write(
+    &profile_path,
+    "[services.gateway.server]\nhttp_port = 18080\n\n[infra.log]\noutput   = \"stderr\"\n",
+  )
+  .expect("rewrite profile");
+
+  let snapshot = store.reload().expect("reload config");
+  assert_eq!(snapshot.version(), 1);
+  assert_eq!(snapshot.content_hash(), initial.content_hash());
+  assert!(Arc::ptr_eq(&snapshot, &initial));
+
+  let stats = store.stats();
+  assert_eq!(stats.reload_attempts, 1);
+  assert_eq!(stats.reload_unchanged, 1);
+  assert!(stats.history_versions.is_empty());
+
+  store.register_validator("server.http_port", |snapshot| {
+    if snapshot.service().server.http_port < 20000 {
+      return Err(
+        Error::new(CONFIGERR_VALIDATIONFAILED).wrap_context("http_port must be >= 20000"),
+      );
+    }
+    Ok(())
+  });
+  let err = store
+    .reload()
+    .expect_err("unchanged reload should still run new validators");
+  assert_eq!(err.code(), CONFIGERR_VALIDATIONFAILED);
+  assert_eq!(store.current_version(), 1);
+
+  fs::write(
+    &profile_path,
+    "[services.gateway.server]\nhttp_port = 28080\n",
+  )
+  .expect("rewrite profile");
+
+  let snapshot = store.reload().expect("reload config");
+  assert_eq!(snapshot.version(), 2);
+  assert_ne!(snapshot.content_hash(), initial.content_hash());
+}
+
+#[test]
+fn config_store_should_start_degraded_from_last_known_good_cache() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  let moved_dir = tempdir.path().join("config.bak");
+  let cache_path = tempdir.path().join("cache/gateway-dev.toml");
+
+  write_runtime_test_config(&config_dir, "stderr", 18080);
+
+  let store = ConfigStore::<InfraConfig, GatewayServiceConfig>::builder("dev", "gateway")
+    .config_dir(&config_dir)
+    .cache_path(&cache_path)
+    .build()
+    .expect("build config store");
+  assert!(!store.snapshot().is_degraded());
+  assert!(cache_path.is_file());
+  let cached_hash = store.snapshot().content_hash();
+  drop(store);
+
+  fs::rename(&config_dir, &moved_dir).expect("move config dir away");
+
+  let store = ConfigStore::<InfraConfig, GatewayServiceConfig>::builder("dev", "gateway")
+    .config_dir(&config_dir)
+    .cache_path(&cache_path)
+    .build()
+    .expect("build config store from cache");
+  let snapshot = store.snapshot();
+  assert!(snapshot.is_degraded());
+  assert!(
+    snapshot
+      .warning()
+      .expect("degraded snapshot should carry a warning")
+      .contains("last-known-good cache")
+  );
+  assert_eq!(snapshot.content_hash(), cached_hash);
+  assert_eq!(snapshot.service().server.http_port, 18080);
+
+  fs::rename(&moved_dir, &config_dir).expect("restore config dir");
+
+  let snapshot = store.reload().expect("reload restored config");
+  assert!(!snapshot.is_degraded());
+  assert_eq!(snapshot.version(), 2);
+  assert_eq!(snapshot.content_hash(), cached_hash);
+}
+
+#[test]
+fn config_store_should_search_ancestors_again_after_degraded_start() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  let service_dir = tempdir.path().join("app/gateway");
+  let cache_path = tempdir.path().join("cache/gateway-dev.toml");
+  fs::create_dir_all(&service_dir).expect("create service dir");
+
+  write_runtime_test_config(&config_dir, "stderr", 18080);
+  ConfigStore::<InfraConfig, GatewayServiceConfig>::builder("dev", "gateway")
+    .source(SearchSource::new(&service_dir, "config"))
+    .cache_path(&cache_path)
+    .build()
+    .expect("build config store");
+  let moved_dir = tempdir.path().join("config.bak");
+  fs::rename(&config_dir, &moved_dir).expect("move config dir away");
+
+  let source = SearchSource::new(&service_dir, "config");
+  let err = source
+    .list("profile")
+    .expect_err("missing config dir should fail");
+  assert_eq!(err.code(), CONFIGERR_CONFIGDIRNOTFOUND);
+  assert!(source.found_dir().is_none());
+
+  let store = ConfigStore::<InfraConfig, GatewayServiceConfig>::builder("dev", "gateway")
+    .source(source)
+    .cache_path(&cache_path)
+    .build()
+    .expect("build config store from cache");
+  assert!(store.snapshot().is_degraded());
+  assert!(store.config_dir().is_none());
+
+  fs::rename(&moved_dir, &config_dir).expect("restore config dir");
+  let snapshot = store
+    .reload()
+    .expect("reload should find restored ancestor config dir");
+  assert!(!snapshot.is_degraded());
+  assert_eq!(store.config_dir(), Some(config_dir.as_path()));
+}
+
+#[test]
+fn config_store_should_reject_tampered_cache() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  let cache_path = tempdir.path().join("gateway-dev.toml");
+
+  write_runtime_test_config(&config_dir, "stderr", 18080);
+
+  ConfigStore::<InfraConfig, GatewayServiceConfig>::builder("dev", "gateway")
+    .config_dir(&config_dir)
+    .cache_path(&cache_path)
+    .build()
+    .expect("build config store");
+
+  let cache = fs::read_to_string(&cache_path).expect("read cache");
+  assert!(cache.contains("label = \"dev\""));
+  fs::remove_dir_all(&config_dir).expect("remove config dir");
+
+  let err = ConfigStore::<InfraConfig, GatewayServiceConfig>::builder("prod", "gateway")
+    .config_dir(&config_dir)
+    .cache_path(&cache_path)
+    .build()
+    .expect_err("cache for another label should be rejected");
+  assert!(format!("{err}").contains("cached label=dev service=gateway requested label=prod"));
+
+  fs::write(&cache_path, cache.replace("18080", "18081")).expect("tamper cache");
+
+  let err = ConfigStore::<InfraConfig, GatewayServiceConfig>::builder("dev", "gateway")
+    .config_dir(&config_dir)
+    .cache_path(&cache_path)
+    .build()
+    .expect_err("tampered cache should be rejected");
+
+  assert_eq!(err.code(), CONFIGERR_CONFIGDIRNOTFOUND);
+  let message = format!("{err}");
+  assert!(message.contains("last-known-good config cache unavailable"));
+  assert!(message.contains(&CONFIGERR_CACHECORRUPTED.to_string()));
+}
+
+#[test]
+fn config_store_should_load_and_reload_from_products() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+
+  write_runtime_test_config(&config_dir, "stderr", 18080);
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+  engine
+    .generate_service("dev", "gateway", &[OutputFormat::Yaml])
+    .expect("generate yaml product");
+
+  let store = ConfigStore::<InfraConfig, GatewayServiceConfig>::builder("dev", "gateway")
+    .engine(ConfigEngine::new(&config_dir).expect("create config engine"))
+    .products(OutputFormat::Yaml)
+    .build()
+    .expect("load config store from products");
+  assert_eq!(store.snapshot().infra().service.name, "gateway");
+  assert_eq!(store.snapshot().service().server.http_port, 18080);
+
+  // 模板变更后必须重新生成产物才会生效
+  write_runtime_test_config(&config_dir, "stdout", 19090);
+  store.reload().expect("reload unchanged product");
+  assert_eq!(store.snapshot().service().server.http_port, 18080);
+
+  engine
+    .generate_service("dev", "gateway", &[OutputFormat::Yaml])
+    .expect("regenerate yaml product");
+  let snapshot = store.reload().expect("reload regenerated product");
+  assert_eq!(snapshot.infra().log.output, "stdout");
+  assert_eq!(snapshot.service().server.http_port, 19090);
+}
+
+#[test]
+fn config_store_should_load_selected_service_instance() {
+  let tempdir = tempdir().expect("create tempdir");
+  let config_dir = tempdir.path().join("config");
+  let profile_path = config_dir.join("profile/dev.toml");
+
+  write_runtime_test_config(&config_dir, "stderr", 18080);
+  let mut profile = fs::read_to_string(&profile_path).expect("read dev profile");
+  profile.push_str("\n[services.gateway.instances.gw-2.server]\nhttp_port = 18082\n");
+  fs::write(&profile_path, profile).expect("write dev profile instances");
+
+  let store = ConfigStore::<InfraConfig, GatewayServiceConfig>::builder("dev", "gateway")
+    .config_dir(&config_dir)
+    .instance("gw-2")
+    .build()
+    .expect("load gw-2 config store");
+
+  assert_eq!(store.service(), "gateway@gw-2");
+  assert_eq!(store.snapshot().infra().service.name, "gateway");
+  assert_eq!(store.snapshot().service().server.http_port, 18082);
+  assert_eq!(store.snapshot().service().routes.prefix, "/api/v1");
+
+  let err = ConfigStore::<InfraConfig, GatewayServiceConfig>::builder("dev", "gateway@gw-9")
+    .config_dir(&config_dir)
+    .build()
+    .expect_err("unknown instance should fail");
+  assert_eq!(err.code(), CONFIGERR_INSTANCENOTFOUND);
+}
+
 fn write_runtime_test_config(config_dir: &std::path::Path, log_output: &str, http_port: u16) {
   fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
   fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
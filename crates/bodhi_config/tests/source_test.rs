@@ -0,0 +1,313 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bodhi_config::prelude::*;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct InfraConfig {
+  log: LogConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct LogConfig {
+  level: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GatewayServiceConfig {
+  server: ServerConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServerConfig {
+  http_port: u16,
+}
+
+#[test]
+fn engine_should_resolve_from_memory_source() {
+  let engine = ConfigEngine::from_source(memory_test_source());
+
+  assert_eq!(engine.config_dir(), None);
+  assert_eq!(engine.profiles().expect("list profiles"), ["dev"]);
+  assert_eq!(engine.services().expect("list services"), ["gateway"]);
+
+  let layers = engine
+    .resolve_layers("dev", "gateway")
+    .expect("resolve gateway layers");
+  let log: LogConfig = layers.extract_infra("log").expect("extract log config");
+  let server: ServerConfig = layers
+    .extract_service("server")
+    .expect("extract server config");
+  assert_eq!(log.level, "DEBUG");
+  assert_eq!(server.http_port, 8080);
+
+  let err = engine
+    .resolve("prod", "gateway")
+    .expect_err("unknown profile should fail");
+  assert_eq!(err.code(), CONFIGERR_PROFILENOTFOUND);
+
+  let err = engine
+    .generate("dev", &[OutputFormat::Toml])
+    .expect_err("memory source has no product directory");
+  assert_eq!(err.code(), CONFIGERR_UNSUPPORTEDOPERATION);
+}
+
+#[test]
+fn config_store_should_load_from_http_source() {
+  let base_url = serve_memory_source(memory_test_source(), "/config");
+
+  let source = HttpSource::new(&base_url).expect("create http source");
+  let store = ConfigStore::<InfraConfig, GatewayServiceConfig>::builder("dev", "gateway")
+    .source(source.clone())
+    .build()
+    .expect("load config store from http source");
+
+  assert_eq!(store.source().describe(), base_url);
+  assert_eq!(store.snapshot().infra().log.level, "DEBUG");
+  assert_eq!(store.snapshot().service().server.http_port, 8080);
+  assert_eq!(
+    store.reload().expect("reload from http source").version(),
+    1
+  );
+
+  let engine = ConfigEngine::from_source(source);
+  assert_eq!(engine.profiles().expect("list http profiles"), ["dev"]);
+  let err = engine
+    .resolve("dev", "lobby")
+    .expect_err("unknown service should fail");
+  assert_eq!(err.code(), CONFIGERR_SERVICENOTFOUND);
+}
+
+#[test]
+fn http_source_should_report_unreachable_server() {
+  let listener = TcpListener::bind("127.0.0.1:0").expect("bind probe listener");
+  let addr = listener.local_addr().expect("get probe address");
+  drop(listener);
+
+  let source = HttpSource::new(&format!("http://{addr}")).expect("create http source");
+  let err = ConfigEngine::from_source(source)
+    .resolve("dev", "gateway")
+    .expect_err("unreachable source should fail");
+  assert_eq!(err.code(), CONFIGERR_SOURCEUNAVAILABLE);
+}
+
+#[test]
+fn http_source_should_encode_paths_and_follow_redirects() {
+  let base_url = serve_memory_source(
+    memory_test_source().with_file("notes/release notes.toml", "[notes]\nready = true\n"),
+    "/config",
+  );
+  let legacy_url = base_url.replace("/config", LEGACY_PREFIX);
+
+  let source = HttpSource::new(&legacy_url).expect("create http source");
+  let notes = source
+    .read("notes/release notes.toml")
+    .expect("read file with space through redirect");
+  assert_eq!(notes.as_deref(), Some("[notes]\nready = true\n"));
+  assert_eq!(
+    source
+      .read("notes/missing.toml")
+      .expect("read missing file"),
+    None
+  );
+
+  let engine = ConfigEngine::from_source(source);
+  let layers = engine
+    .resolve_layers("dev", "gateway")
+    .expect("resolve through redirect");
+  let server: ServerConfig = layers
+    .extract_service("server")
+    .expect("extract server config");
+  assert_eq!(server.http_port, 8080);
+}
+
+#[test]
+fn http_source_should_cap_response_size() {
+  let base_url = serve_raw(|mut stream| {
+    let body = "x".repeat(64 * 1024);
+    let response = format!("HTTP/1.0 200 OK\r\n\r\n{body}");
+    let _ = stream.write_all(response.as_bytes());
+  });
+
+  let source = HttpSource::new(&base_url)
+    .expect("create http source")
+    .with_max_response_size(1024);
+  let err = source
+    .read("profile/dev.toml")
+    .expect_err("oversized response should fail");
+  assert_eq!(err.code(), CONFIGERR_SOURCEUNAVAILABLE);
+  assert!(format!("{err:?}").contains("http response too large"));
+}
+
+#[test]
+fn http_source_should_bound_whole_request_by_timeout() {
+  let base_url = serve_raw(|mut stream| {
+    // 每次只写一个字节，单次读取都不会超时
+    let _ = stream.write_all(b"HTTP/1.0 200 OK\r\n\r\n");
+    while stream.write_all(b"x").is_ok() {
+      thread::sleep(Duration::from_millis(20));
+    }
+  });
+
+  let source = HttpSource::new(&base_url)
+    .expect("create http source")
+    .with_timeout(Duration::from_millis(300));
+  let started = Instant::now();
+  let err = source
+    .read("profile/dev.toml")
+    .expect_err("slow response should time out");
+  assert!(started.elapsed() < Duration::from_secs(3));
+  assert_eq!(err.code(), CONFIGERR_SOURCEUNAVAILABLE);
+  assert!(format!("{err:?}").contains("http request timed out"));
+}
+
+#[test]
+fn http_source_should_reject_cross_origin_redirects() {
+  let other_url = serve_memory_source(memory_test_source(), "/config");
+  let base_url = serve_raw(move |mut stream| {
+    let response = format!("HTTP/1.0 302 Found\r\nLocation: {other_url}/profile/dev.toml\r\n\r\n");
+    let _ = stream.write_all(response.as_bytes());
+  });
+
+  let source = HttpSource::new(&base_url).expect("create http source");
+  let err = source
+    .read("profile/dev.toml")
+    .expect_err("cross-origin redirect should fail");
+  assert_eq!(err.code(), CONFIGERR_SOURCEUNAVAILABLE);
+  assert!(format!("{err:?}").contains("cross-origin http redirect rejected"));
+}
+
+#[test]
+fn http_source_should_accept_bracketed_ipv6_hosts() {
+  let source = HttpSource::new("http://[::1]:1/config").expect("create ipv6 http source");
+  assert_eq!(source.describe(), "http://[::1]:1/config");
+  let err = ConfigEngine::from_source(source)
+    .resolve("dev", "gateway")
+    .expect_err("closed ipv6 port should fail");
+  assert_eq!(err.code(), CONFIGERR_SOURCEUNAVAILABLE);
+
+  for url in [
+    "http://::1:8080/config",
+    "http://[::1/config",
+    "http://user@host/config",
+  ] {
+    let err = HttpSource::new(url).expect_err("invalid authority should be rejected");
+    assert_eq!(err.code(), CONFIGERR_INVALIDPATH, "url={url}");
+  }
+}
+
+fn memory_test_source() -> MemorySource {
+  MemorySource::new()
+    .with_file("template/infra/log.toml", "[log]\nlevel = \"INFO\"\n")
+    .with_file(
+      "template/service/gateway.toml",
+      "[server]\nhttp_port = 80\n",
+    )
+    .with_file(
+      "profile/dev.toml",
+      "[infra.log]\nlevel = \"DEBUG\"\n[services.gateway.server]\nhttp_port = 8080\n",
+    )
+}
+
+/// 替身服务中重定向到真实前缀的旧前缀
+const LEGACY_PREFIX: &str = "/legacy";
+
+fn decode_path(path: &str) -> String {
+  let bytes = path.as_bytes();
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut index = 0;
+  while index < bytes.len() {
+    if bytes[index] == b'%'
+      && let Some(byte) = path
+        .get(index + 1..index + 3)
+        .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+    {
+      decoded.push(byte);
+      index += 3;
+    } else {
+      decoded.push(bytes[index]);
+      index += 1;
+    }
+  }
+  String::from_utf8(decoded).expect("decoded path should be utf-8")
+}
+
+/// 启动以内存来源为后端的本地 HTTP 替身服务，返回基础地址
+fn serve_memory_source(source: MemorySource, prefix: &'static str) -> String {
+  let listener = TcpListener::bind("127.0.0.1:0").expect("bind stand-in server");
+  let addr = listener.local_addr().expect("get stand-in server address");
+
+  thread::spawn(move || {
+    for stream in listener.incoming() {
+      let Ok(mut stream) = stream else {
+        continue;
+      };
+
+      let mut request_line = String::new();
+      let mut reader = BufReader::new(&stream);
+      if reader.read_line(&mut request_line).is_err() {
+        continue;
+      }
+      let mut header = String::new();
+      while reader.read_line(&mut header).is_ok_and(|read| read > 2) {
+        header.clear();
+      }
+
+      let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+      // `/legacy` 下的请求原样重定向到真实前缀
+      if let Some(path) = target.strip_prefix(LEGACY_PREFIX) {
+        let response =
+          format!("HTTP/1.0 301 Moved Permanently\r\nLocation: {prefix}{path}\r\n\r\n");
+        let _ = stream.write_all(response.as_bytes());
+        continue;
+      }
+      let target = decode_path(target);
+      let body = target.strip_prefix(prefix).and_then(|path| {
+        if path.ends_with('/') {
+          source
+            .list(path)
+            .expect("list memory source")
+            .map(|names| names.join("\n"))
+        } else {
+          source.read(path).expect("read memory source")
+        }
+      });
+
+      let response = match body {
+        Some(body) => format!(
+          "HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n{body}",
+          body.len()
+        ),
+        None => "HTTP/1.0 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+      };
+      let _ = stream.write_all(response.as_bytes());
+    }
+  });
+
+  format!("http://{addr}{prefix}")
+}
+
+/// 启动读完请求头后交给 `respond` 处理的本地 HTTP 替身服务，返回基础地址
+fn serve_raw(respond: impl Fn(TcpStream) + Send + 'static) -> String {
+  let listener = TcpListener::bind("127.0.0.1:0").expect("bind stand-in server");
+  let addr = listener.local_addr().expect("get stand-in server address");
+
+  thread::spawn(move || {
+    for stream in listener.incoming() {
+      let Ok(stream) = stream else {
+        continue;
+      };
+      let mut reader = BufReader::new(&stream);
+      let mut line = String::new();
+      while reader.read_line(&mut line).is_ok_and(|read| read > 2) {
+        line.clear();
+      }
+      respond(stream);
+    }
+  });
+
+  format!("http://{addr}/config")
+}
@@ -0,0 +1,218 @@
+use bodhi_config::prelude::*;
+use bodhi_config::typed::{FieldIssue, FieldSpec, check_struct_fields};
+use bodhi_config::validate::expand_optional_entries;
+
+const SCHEMA: &str = r#"
+[server]
+http_port = 8080
+grpc_port = 50051
+hosts = ["a", "b"]
+
+[routes]
+prefix = "/api/v1"
+"#;
+
+fn field(name: &str, rust_type: &str, optional: bool) -> FieldSpec {
+  FieldSpec {
+    name: name.to_string(),
+    rust_type: rust_type.to_string(),
+    optional,
+    nested: false,
+  }
+}
+
+fn nested_field(name: &str, rust_type: &str) -> FieldSpec {
+  FieldSpec {
+    nested: true,
+    ..field(name, rust_type, false)
+  }
+}
+
+fn schema() -> toml::Value {
+  toml::from_str(SCHEMA).expect("parse schema")
+}
+
+#[test]
+fn matching_struct_should_have_no_issues() {
+  let fields = [
+    field("http_port", "u16", false),
+    field("grpc_port", "Option < u32 >", false),
+    field("hosts", "Vec < String >", false),
+    field("timeout_ms", "Option < u64 >", true),
+  ];
+
+  let issues = check_struct_fields(&schema(), "server", &fields, false).expect("check fields");
+  assert!(issues.is_empty(), "unexpected issues: {issues:?}");
+}
+
+#[test]
+fn mismatched_struct_should_report_every_issue() {
+  let fields = [
+    field("http_port", "u8", false),
+    field("hosts", "Vec < u32 >", false),
+    field("timeout_ms", "u64", false),
+  ];
+
+  let issues = check_struct_fields(&schema(), "server", &fields, false).expect("check fields");
+  assert_eq!(issues.len(), 4, "unexpected issues: {issues:?}");
+  assert!(matches!(
+    &issues[0],
+    FieldIssue::Incompatible { field, reason, .. }
+      if field == "http_port" && reason == "8080 is out of range for u8"
+  ));
+  assert!(matches!(
+    &issues[1],
+    FieldIssue::Incompatible { field, reason, .. }
+      if field == "hosts" && reason == "item 0: expected integer, found string"
+  ));
+  assert_eq!(
+    issues[2],
+    FieldIssue::Extra {
+      field: "timeout_ms".to_string(),
+      path: "server.timeout_ms".to_string(),
+    }
+  );
+  assert_eq!(
+    issues[3],
+    FieldIssue::Missing {
+      path: "server.grpc_port".to_string(),
+    }
+  );
+}
+
+#[test]
+fn flattened_struct_should_allow_unlisted_fields() {
+  let fields = [field("prefix", "String", false)];
+  let issues = check_struct_fields(&schema(), ".", &fields, true).expect("check fields");
+  assert_eq!(
+    issues,
+    [FieldIssue::Extra {
+      field: "prefix".to_string(),
+      path: "prefix".to_string(),
+    }]
+  );
+
+  let issues = check_struct_fields(&schema(), "routes", &fields, true).expect("check fields");
+  assert!(issues.is_empty());
+}
+
+#[test]
+fn invalid_bound_path_should_fail() {
+  let err = check_struct_fields(&schema(), "server.missing", &[], false)
+    .expect_err("missing path should fail");
+  assert_eq!(err.code(), CONFIGERR_INVALIDPATH);
+
+  let err = check_struct_fields(&schema(), "server.http_port", &[], false)
+    .expect_err("non-table path should fail");
+  assert_eq!(err.code(), CONFIGERR_TYPEMISMATCH);
+}
+
+#[test]
+fn optional_template_field_should_require_optional_struct_field() {
+  let schema: toml::Value = toml::from_str(
+    "[server]\n__optional = [\"tls\"]\nhttp_port = 80\n\n[server.tls]\ncert = \"a.pem\"\n",
+  )
+  .expect("parse schema");
+
+  let fields = [
+    field("http_port", "u16", false),
+    field("tls", "Option < TlsConfig >", true),
+  ];
+  let issues = check_struct_fields(&schema, "server", &fields, false).expect("check fields");
+  assert!(issues.is_empty(), "unexpected issues: {issues:?}");
+
+  let fields = [
+    field("http_port", "u16", false),
+    field("tls", "TlsConfig", false),
+  ];
+  let issues = check_struct_fields(&schema, "server", &fields, false).expect("check fields");
+  assert!(matches!(
+    issues.as_slice(),
+    [FieldIssue::Incompatible { field, .. }] if field == "tls"
+  ));
+}
+
+#[test]
+fn field_markers_should_check_declared_type() {
+  let mut schema: toml::Value = toml::from_str(concat!(
+    "[upstream]\n",
+    "endpoint = { __kind = \"required\", type = \"string\" }\n",
+    "__optional = [{ name = \"timeout_ms\", type = \"integer\" }]\n",
+  ))
+  .expect("parse schema");
+  expand_optional_entries(&mut schema, "template").expect("expand optional entries");
+
+  let fields = [
+    field("endpoint", "String", false),
+    field("timeout_ms", "Option < u64 >", true),
+  ];
+  let issues = check_struct_fields(&schema, "upstream", &fields, false).expect("check fields");
+  assert!(issues.is_empty(), "unexpected issues: {issues:?}");
+
+  let fields = [
+    field("endpoint", "u16", false),
+    field("timeout_ms", "u64", false),
+  ];
+  let issues = check_struct_fields(&schema, "upstream", &fields, false).expect("check fields");
+  assert_eq!(issues.len(), 2, "unexpected issues: {issues:?}");
+}
+
+#[test]
+fn struct_types_should_require_table_values() {
+  let schema: toml::Value = toml::from_str(concat!(
+    "[server]\n",
+    "tls = 443\n",
+    "mode = \"active\"\n",
+    "bind = \"0.0.0.0:80\"\n",
+    "pair = [1, \"a\"]\n",
+    "\n",
+    "[server.limits]\n",
+    "max_conn = 10\n",
+  ))
+  .expect("parse schema");
+
+  let fields = [
+    field("tls", "TlsConfig", false),
+    field("mode", "Mode", false),
+    field("bind", "SocketAddr", false),
+    field("pair", "(u8 , String)", false),
+    nested_field("limits", "LimitsConfig"),
+  ];
+  let issues = check_struct_fields(&schema, "server", &fields, false).expect("check fields");
+  assert!(
+    matches!(
+      issues.as_slice(),
+      [FieldIssue::Incompatible { field, reason, .. }]
+        if field == "tls" && reason == "expected table or string for TlsConfig, found integer"
+    ),
+    "unexpected issues: {issues:?}"
+  );
+
+  let fields = [
+    nested_field("tls", "TlsConfig"),
+    field("mode", "Mode", false),
+    field("bind", "u16", false),
+    field("pair", "(u8 , u8)", false),
+    field("limits", "Vec < u32 >", false),
+  ];
+  let issues = check_struct_fields(&schema, "server", &fields, false).expect("check fields");
+  let reasons: Vec<_> = issues
+    .iter()
+    .filter_map(|issue| match issue {
+      FieldIssue::Incompatible { field, reason, .. } => Some((field.as_str(), reason.as_str())),
+      _ => None,
+    })
+    .collect();
+  assert_eq!(
+    reasons,
+    [
+      (
+        "tls",
+        "expected table for nested BodhiConfig struct, found integer"
+      ),
+      ("bind", "expected integer, found string"),
+      ("pair", "item 1: expected integer, found string"),
+      ("limits", "expected array, found table"),
+    ]
+  );
+}
@@ -0,0 +1,244 @@
+//! # Bodhi 配置构建脚本辅助模块
+//!
+//! 在服务的 `build.rs` 中调用 [`generate_service_config`]，按模板生成 `OUT_DIR/config.rs`，
+//! 供 `service_config!` 引入。
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bodhi_config::prelude::*;
+use bodhi_config::source::{FsSource, RecordingSource};
+use toml::Value;
+
+/// 覆盖配置目录位置的环境变量，相对路径基于 workspace 根目录
+pub const CONFIG_DIR_ENV: &str = "BODHI_CONFIG_DIR";
+
+/// 默认的类型覆盖规则文件名，位于配置目录下
+pub const TYPE_OVERRIDES_FILE: &str = "type_overrides.toml";
+
+/// 生成的 Rust 配置结构文件名
+pub const OUTPUT_FILE: &str = "config.rs";
+
+/// 在 `build.rs` 中一行完成服务配置结构生成，失败时中止构建
+pub fn generate_service_config() {
+  let result = ServiceConfigBuild::from_env().and_then(|build| build.generate());
+  match result {
+    Ok(outcome) => outcome.emit_cargo_directives(),
+    Err(err) => panic!("bodhi_config_build failed: {err}"),
+  }
+}
+
+/// 服务配置结构生成任务
+#[derive(Clone, Debug)]
+pub struct ServiceConfigBuild {
+  manifest_dir: PathBuf,
+  out_dir: PathBuf,
+  service: String,
+  config_dir: Option<PathBuf>,
+  type_overrides: Option<PathBuf>,
+}
+
+impl ServiceConfigBuild {
+  /// 创建生成任务
+  pub fn new(
+    manifest_dir: impl AsRef<Path>,
+    out_dir: impl AsRef<Path>,
+    service: impl Into<String>,
+  ) -> Self {
+    Self {
+      manifest_dir: manifest_dir.as_ref().to_path_buf(),
+      out_dir: out_dir.as_ref().to_path_buf(),
+      service: service.into(),
+      config_dir: None,
+      type_overrides: None,
+    }
+  }
+
+  /// 从 cargo 提供给构建脚本的环境变量创建生成任务
+  pub fn from_env() -> Result<Self> {
+    let manifest_dir = cargo_env("CARGO_MANIFEST_DIR")?;
+    let out_dir = cargo_env("OUT_DIR")?;
+    let service = cargo_env("CARGO_PKG_NAME")?;
+    let mut build = Self::new(manifest_dir, out_dir, service);
+    build.config_dir = env::var_os(CONFIG_DIR_ENV).map(PathBuf::from);
+    Ok(build)
+  }
+
+  /// 指定服务名，默认使用包名
+  pub fn service(mut self, service: impl Into<String>) -> Self {
+    self.service = service.into();
+    self
+  }
+
+  /// 指定配置目录，相对路径基于 workspace 根目录
+  pub fn config_dir(mut self, config_dir: impl AsRef<Path>) -> Self {
+    self.config_dir = Some(config_dir.as_ref().to_path_buf());
+    self
+  }
+
+  /// 指定类型覆盖规则文件，默认使用配置目录下的 `type_overrides.toml`
+  pub fn type_overrides(mut self, path: impl AsRef<Path>) -> Self {
+    self.type_overrides = Some(path.as_ref().to_path_buf());
+    self
+  }
+
+  /// 生成配置结构，内容未变化时不改写输出文件
+  pub fn generate(&self) -> Result<BuildOutcome> {
+    let workspace_root = find_workspace_root(&self.manifest_dir)?;
+    let config_dir = match &self.config_dir {
+      Some(config_dir) => workspace_root.join(config_dir),
+      None => workspace_root.join("config"),
+    };
+    ensure_dir(&config_dir)?;
+
+    let mut watched = BTreeSet::new();
+    let type_overrides_path = self
+      .type_overrides
+      .as_ref()
+      .map(|path| workspace_root.join(path))
+      .unwrap_or_else(|| config_dir.join(TYPE_OVERRIDES_FILE));
+    let type_overrides = if type_overrides_path.is_file() {
+      watched.insert(type_overrides_path.clone());
+      TypeOverrideRules::from_file(&type_overrides_path)?
+    } else if self.type_overrides.is_some() {
+      return Err(
+        Error::new(CONFIGERR_FILELOADFAILED)
+          .wrap_context("type override rules file not found")
+          .wrap_context_with(|| format!("path={}", type_overrides_path.display())),
+      );
+    } else {
+      watched.insert(watch_missing(&config_dir, &type_overrides_path));
+      TypeOverrideRules::default()
+    };
+
+    let source = Arc::new(RecordingSource::new(FsSource::new(&config_dir)));
+    let engine = ConfigEngine::from_shared_source(source.clone());
+    let options = RustCodegenOptions {
+      type_overrides,
+      ..RustCodegenOptions::default()
+    };
+    let content = engine
+      .render_service_rust_types_with(&self.service, &options)
+      .wrap_context_with(|| format!("service={}", self.service))?;
+    let touched = source.listed_dirs().into_iter().chain(source.read_files());
+    watched.extend(touched.map(|path| config_dir.join(path)));
+    watched.extend(
+      source
+        .missing_files()
+        .into_iter()
+        .map(|path| watch_missing(&config_dir, &config_dir.join(path))),
+    );
+
+    let output_path = self.out_dir.join(OUTPUT_FILE);
+    let written = write_if_changed(&output_path, &content)?;
+
+    Ok(BuildOutcome {
+      output_path,
+      written,
+      watched: watched.into_iter().collect(),
+    })
+  }
+}
+
+/// 生成结果
+#[derive(Clone, Debug)]
+pub struct BuildOutcome {
+  /// 生成文件路径
+  pub output_path: PathBuf,
+  /// 本次是否改写了生成文件
+  pub written: bool,
+  /// 生成过程中读取过的文件和目录
+  pub watched: Vec<PathBuf>,
+}
+
+impl BuildOutcome {
+  /// 渲染 cargo 构建指令
+  pub fn cargo_directives(&self) -> Vec<String> {
+    let mut directives: Vec<_> = self
+      .watched
+      .iter()
+      .map(|path| format!("cargo:rerun-if-changed={}", path.display()))
+      .collect();
+    directives.push(format!("cargo:rerun-if-env-changed={CONFIG_DIR_ENV}"));
+    directives
+  }
+
+  /// 输出 cargo 构建指令
+  pub fn emit_cargo_directives(&self) {
+    for directive in self.cargo_directives() {
+      println!("{directive}");
+    }
+  }
+}
+
+/// 从指定目录向上查找声明了 `[workspace]` 的 `Cargo.toml`
+pub fn find_workspace_root(start: &Path) -> Result<PathBuf> {
+  for dir in start.ancestors() {
+    let cargo_toml = dir.join("Cargo.toml");
+    if !cargo_toml.is_file() {
+      continue;
+    }
+
+    let content = fs::read_to_string(&cargo_toml)
+      .map_err(Error::from_std)
+      .wrap_context("read Cargo.toml failed")
+      .wrap_context_with(|| format!("path={}", cargo_toml.display()))?;
+    let manifest: Value = toml::from_str(&content)
+      .map_err(Error::from_std)
+      .wrap_context("parse Cargo.toml failed")
+      .wrap_context_with(|| format!("path={}", cargo_toml.display()))?;
+    if manifest.get("workspace").is_some_and(Value::is_table) {
+      return Ok(dir.to_path_buf());
+    }
+  }
+
+  Err(
+    Error::new(CONFIGERR_CONFIGDIRNOTFOUND)
+      .wrap_context("cannot find workspace root (Cargo.toml with [workspace])")
+      .wrap_context_with(|| format!("start={}", start.display())),
+  )
+}
+
+/// 尚不存在的可选文件改为监听最近的已存在目录
+///
+/// cargo 会把不存在的 `rerun-if-changed` 路径视为始终过期，直接监听文件会让构建脚本每次都重跑；
+/// 监听目录时 cargo 会递归比较修改时间，文件被创建后即可触发重新生成。
+fn watch_missing(config_dir: &Path, path: &Path) -> PathBuf {
+  path
+    .ancestors()
+    .skip(1)
+    .take_while(|dir| dir.starts_with(config_dir))
+    .find(|dir| dir.is_dir())
+    .unwrap_or(config_dir)
+    .to_path_buf()
+}
+
+fn write_if_changed(path: &Path, content: &str) -> Result<bool> {
+  if fs::read_to_string(path).is_ok_and(|existing| existing == content) {
+    return Ok(false);
+  }
+
+  bodhi_config::codegen::write_rust_types(path, content)?;
+  Ok(true)
+}
+
+fn ensure_dir(dir: &Path) -> Result<()> {
+  if dir.is_dir() {
+    Ok(())
+  } else {
+    Err(
+      Error::new(CONFIGERR_CONFIGDIRNOTFOUND)
+        .wrap_context_with(|| format!("config_dir={} not found", dir.display())),
+    )
+  }
+}
+
+fn cargo_env(key: &str) -> Result<String> {
+  env::var(key)
+    .map_err(Error::from_std)
+    .wrap_context("read cargo build environment failed")
+    .wrap_context_with(|| format!("key={key}"))
+}
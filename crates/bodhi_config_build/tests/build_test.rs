@@ -0,0 +1,178 @@
+use std::fs;
+use std::path::Path;
+
+use bodhi_config_build::{ServiceConfigBuild, find_workspace_root};
+use tempfile::tempdir;
+
+#[test]
+fn build_should_generate_types_with_overrides_and_precise_watch_list() {
+  let tempdir = tempdir().expect("create tempdir");
+  let workspace_dir = tempdir.path();
+  let manifest_dir = workspace_dir.join("app/gateway");
+  let out_dir = workspace_dir.join("out");
+  write_build_test_workspace(workspace_dir, &manifest_dir);
+
+  let outcome = ServiceConfigBuild::new(&manifest_dir, &out_dir, "gateway")
+    .generate()
+    .expect("generate service config");
+
+  assert!(outcome.written);
+  let content = fs::read_to_string(&outcome.output_path).expect("read generated config");
+  assert!(content.contains("pub http_port: u16"));
+  assert!(content.contains("pub request_timeout_ms: u32"));
+
+  let directives = outcome.cargo_directives();
+  let config_dir = workspace_dir.join("config");
+  for watched in [
+    "template/infra",
+    "template/infra/log.toml",
+    "template/service/gateway.toml",
+    "type_overrides.toml",
+  ] {
+    let directive = format!(
+      "cargo:rerun-if-changed={}",
+      config_dir.join(watched).display()
+    );
+    assert!(directives.contains(&directive), "missing {directive}");
+  }
+  assert!(
+    !directives
+      .iter()
+      .any(|directive| directive.contains("profile")),
+    "profiles do not affect generated types"
+  );
+  assert!(directives.contains(&"cargo:rerun-if-env-changed=BODHI_CONFIG_DIR".to_string()));
+}
+
+#[test]
+fn build_should_not_rewrite_unchanged_output() {
+  let tempdir = tempdir().expect("create tempdir");
+  let workspace_dir = tempdir.path();
+  let manifest_dir = workspace_dir.join("app/gateway");
+  let out_dir = workspace_dir.join("out");
+  write_build_test_workspace(workspace_dir, &manifest_dir);
+
+  let build = ServiceConfigBuild::new(&manifest_dir, &out_dir, "gateway");
+  let first = build.generate().expect("first generate");
+  let modified = fs::metadata(&first.output_path)
+    .and_then(|metadata| metadata.modified())
+    .expect("read first mtime");
+
+  let second = build.generate().expect("second generate");
+  assert!(!second.written);
+  assert_eq!(
+    fs::metadata(&second.output_path)
+      .and_then(|metadata| metadata.modified())
+      .expect("read second mtime"),
+    modified
+  );
+
+  fs::write(
+    workspace_dir.join("config/template/service/gateway.toml"),
+    "[server]\nhttp_port = 80\nhost = \"0.0.0.0\"\n[client]\nrequest_timeout_ms = 10\n",
+  )
+  .expect("update gateway template");
+  let third = build.generate().expect("third generate");
+  assert!(third.written);
+  assert!(
+    fs::read_to_string(&third.output_path)
+      .expect("read regenerated config")
+      .contains("pub host: String")
+  );
+}
+
+#[test]
+fn build_should_watch_optional_files_before_they_exist() {
+  let tempdir = tempdir().expect("create tempdir");
+  let workspace_dir = tempdir.path();
+  let manifest_dir = workspace_dir.join("app/gateway");
+  let out_dir = workspace_dir.join("out");
+  write_build_test_workspace(workspace_dir, &manifest_dir);
+  let config_dir = workspace_dir.join("config");
+  fs::remove_file(config_dir.join("type_overrides.toml")).expect("remove type overrides");
+
+  let build = ServiceConfigBuild::new(&manifest_dir, &out_dir, "gateway");
+  let first = build.generate().expect("generate without type overrides");
+  assert!(
+    !fs::read_to_string(&first.output_path)
+      .expect("read generated config")
+      .contains("pub request_timeout_ms: u32")
+  );
+
+  // 不存在的文件会让 cargo 每次重跑构建脚本，改为监听所在目录
+  let directives = first.cargo_directives();
+  let missing = format!(
+    "cargo:rerun-if-changed={}",
+    config_dir.join("type_overrides.toml").display()
+  );
+  assert!(!directives.contains(&missing));
+  assert!(
+    directives.contains(&format!("cargo:rerun-if-changed={}", config_dir.display())),
+    "missing config dir watch in {directives:?}"
+  );
+  assert!(first.watched.iter().all(|path| path.exists()));
+
+  fs::write(
+    config_dir.join("type_overrides.toml"),
+    "[suffix_types]\ntimeout_ms = \"u32\"\n",
+  )
+  .expect("write type overrides");
+  let second = build.generate().expect("generate with type overrides");
+  assert!(second.written);
+  assert!(
+    fs::read_to_string(&second.output_path)
+      .expect("read regenerated config")
+      .contains("pub request_timeout_ms: u32")
+  );
+  assert!(second.cargo_directives().contains(&missing));
+}
+
+#[test]
+fn find_workspace_root_should_require_workspace_table() {
+  let tempdir = tempdir().expect("create tempdir");
+  let workspace_dir = tempdir.path();
+  let manifest_dir = workspace_dir.join("app/gateway");
+  write_build_test_workspace(workspace_dir, &manifest_dir);
+
+  // 注释中的 [workspace] 不应被误判
+  fs::write(
+    manifest_dir.join("Cargo.toml"),
+    "# [workspace]\n[package]\nname = \"gateway\"\n",
+  )
+  .expect("write package manifest");
+
+  assert_eq!(
+    find_workspace_root(&manifest_dir).expect("find workspace root"),
+    workspace_dir
+  );
+}
+
+fn write_build_test_workspace(workspace_dir: &Path, manifest_dir: &Path) {
+  let config_dir = workspace_dir.join("config");
+  fs::create_dir_all(config_dir.join("template/infra")).expect("create template infra dir");
+  fs::create_dir_all(config_dir.join("template/service")).expect("create template service dir");
+  fs::create_dir_all(config_dir.join("profile")).expect("create profile dir");
+  fs::create_dir_all(manifest_dir).expect("create manifest dir");
+
+  fs::write(
+    workspace_dir.join("Cargo.toml"),
+    "[workspace]\nmembers = [\"app/*\"]\n",
+  )
+  .expect("write workspace manifest");
+  fs::write(
+    config_dir.join("template/infra/log.toml"),
+    "[log]\nlevel = \"INFO\"\n",
+  )
+  .expect("write infra log");
+  fs::write(
+    config_dir.join("template/service/gateway.toml"),
+    "[server]\nhttp_port = 80\n[client]\nrequest_timeout_ms = 10\n",
+  )
+  .expect("write gateway template");
+  fs::write(config_dir.join("profile/dev.toml"), "").expect("write dev profile");
+  fs::write(
+    config_dir.join("type_overrides.toml"),
+    "[suffix_types]\ntimeout_ms = \"u32\"\n",
+  )
+  .expect("write type overrides");
+}
@@ -0,0 +1,285 @@
+//! # Bodhi 配置派生宏
+//!
+//! `#[derive(BodhiConfig)]` 在编译期解析服务配置结构，比对手写结构与模板，
+//! 发现缺失字段、多余字段或类型不兼容时中止编译。
+
+mod serde_attr;
+
+use std::env;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use bodhi_config::ConfigEngine;
+use bodhi_config::loader::find_config_dir;
+use bodhi_config::source::{FsSource, RecordingSource};
+use bodhi_config::typed::{FieldIssue, FieldSpec, check_struct_fields};
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{ToTokens, quote};
+use syn::{Data, DeriveInput, Fields, LitStr, parse_macro_input};
+
+use crate::serde_attr::{FieldAttrs, RenameRule};
+
+/// 把手写配置结构绑定到服务模板路径
+///
+/// `#[bodhi(service = "gateway", path = "server", config_dir = "config")]`，其中 `path` 默认为根，
+/// `config_dir` 默认从当前 crate 向上查找 `config`。字段类型同样派生了 `BodhiConfig` 时标注
+/// `#[bodhi(nested)]`，该结构须绑定到字段所在路径，其字段由自身的派生检查。
+#[proc_macro_derive(BodhiConfig, attributes(bodhi))]
+pub fn derive_bodhi_config(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  expand(&input)
+    .unwrap_or_else(syn::Error::into_compile_error)
+    .into()
+}
+
+struct BindingArgs {
+  service: LitStr,
+  path: LitStr,
+  config_dir: LitStr,
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+  let binding = parse_binding(input)?;
+  let Data::Struct(data) = &input.data else {
+    return Err(syn::Error::new_spanned(
+      &input.ident,
+      "BodhiConfig can only be derived for structs with named fields",
+    ));
+  };
+  let Fields::Named(named) = &data.fields else {
+    return Err(syn::Error::new_spanned(
+      &input.ident,
+      "BodhiConfig can only be derived for structs with named fields",
+    ));
+  };
+
+  let rename_rule = RenameRule::from_attrs(&input.attrs)?;
+  let mut specs = Vec::new();
+  let mut spans = Vec::new();
+  let mut flatten = false;
+  let mut nested_fields = Vec::new();
+  for field in &named.named {
+    let attrs = FieldAttrs::from_attrs(&field.attrs)?;
+    if attrs.skip {
+      continue;
+    }
+    if attrs.flatten {
+      flatten = true;
+      continue;
+    }
+
+    let ident = field.ident.as_ref().expect("named field");
+    let name = attrs
+      .rename
+      .unwrap_or_else(|| rename_rule.apply(&ident.to_string()));
+    let rust_type = field.ty.to_token_stream().to_string();
+    let nested = parse_nested(field)?;
+    if nested {
+      nested_fields.push((
+        option_inner(&field.ty).clone(),
+        join_path(&binding.path.value(), &name),
+      ));
+    }
+    specs.push(FieldSpec {
+      name,
+      optional: attrs.default || is_option(&field.ty),
+      rust_type,
+      nested,
+    });
+    spans.push((ident.clone(), field.ty.clone()));
+  }
+
+  let (schema, files) = resolve_schema(&binding)?;
+  let issues = check_struct_fields(&schema, &binding.path.value(), &specs, flatten)
+    .map_err(|err| syn::Error::new(binding.path.span(), err.to_string()))?;
+
+  let mut error: Option<syn::Error> = None;
+  for issue in &issues {
+    let next = match issue {
+      FieldIssue::Missing { .. } => syn::Error::new_spanned(&input.ident, issue),
+      FieldIssue::Extra { field, .. } => {
+        let index = specs.iter().position(|spec| &spec.name == field);
+        match index {
+          Some(index) => syn::Error::new_spanned(&spans[index].0, issue),
+          None => syn::Error::new_spanned(&input.ident, issue),
+        }
+      }
+      FieldIssue::Incompatible { field, .. } => {
+        let index = specs.iter().position(|spec| &spec.name == field);
+        match index {
+          Some(index) => syn::Error::new_spanned(&spans[index].1, issue),
+          None => syn::Error::new_spanned(&input.ident, issue),
+        }
+      }
+    };
+    match error.as_mut() {
+      Some(error) => error.combine(next),
+      None => error = Some(next),
+    }
+  }
+  if let Some(error) = error {
+    return Err(error);
+  }
+
+  let ident = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+  let service = &binding.service;
+  let path = &binding.path;
+  let files = files.iter().map(|path| path.display().to_string());
+  let nested_checks = nested_fields.iter().map(|(ty, field_path)| {
+    let message = format!(
+      "nested BodhiConfig field must bind to service {service:?} path {field_path:?}",
+      service = service.value()
+    );
+    quote! {
+      ::core::assert!(
+        ::bodhi_config::typed::same_binding(
+          <#ty as ::bodhi_config::BodhiConfig>::SERVICE,
+          #service
+        ) && ::bodhi_config::typed::same_binding(
+          <#ty as ::bodhi_config::BodhiConfig>::PATH,
+          #field_path
+        ),
+        #message
+      );
+    }
+  });
+
+  Ok(quote! {
+    impl #impl_generics ::bodhi_config::BodhiConfig for #ident #ty_generics #where_clause {
+      const SERVICE: &'static str = #service;
+      const PATH: &'static str = #path;
+    }
+
+    // 引用模板文件，模板变更时触发重新编译
+    const _: () = {
+      #(const _: &[u8] = ::core::include_bytes!(#files);)*
+    };
+
+    // 嵌套结构由自身的派生检查字段，这里只校验其绑定到字段路径
+    const _: () = {
+      #(#nested_checks)*
+    };
+  })
+}
+
+fn parse_binding(input: &DeriveInput) -> syn::Result<BindingArgs> {
+  let mut service = None;
+  let mut path = None;
+  let mut config_dir = None;
+
+  for attr in input
+    .attrs
+    .iter()
+    .filter(|attr| attr.path().is_ident("bodhi"))
+  {
+    attr.parse_nested_meta(|meta| {
+      let slot = if meta.path.is_ident("service") {
+        &mut service
+      } else if meta.path.is_ident("path") {
+        &mut path
+      } else if meta.path.is_ident("config_dir") {
+        &mut config_dir
+      } else {
+        return Err(meta.error("unknown bodhi attribute, expected service, path or config_dir"));
+      };
+      if slot.is_some() {
+        return Err(meta.error("duplicate bodhi attribute"));
+      }
+      *slot = Some(meta.value()?.parse::<LitStr>()?);
+      Ok(())
+    })?;
+  }
+
+  let service = service.ok_or_else(|| {
+    syn::Error::new_spanned(
+      &input.ident,
+      "missing #[bodhi(service = \"...\")] on BodhiConfig struct",
+    )
+  })?;
+  Ok(BindingArgs {
+    service,
+    path: path.unwrap_or_else(|| LitStr::new("", Span::call_site())),
+    config_dir: config_dir.unwrap_or_else(|| LitStr::new("config", Span::call_site())),
+  })
+}
+
+fn resolve_schema(binding: &BindingArgs) -> syn::Result<(bodhi_config::toml::Value, Vec<PathBuf>)> {
+  let span = binding.config_dir.span();
+  let manifest_dir = env::var("CARGO_MANIFEST_DIR")
+    .map(PathBuf::from)
+    .map_err(|err| syn::Error::new(span, format!("read CARGO_MANIFEST_DIR failed: {err}")))?;
+  let config_dir = find_config_dir(&manifest_dir, binding.config_dir.value().as_ref())
+    .map_err(|err| syn::Error::new(span, err.to_string()))?;
+
+  let source = Arc::new(RecordingSource::new(FsSource::new(&config_dir)));
+  let engine = ConfigEngine::from_shared_source(source.clone());
+  let layers = engine
+    .resolve_service_schema_layers(&binding.service.value())
+    .map_err(|err| syn::Error::new(binding.service.span(), err.to_string()))?;
+
+  let files = source
+    .read_files()
+    .into_iter()
+    .map(|path| config_dir.join(path))
+    .collect();
+  Ok((layers.merged().clone(), files))
+}
+
+/// 解析字段上的 `#[bodhi(nested)]`
+fn parse_nested(field: &syn::Field) -> syn::Result<bool> {
+  let mut nested = false;
+  for attr in field
+    .attrs
+    .iter()
+    .filter(|attr| attr.path().is_ident("bodhi"))
+  {
+    attr.parse_nested_meta(|meta| {
+      if !meta.path.is_ident("nested") {
+        return Err(meta.error("unknown bodhi field attribute, expected nested"));
+      }
+      if nested {
+        return Err(meta.error("duplicate bodhi attribute"));
+      }
+      nested = true;
+      Ok(())
+    })?;
+  }
+  Ok(nested)
+}
+
+fn join_path(path: &str, key: &str) -> String {
+  if path.is_empty() || path == "." {
+    key.to_string()
+  } else {
+    format!("{path}.{key}")
+  }
+}
+
+/// 取 `Option<T>` 的内层类型，其他类型原样返回
+fn option_inner(ty: &syn::Type) -> &syn::Type {
+  if let syn::Type::Path(type_path) = ty
+    && type_path.qself.is_none()
+    && let Some(segment) = type_path.path.segments.last()
+    && segment.ident == "Option"
+    && let syn::PathArguments::AngleBracketed(arguments) = &segment.arguments
+    && let Some(syn::GenericArgument::Type(inner)) = arguments.args.first()
+  {
+    return inner;
+  }
+  ty
+}
+
+fn is_option(ty: &syn::Type) -> bool {
+  matches!(
+    ty,
+    syn::Type::Path(type_path)
+      if type_path.qself.is_none()
+        && type_path
+          .path
+          .segments
+          .last()
+          .is_some_and(|segment| segment.ident == "Option")
+  )
+}
@@ -0,0 +1,148 @@
+//! serde 属性解析，仅识别影响键名与可缺省性的部分
+
+use syn::{Attribute, LitStr};
+
+/// 字段级 serde 属性
+#[derive(Default)]
+pub(crate) struct FieldAttrs {
+  pub(crate) rename: Option<String>,
+  pub(crate) default: bool,
+  pub(crate) flatten: bool,
+  pub(crate) skip: bool,
+}
+
+impl FieldAttrs {
+  pub(crate) fn from_attrs(attrs: &[Attribute]) -> syn::Result<Self> {
+    let mut parsed = Self::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+      attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("rename") {
+          if meta.input.peek(syn::Token![=]) {
+            parsed.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+          } else {
+            meta.parse_nested_meta(|nested| {
+              let value = nested.value()?.parse::<LitStr>()?;
+              if nested.path.is_ident("deserialize") {
+                parsed.rename = Some(value.value());
+              }
+              Ok(())
+            })?;
+          }
+        } else if meta.path.is_ident("default") {
+          parsed.default = true;
+          skip_value(&meta)?;
+        } else if meta.path.is_ident("flatten") {
+          parsed.flatten = true;
+        } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+          parsed.skip = true;
+        } else {
+          skip_value(&meta)?;
+        }
+        Ok(())
+      })?;
+    }
+    Ok(parsed)
+  }
+}
+
+/// 结构级 `rename_all` 规则
+#[derive(Clone, Copy, Default)]
+pub(crate) enum RenameRule {
+  #[default]
+  None,
+  Lower,
+  Upper,
+  Camel,
+  Pascal,
+  ScreamingSnake,
+  Kebab,
+  ScreamingKebab,
+}
+
+impl RenameRule {
+  pub(crate) fn from_attrs(attrs: &[Attribute]) -> syn::Result<Self> {
+    let mut rule = Self::None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+      attr.parse_nested_meta(|meta| {
+        if !meta.path.is_ident("rename_all") {
+          return skip_value(&meta);
+        }
+
+        let value = if meta.input.peek(syn::Token![=]) {
+          Some(meta.value()?.parse::<LitStr>()?)
+        } else {
+          let mut value = None;
+          meta.parse_nested_meta(|nested| {
+            let lit = nested.value()?.parse::<LitStr>()?;
+            if nested.path.is_ident("deserialize") {
+              value = Some(lit);
+            }
+            Ok(())
+          })?;
+          value
+        };
+        if let Some(value) = value {
+          rule = Self::parse(&value)?;
+        }
+        Ok(())
+      })?;
+    }
+    Ok(rule)
+  }
+
+  fn parse(value: &LitStr) -> syn::Result<Self> {
+    Ok(match value.value().as_str() {
+      "lowercase" => Self::Lower,
+      "UPPERCASE" => Self::Upper,
+      "camelCase" => Self::Camel,
+      "PascalCase" => Self::Pascal,
+      "snake_case" => Self::None,
+      "SCREAMING_SNAKE_CASE" => Self::ScreamingSnake,
+      "kebab-case" => Self::Kebab,
+      "SCREAMING-KEBAB-CASE" => Self::ScreamingKebab,
+      other => {
+        return Err(syn::Error::new(
+          value.span(),
+          format!("unsupported serde rename_all rule `{other}`"),
+        ));
+      }
+    })
+  }
+
+  /// 把 snake_case 字段名转换为反序列化键名
+  pub(crate) fn apply(self, field: &str) -> String {
+    let field = field.strip_prefix("r#").unwrap_or(field);
+    match self {
+      Self::None => field.to_string(),
+      Self::Lower => field.to_ascii_lowercase(),
+      Self::Upper | Self::ScreamingSnake => field.to_ascii_uppercase(),
+      Self::Kebab => field.replace('_', "-"),
+      Self::ScreamingKebab => field.replace('_', "-").to_ascii_uppercase(),
+      Self::Camel | Self::Pascal => {
+        let mut output = String::with_capacity(field.len());
+        let mut upper = matches!(self, Self::Pascal);
+        for ch in field.chars() {
+          if ch == '_' {
+            upper = true;
+          } else if upper {
+            output.push(ch.to_ascii_uppercase());
+            upper = false;
+          } else {
+            output.push(ch);
+          }
+        }
+        output
+      }
+    }
+  }
+}
+
+/// 跳过不关心的属性值，例如 `default = "path"` 或 `bound(...)`
+fn skip_value(meta: &syn::meta::ParseNestedMeta<'_>) -> syn::Result<()> {
+  if meta.input.peek(syn::Token![=]) {
+    meta.value()?.parse::<syn::Expr>()?;
+  } else if meta.input.peek(syn::token::Paren) {
+    meta.parse_nested_meta(|nested| skip_value(&nested))?;
+  }
+  Ok(())
+}
@@ -0,0 +1,81 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use bodhi_config::prelude::*;
+use bodhi_config_derive::BodhiConfig;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, BodhiConfig)]
+#[bodhi(
+  service = "gateway",
+  path = "server",
+  config_dir = "tests/fixtures/config"
+)]
+struct ServerConfig {
+  http_port: u16,
+  grpc_port: u32,
+  #[serde(default)]
+  read_timeout_ms: u64,
+}
+
+#[derive(Debug, Deserialize, BodhiConfig)]
+#[bodhi(
+  service = "gateway",
+  path = "routes",
+  config_dir = "tests/fixtures/config"
+)]
+struct RoutesConfig {
+  #[serde(rename = "prefix")]
+  path_prefix: String,
+  upstreams: Vec<String>,
+  fallback: Option<String>,
+}
+
+#[derive(Debug, Deserialize, BodhiConfig)]
+#[bodhi(service = "gateway", config_dir = "tests/fixtures/config")]
+struct GatewayConfig {
+  #[bodhi(nested)]
+  server: ServerConfig,
+  #[bodhi(nested)]
+  routes: Option<RoutesConfig>,
+  #[serde(flatten)]
+  infra: BTreeMap<String, bodhi_config::toml::Value>,
+}
+
+#[test]
+fn derived_config_should_bind_to_template_path() {
+  assert_eq!(ServerConfig::SERVICE, "gateway");
+  assert_eq!(ServerConfig::PATH, "server");
+  assert_eq!(RoutesConfig::PATH, "routes");
+}
+
+#[test]
+fn derived_config_should_load_from_engine() {
+  let config_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/config");
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+
+  let server = ServerConfig::load(&engine, "dev").expect("load server config");
+  assert_eq!(server.http_port, 9090);
+  assert_eq!(server.grpc_port, 50051);
+  assert_eq!(server.read_timeout_ms, 0);
+
+  let layers = engine
+    .resolve_layers("dev", "gateway")
+    .expect("resolve gateway layers");
+  let routes = RoutesConfig::from_layers(&layers).expect("extract routes config");
+  assert_eq!(routes.path_prefix, "/api/v1");
+  assert_eq!(routes.upstreams, ["lobby"]);
+  assert_eq!(routes.fallback, None);
+}
+
+#[test]
+fn derived_config_should_load_nested_structs() {
+  let config_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/config");
+  let engine = ConfigEngine::new(&config_dir).expect("create config engine");
+
+  let gateway = GatewayConfig::load(&engine, "dev").expect("load gateway config");
+  assert_eq!(GatewayConfig::PATH, "");
+  assert_eq!(gateway.server.http_port, 9090);
+  assert_eq!(gateway.routes.expect("routes config").upstreams, ["lobby"]);
+  assert!(gateway.infra.contains_key("log"));
+}
@@ -0,0 +1,97 @@
+//! 宏参数解析模块
+
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Ident, Lit, LitBool, LitStr, Token};
+
+/// `key = literal` 形式的宏参数
+pub(crate) struct MacroArg {
+  pub key: Ident,
+  pub value: Lit,
+}
+
+impl Parse for MacroArg {
+  fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+    let key = input.parse()?;
+    input.parse::<Token![=]>()?;
+    let value = input.parse()?;
+    Ok(Self { key, value })
+  }
+}
+
+/// 逗号分隔的宏参数列表
+pub(crate) struct MacroArgs {
+  args: Vec<MacroArg>,
+}
+
+impl Parse for MacroArgs {
+  fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+    let args = Punctuated::<MacroArg, Token![,]>::parse_terminated(input)?;
+    let mut seen: Vec<&MacroArg> = Vec::new();
+    for arg in &args {
+      if seen.iter().any(|prev| prev.key == arg.key) {
+        return Err(syn::Error::new(
+          arg.key.span(),
+          format!("duplicate argument `{}`", arg.key),
+        ));
+      }
+      seen.push(arg);
+    }
+
+    Ok(Self {
+      args: args.into_iter().collect(),
+    })
+  }
+}
+
+impl MacroArgs {
+  /// 取出字符串参数
+  pub fn take_str(&mut self, key: &str) -> syn::Result<Option<LitStr>> {
+    match self.take(key) {
+      None => Ok(None),
+      Some(MacroArg {
+        value: Lit::Str(value),
+        ..
+      }) => Ok(Some(value)),
+      Some(arg) => Err(syn::Error::new_spanned(
+        arg.value,
+        format!("argument `{key}` expects a string literal"),
+      )),
+    }
+  }
+
+  /// 取出布尔参数
+  pub fn take_bool(&mut self, key: &str) -> syn::Result<Option<LitBool>> {
+    match self.take(key) {
+      None => Ok(None),
+      Some(MacroArg {
+        value: Lit::Bool(value),
+        ..
+      }) => Ok(Some(value)),
+      Some(arg) => Err(syn::Error::new_spanned(
+        arg.value,
+        format!("argument `{key}` expects `true` or `false`"),
+      )),
+    }
+  }
+
+  /// 确认所有参数都已被识别
+  pub fn finish(self, macro_name: &str, allowed: &[&str]) -> syn::Result<()> {
+    match self.args.into_iter().next() {
+      None => Ok(()),
+      Some(arg) => Err(syn::Error::new(
+        arg.key.span(),
+        format!(
+          "unknown {macro_name}! argument `{}`, expected one of: {}",
+          arg.key,
+          allowed.join(", ")
+        ),
+      )),
+    }
+  }
+
+  fn take(&mut self, key: &str) -> Option<MacroArg> {
+    let index = self.args.iter().position(|arg| arg.key == key)?;
+    Some(self.args.remove(index))
+  }
+}
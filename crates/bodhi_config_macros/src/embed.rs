@@ -0,0 +1,165 @@
+//! 配置嵌入宏模块
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::LitStr;
+
+use crate::args::MacroArgs;
+
+const EMBED_ARGS: &[&str] = &["dir", "product"];
+const EMBED_EXTENSIONS: &[&str] = &["toml", "json", "yaml"];
+/// 产物元数据旁路文件后缀，与 `bodhi_config::output::PRODUCT_META_SUFFIX` 保持一致
+const PRODUCT_META_SUFFIX: &str = "meta";
+
+pub(crate) fn expand(mut args: MacroArgs) -> syn::Result<TokenStream> {
+  let dir = args.take_str("dir")?;
+  let product = args.take_str("product")?;
+  args.finish("embed_config", EMBED_ARGS)?;
+
+  let dir_span = dir.as_ref().map_or_else(Span::call_site, LitStr::span);
+  let dir = dir.map_or_else(|| "config".to_string(), |dir| dir.value());
+  let config_dir = find_config_dir(&dir).map_err(|err| syn::Error::new(dir_span, err))?;
+
+  let files = match product {
+    Some(profile) => {
+      let product_dir = config_dir
+        .join("product")
+        .join(product_dir(&profile.value()));
+      if !product_dir.is_dir() {
+        return Err(syn::Error::new(
+          profile.span(),
+          format!(
+            "product directory {} not found, generate products before embedding them",
+            product_dir.display()
+          ),
+        ));
+      }
+      let mut files = Vec::new();
+      for extension in EMBED_EXTENSIONS {
+        collect_files(&config_dir, &product_dir.join(extension), &mut files)
+          .map_err(|err| syn::Error::new(profile.span(), err))?;
+      }
+      files
+    }
+    None => {
+      let mut files = Vec::new();
+      collect_files(&config_dir, &config_dir, &mut files)
+        .map_err(|err| syn::Error::new(dir_span, err))?;
+      // 整树嵌入时不携带产物，避免产物与模板互相覆盖
+      files.retain(|(relative, _)| !relative.starts_with("product/"));
+      files
+    }
+  };
+
+  let entries = files.iter().map(|(relative, absolute)| {
+    let absolute = absolute.display().to_string();
+    quote!((#relative, ::core::include_str!(#absolute)))
+  });
+
+  Ok(quote! {
+    ::bodhi_config::EmbeddedSource::new(&[#(#entries),*])
+  })
+}
+
+/// 产物标签对应的目录，与 `bodhi_config::output::product_dir` 保持一致
+fn product_dir(label: &str) -> PathBuf {
+  let mut parts = label.split(',');
+  let mut dir = PathBuf::from(parts.next().unwrap_or_default());
+  let mut parts = parts.peekable();
+  if parts.peek().is_some() {
+    dir.push("dimensions");
+  }
+  for part in parts {
+    dir.extend(part.splitn(2, '='));
+  }
+  dir
+}
+
+/// 从当前 crate 的 manifest 目录向上查找配置目录
+pub(crate) fn find_config_dir(dir: &str) -> Result<PathBuf, String> {
+  let dir = Path::new(dir);
+  if dir.is_absolute() {
+    return if dir.is_dir() {
+      Ok(dir.to_path_buf())
+    } else {
+      Err(format!("config directory {} not found", dir.display()))
+    };
+  }
+
+  let manifest_dir = env::var("CARGO_MANIFEST_DIR")
+    .map(PathBuf::from)
+    .map_err(|err| format!("read CARGO_MANIFEST_DIR failed: {err}"))?;
+  manifest_dir
+    .ancestors()
+    .map(|ancestor| ancestor.join(dir))
+    .find(|candidate| candidate.is_dir())
+    .ok_or_else(|| {
+      format!(
+        "config directory {} not found from {}",
+        dir.display(),
+        manifest_dir.display()
+      )
+    })
+}
+
+fn collect_files(
+  root: &Path,
+  dir: &Path,
+  files: &mut Vec<(String, PathBuf)>,
+) -> Result<(), String> {
+  if !dir.is_dir() {
+    return Ok(());
+  }
+
+  let entries =
+    fs::read_dir(dir).map_err(|err| format!("read directory {} failed: {err}", dir.display()))?;
+  let mut paths = Vec::new();
+  for entry in entries {
+    let entry = entry.map_err(|err| format!("read directory {} failed: {err}", dir.display()))?;
+    paths.push(entry.path());
+  }
+  paths.sort();
+
+  for path in paths {
+    if path.is_dir() {
+      collect_files(root, &path, files)?;
+      continue;
+    }
+
+    if !is_embeddable(&path) {
+      continue;
+    }
+
+    let relative = path
+      .strip_prefix(root)
+      .map_err(|err| format!("resolve relative path {} failed: {err}", path.display()))?;
+    let relative = relative
+      .components()
+      .map(|component| component.as_os_str().to_string_lossy())
+      .collect::<Vec<_>>()
+      .join("/");
+    files.push((relative, path));
+  }
+
+  Ok(())
+}
+
+/// 配置文件及产物元数据旁路文件（例如 `gateway.toml.meta`）可嵌入
+fn is_embeddable(path: &Path) -> bool {
+  let has_extension = |path: &Path| {
+    path
+      .extension()
+      .and_then(|ext| ext.to_str())
+      .is_some_and(|ext| EMBED_EXTENSIONS.contains(&ext))
+  };
+  match path.extension().and_then(|ext| ext.to_str()) {
+    Some(PRODUCT_META_SUFFIX) => path
+      .file_stem()
+      .is_some_and(|stem| has_extension(Path::new(stem))),
+    _ => has_extension(path),
+  }
+}
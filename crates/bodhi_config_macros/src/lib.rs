@@ -1,64 +1,36 @@
-use std::env;
+mod args;
+mod embed;
+mod service;
 
 use proc_macro::TokenStream;
-use proc_macro2::Span;
-use quote::quote;
-use syn::LitStr;
-
+use syn::parse_macro_input;
+
+use crate::args::MacroArgs;
+use crate::service::ServiceConfigInput;
+
+/// 生成服务配置类型和装载函数
+///
+/// 参数均为可选的 `key = literal`：`service`、`config_dir`、`profile_env`、`default_profile`、
+/// `embed`，也兼容只传服务名字面量的写法。
+///
+/// `load_service_config(profile)` 等装载函数按传入的 profile 装载；`load_selected_*` 系列按
+/// `service_profile()` 选出的 profile 装载。
 #[proc_macro]
 pub fn service_config(input: TokenStream) -> TokenStream {
-  let input = input.to_string().trim().to_string();
-
-  let service = if input.is_empty() {
-    match env::var("CARGO_PKG_NAME") {
-      Ok(value) => value,
-      Err(err) => return compile_error(&format!("read CARGO_PKG_NAME failed: {err}")),
-    }
-  } else {
-    match syn::parse_str::<LitStr>(&input) {
-      Ok(value) => value.value(),
-      Err(err) => {
-        return compile_error(&format!(
-          "service_config! expects no arguments or a single string literal: {err}"
-        ));
-      }
-    }
-  };
-
-  let service = LitStr::new(&service, Span::call_site());
-
-  quote! {
-    mod __bodhi_generated_config {
-      include!(concat!(env!("OUT_DIR"), "/config.rs"));
-    }
-
-    pub use __bodhi_generated_config::Config;
-    pub type InfraConfig = __bodhi_generated_config::infra::Config;
-    pub type ServiceConfig = __bodhi_generated_config::service::Config;
-    pub type ServiceConfigStore = ::bodhi_config::ConfigStore<InfraConfig, ServiceConfig>;
-
-    fn load_service_config(profile: &str) -> ::bodhi_config::prelude::Result<Config> {
-      ::bodhi_config::load_config(profile, #service)
-    }
-
-    fn load_infra_config(profile: &str) -> ::bodhi_config::prelude::Result<InfraConfig> {
-      ::bodhi_config::load_layered_config(profile, #service)?.extract_infra(".")
-    }
-
-    fn load_service_layer_config(profile: &str) -> ::bodhi_config::prelude::Result<ServiceConfig> {
-      ::bodhi_config::load_layered_config(profile, #service)?.extract_service(".")
-    }
-
-    fn load_service_config_store(
-      profile: &str,
-    ) -> ::bodhi_config::prelude::Result<ServiceConfigStore> {
-      ::bodhi_config::ConfigStore::<InfraConfig, ServiceConfig>::load(profile, #service)
-    }
-  }
-  .into()
+  let input = parse_macro_input!(input as ServiceConfigInput);
+  service::expand(input)
+    .unwrap_or_else(syn::Error::into_compile_error)
+    .into()
 }
 
-fn compile_error(message: &str) -> TokenStream {
-  let message = LitStr::new(message, Span::call_site());
-  quote!(compile_error!(#message);).into()
+/// 在编译期把配置树嵌入二进制，展开为 `EmbeddedSource`
+///
+/// - `embed_config!()` 嵌入 `config` 目录下除产物外的全部配置
+/// - `embed_config!(dir = "config", product = "dev")` 仅嵌入指定 profile 的产物
+#[proc_macro]
+pub fn embed_config(input: TokenStream) -> TokenStream {
+  let args = parse_macro_input!(input as MacroArgs);
+  embed::expand(args)
+    .unwrap_or_else(syn::Error::into_compile_error)
+    .into()
 }
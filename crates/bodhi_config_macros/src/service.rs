@@ -0,0 +1,238 @@
+//! 服务配置宏模块
+
+use std::env;
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::LitStr;
+use syn::parse::{Parse, ParseStream};
+
+use crate::args::MacroArgs;
+
+const SERVICE_ARGS: &[&str] = &[
+  "service",
+  "config_dir",
+  "profile_env",
+  "default_profile",
+  "embed",
+];
+
+/// `service_config!` 参数，兼容只传服务名字面量的旧写法
+pub(crate) enum ServiceConfigInput {
+  Legacy(LitStr),
+  Args(MacroArgs),
+}
+
+impl Parse for ServiceConfigInput {
+  fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+    if !input.peek(LitStr) {
+      return input.parse().map(Self::Args);
+    }
+
+    let service: LitStr = input.parse()?;
+    if !input.is_empty() {
+      return Err(input.error(
+        "a bare service name cannot be combined with other arguments, use `service = \"...\"`",
+      ));
+    }
+    Ok(Self::Legacy(service))
+  }
+}
+
+struct ServiceConfigOptions {
+  service: LitStr,
+  config_dir: LitStr,
+  profile_env: LitStr,
+  default_profile: LitStr,
+  embed: bool,
+}
+
+impl ServiceConfigOptions {
+  fn from_input(input: ServiceConfigInput) -> syn::Result<Self> {
+    let mut args = match input {
+      ServiceConfigInput::Legacy(service) => {
+        return Ok(Self {
+          service: non_empty(service, "service")?,
+          config_dir: LitStr::new("config", Span::call_site()),
+          profile_env: LitStr::new("BODHI_PROFILE", Span::call_site()),
+          default_profile: LitStr::new("dev", Span::call_site()),
+          embed: false,
+        });
+      }
+      ServiceConfigInput::Args(args) => args,
+    };
+
+    let service = match args.take_str("service")? {
+      Some(service) => non_empty(service, "service")?,
+      None => {
+        let service = env::var("CARGO_PKG_NAME").map_err(|err| {
+          syn::Error::new(
+            Span::call_site(),
+            format!("read CARGO_PKG_NAME failed, pass `service = \"...\"` explicitly: {err}"),
+          )
+        })?;
+        LitStr::new(&service, Span::call_site())
+      }
+    };
+    let config_dir = optional_str(args.take_str("config_dir")?, "config_dir", "config")?;
+    let profile_env = optional_str(
+      args.take_str("profile_env")?,
+      "profile_env",
+      "BODHI_PROFILE",
+    )?;
+    if profile_env.value().contains('=') {
+      return Err(syn::Error::new(
+        profile_env.span(),
+        "`profile_env` must be an environment variable name",
+      ));
+    }
+    let default_profile =
+      optional_str(args.take_str("default_profile")?, "default_profile", "dev")?;
+    let embed = args.take_bool("embed")?.is_some_and(|embed| embed.value);
+    args.finish("service_config", SERVICE_ARGS)?;
+
+    Ok(Self {
+      service,
+      config_dir,
+      profile_env,
+      default_profile,
+      embed,
+    })
+  }
+}
+
+pub(crate) fn expand(input: ServiceConfigInput) -> syn::Result<TokenStream> {
+  let ServiceConfigOptions {
+    service,
+    config_dir,
+    profile_env,
+    default_profile,
+    embed,
+  } = ServiceConfigOptions::from_input(input)?;
+
+  let engine = if embed {
+    quote! {
+      const EMBEDDED: ::bodhi_config::EmbeddedSource =
+        ::bodhi_config::embed_config!(dir = #config_dir);
+      Ok(::bodhi_config::ConfigEngine::embedded(EMBEDDED, #config_dir))
+    }
+  } else {
+    quote! {
+      ::bodhi_config::ConfigEngine::find(#config_dir)
+    }
+  };
+  let store_builder = if embed {
+    quote! {
+      ::bodhi_config::ConfigStore::<InfraConfig, ServiceConfig>::builder(profile, SERVICE_NAME)
+        .engine(service_config_engine()?)
+    }
+  } else {
+    quote! {
+      ::bodhi_config::ConfigStore::<InfraConfig, ServiceConfig>::builder(profile, SERVICE_NAME)
+        .config_dir(#config_dir)
+    }
+  };
+
+  Ok(quote! {
+    mod __bodhi_generated_config {
+      include!(concat!(env!("OUT_DIR"), "/config.rs"));
+    }
+
+    pub use __bodhi_generated_config::Config;
+    pub type InfraConfig = __bodhi_generated_config::infra::Config;
+    pub type ServiceConfig = __bodhi_generated_config::service::Config;
+    pub type ServiceConfigStore = ::bodhi_config::ConfigStore<InfraConfig, ServiceConfig>;
+    pub type ServiceConfigStoreBuilder =
+      ::bodhi_config::ConfigStoreBuilder<InfraConfig, ServiceConfig>;
+
+    /// 当前服务名
+    pub const SERVICE_NAME: &str = #service;
+
+    /// 按命令行 `--profile`、环境变量、默认值的顺序选择 profile
+    #[allow(dead_code)]
+    fn service_profile() -> ::std::string::String {
+      ::bodhi_config::select_profile(#profile_env, #default_profile)
+    }
+
+    #[allow(dead_code)]
+    fn service_config_engine() -> ::bodhi_config::prelude::Result<::bodhi_config::ConfigEngine> {
+      #engine
+    }
+
+    #[allow(dead_code)]
+    fn load_service_config(profile: &str) -> ::bodhi_config::prelude::Result<Config> {
+      service_config_engine()?.resolve(profile, SERVICE_NAME)?.extract(".")
+    }
+
+    /// 按 `service_profile()` 选出的 profile 装载配置
+    #[allow(dead_code)]
+    fn load_selected_service_config() -> ::bodhi_config::prelude::Result<Config> {
+      load_service_config(&service_profile())
+    }
+
+    #[allow(dead_code)]
+    fn load_infra_config(profile: &str) -> ::bodhi_config::prelude::Result<InfraConfig> {
+      service_config_engine()?
+        .resolve_layers(profile, SERVICE_NAME)?
+        .extract_infra(".")
+    }
+
+    #[allow(dead_code)]
+    fn load_selected_infra_config() -> ::bodhi_config::prelude::Result<InfraConfig> {
+      load_infra_config(&service_profile())
+    }
+
+    #[allow(dead_code)]
+    fn load_service_layer_config(profile: &str) -> ::bodhi_config::prelude::Result<ServiceConfig> {
+      service_config_engine()?
+        .resolve_layers(profile, SERVICE_NAME)?
+        .extract_service(".")
+    }
+
+    #[allow(dead_code)]
+    fn load_selected_service_layer_config() -> ::bodhi_config::prelude::Result<ServiceConfig> {
+      load_service_layer_config(&service_profile())
+    }
+
+    #[allow(dead_code)]
+    fn service_config_store_builder(
+      profile: &str,
+    ) -> ::bodhi_config::prelude::Result<ServiceConfigStoreBuilder> {
+      Ok(#store_builder)
+    }
+
+    #[allow(dead_code)]
+    fn selected_service_config_store_builder()
+    -> ::bodhi_config::prelude::Result<ServiceConfigStoreBuilder> {
+      service_config_store_builder(&service_profile())
+    }
+
+    #[allow(dead_code)]
+    fn load_service_config_store(profile: &str) -> ::bodhi_config::prelude::Result<ServiceConfigStore> {
+      service_config_store_builder(profile)?.build()
+    }
+
+    #[allow(dead_code)]
+    fn load_selected_service_config_store() -> ::bodhi_config::prelude::Result<ServiceConfigStore> {
+      selected_service_config_store_builder()?.build()
+    }
+  })
+}
+
+fn non_empty(value: LitStr, key: &str) -> syn::Result<LitStr> {
+  if value.value().trim().is_empty() {
+    Err(syn::Error::new(
+      value.span(),
+      format!("`{key}` must not be empty"),
+    ))
+  } else {
+    Ok(value)
+  }
+}
+
+fn optional_str(value: Option<LitStr>, key: &str, default: &str) -> syn::Result<LitStr> {
+  match value {
+    Some(value) => non_empty(value, key),
+    None => Ok(LitStr::new(default, Span::call_site())),
+  }
+}
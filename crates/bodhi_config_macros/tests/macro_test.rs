@@ -0,0 +1,5 @@
+#[test]
+fn macros_should_reject_invalid_arguments() {
+  let cases = trybuild::TestCases::new();
+  cases.compile_fail("tests/ui/*.rs");
+}
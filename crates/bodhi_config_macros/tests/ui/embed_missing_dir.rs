@@ -0,0 +1,3 @@
+const SOURCE: bodhi_config::EmbeddedSource = bodhi_config::embed_config!(dir = "no_such_config_dir");
+
+fn main() {}
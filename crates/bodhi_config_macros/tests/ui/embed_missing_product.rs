@@ -0,0 +1,4 @@
+const SOURCE: bodhi_config::EmbeddedSource =
+  bodhi_config::embed_config!(dir = "config", product = "no_such_profile");
+
+fn main() {}
@@ -0,0 +1,3 @@
+const SOURCE: bodhi_config::EmbeddedSource = bodhi_config::embed_config!(path = "config");
+
+fn main() {}
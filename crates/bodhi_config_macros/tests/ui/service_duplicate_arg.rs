@@ -0,0 +1,3 @@
+bodhi_config::service_config!(service = "gateway", service = "lobby");
+
+fn main() {}
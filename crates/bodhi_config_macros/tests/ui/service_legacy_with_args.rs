@@ -0,0 +1,3 @@
+bodhi_config::service_config!("gateway", embed = true);
+
+fn main() {}
@@ -0,0 +1,3 @@
+bodhi_config::service_config!(service = "gateway", profile_env = "BODHI_PROFILE=dev");
+
+fn main() {}